@@ -0,0 +1,114 @@
+use crate::math::Vec3;
+use crate::poly_mesh::PolyMesh;
+
+/// Flat parts still get a box this many units thick, since Godot's
+/// physics doesn't handle boxes with a zero extent well.
+pub const MIN_BOX_EXTENT: f32 = 0.01;
+
+/// Which collision shape is generated for a mesh.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum CollisionMode {
+    #[default]
+    None,
+    /// Every face, triangulated. Exact, but only for static bodies.
+    Trimesh,
+    /// The convex hull around every vertex.
+    Convex,
+    /// One box around each connected part of the mesh.
+    Boxes,
+}
+
+/// An axis aligned box, laid out like a Godot `BoxShape` placed at
+/// `center`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct CollisionBox {
+    pub center: Vec3,
+    /// Half the size along each axis.
+    pub extents: Vec3,
+}
+
+/// Triangle soup for a `ConcavePolygonShape`, three positions per
+/// triangle, wound clockwise like the render surfaces.
+pub fn trimesh_faces(mesh: &PolyMesh) -> Vec<Vec3> {
+    let mut faces = Vec::new();
+    for face in 0..mesh.face_count() {
+        for [a, b, c] in mesh.face_triangles(face) {
+            faces.push(mesh.position(a));
+            faces.push(mesh.position(c));
+            faces.push(mesh.position(b));
+        }
+    }
+    faces
+}
+
+/// Points for a `ConvexPolygonShape`, which builds the hull around them.
+/// Vertices no face uses are left out.
+pub fn convex_points(mesh: &PolyMesh) -> Vec<Vec3> {
+    let faces: Vec<_> = (0..mesh.face_count()).collect();
+    let mut vertices = mesh.faces_vertices(&faces);
+    vertices.sort_unstable();
+    vertices.into_iter().map(|vertex| mesh.position(vertex)).collect()
+}
+
+/// One bounding box per connected part, which keeps blockouts made of
+/// several pieces walkable without the cost of a trimesh.
+pub fn collision_boxes(mesh: &PolyMesh) -> Vec<CollisionBox> {
+    mesh.face_islands()
+        .iter()
+        .map(|faces| {
+            let vertices = mesh.faces_vertices(faces);
+            let first = mesh.position(vertices[0]);
+            let (min, max) = vertices
+                .iter()
+                .map(|&vertex| mesh.position(vertex))
+                .fold((first, first), |(min, max), position| (min.min(position), max.max(position)));
+
+            let half = (max - min) / 2.0;
+            CollisionBox {
+                center: (min + max) / 2.0,
+                extents: half.max(Vec3::new(MIN_BOX_EXTENT, MIN_BOX_EXTENT, MIN_BOX_EXTENT) / 2.0),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poly_mesh::tests::cube;
+
+    #[test]
+    fn trimesh_has_three_points_per_triangle() {
+        let mesh = cube();
+        let faces = trimesh_faces(&mesh);
+
+        assert_eq!(faces.len(), 6 * 2 * 3);
+        // First triangle of the front face, wound clockwise
+        assert_eq!(faces[..3], [mesh.position(0), mesh.position(2), mesh.position(3)]);
+    }
+
+    #[test]
+    fn convex_points_skip_unused_vertices() {
+        let mut mesh = cube();
+        mesh.add_vertex(Vec3::new(5.0, 5.0, 5.0));
+        assert_eq!(convex_points(&mesh).len(), 8);
+    }
+
+    #[test]
+    fn boxes_wrap_each_part() {
+        let mut mesh = cube();
+        let ids: Vec<_> = [(3.0, 0.0, 0.0), (5.0, 0.0, 0.0), (5.0, 0.0, -2.0), (3.0, 0.0, -2.0)]
+            .iter()
+            .map(|&(x, y, z)| mesh.add_vertex(Vec3::new(x, y, z)))
+            .collect();
+        mesh.add_face(&[ids[0], ids[3], ids[2], ids[1]]).unwrap();
+
+        let boxes = collision_boxes(&mesh);
+        assert_eq!(boxes.len(), 2);
+        assert_eq!(boxes[0].center, Vec3::new(0.5, 0.5, 0.5));
+        assert_eq!(boxes[0].extents, Vec3::new(0.5, 0.5, 0.5));
+        // The floor quad is flat, so it gets the minimum thickness
+        assert_eq!(boxes[1].center, Vec3::new(4.0, 0.0, -1.0));
+        assert_eq!(boxes[1].extents, Vec3::new(1.0, MIN_BOX_EXTENT / 2.0, 1.0));
+    }
+}
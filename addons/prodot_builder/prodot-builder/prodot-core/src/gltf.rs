@@ -0,0 +1,467 @@
+use crate::json::Json;
+use crate::math::{Color, Transform, Vec3};
+use crate::poly_mesh::PolyMesh;
+use crate::render::{self, SurfaceArrays};
+
+const ARRAY_BUFFER: u32 = 34962;
+const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+const FLOAT: u32 = 5126;
+const UNSIGNED_INT: u32 = 5125;
+
+const GLB_MAGIC: u32 = 0x4654_6C67;
+const GLB_JSON_CHUNK: u32 = 0x4E4F_534A;
+const GLB_BIN_CHUNK: u32 = 0x004E_4942;
+
+/// A metallic-roughness material, as written to the `materials` list.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GltfMaterial {
+    pub name: String,
+    pub base_color: Color,
+    pub metallic: f32,
+    pub roughness: f32,
+}
+
+impl GltfMaterial {
+    /// What slots without a material are exported with, matching a new
+    /// Godot `SpatialMaterial`.
+    pub fn slot_default(slot: usize) -> Self {
+        GltfMaterial {
+            name: format!("material_{}", slot),
+            base_color: Color::white(),
+            metallic: 0.0,
+            roughness: 1.0,
+        }
+    }
+}
+
+/// One node of the exported hierarchy.
+#[derive(Clone, Debug)]
+pub struct GltfNode<'a> {
+    pub name: &'a str,
+    /// Index of the parent node in the list passed to the writer. Nodes
+    /// without one are the roots of the scene.
+    pub parent: Option<usize>,
+    /// Transform relative to the parent.
+    pub transform: Transform,
+    pub mesh: Option<&'a PolyMesh>,
+    /// Material of each slot. Slots past the end get `slot_default`.
+    pub materials: &'a [GltfMaterial],
+}
+
+/// Builds the glTF JSON for `nodes` and the binary buffer its accessors
+/// point into. The buffer isn't referenced by a URI yet, see `write_gltf`
+/// and `write_glb`.
+///
+/// Each mesh gets one primitive per material slot, made of the same render
+/// vertices Godot draws, with positions, normals, tangents, UVs and
+/// vertex colors. Triangles are wound counter-clockwise as glTF expects.
+pub fn build_gltf(nodes: &[GltfNode]) -> (Json, Vec<u8>) {
+    let mut builder = Builder::default();
+    let mut node_json = Vec::with_capacity(nodes.len());
+    let mut meshes = Vec::new();
+
+    for (index, node) in nodes.iter().enumerate() {
+        let mut json = Json::object(vec![("name", Json::from(node.name))]);
+
+        let children: Vec<usize> = (0..nodes.len()).filter(|&child| nodes[child].parent == Some(index)).collect();
+        if !children.is_empty() {
+            json.set("children", Json::from(children));
+        }
+        if node.transform != Transform::identity() {
+            json.set("matrix", matrix(&node.transform));
+        }
+
+        if let Some(mesh) = node.mesh.filter(|mesh| mesh.face_count() > 0) {
+            let primitives: Vec<Json> = render::build_surfaces(mesh)
+                .surfaces
+                .iter()
+                .map(|surface| {
+                    let material = match node.materials.get(surface.material) {
+                        Some(material) => builder.material(material.clone()),
+                        None => builder.material(GltfMaterial::slot_default(surface.material)),
+                    };
+                    builder.primitive(surface, material)
+                })
+                .collect();
+            json.set("mesh", Json::from(meshes.len()));
+            meshes.push(Json::object(vec![
+                ("name", Json::from(node.name)),
+                ("primitives", Json::Array(primitives)),
+            ]));
+        }
+
+        node_json.push(json);
+    }
+
+    let roots: Vec<usize> = (0..nodes.len()).filter(|&node| nodes[node].parent.is_none()).collect();
+    let mut json = Json::object(vec![(
+        "asset",
+        Json::object(vec![("version", Json::from("2.0")), ("generator", Json::from("Prodot Builder"))]),
+    )]);
+    // glTF doesn't allow empty lists, so only the ones in use are written
+    if !node_json.is_empty() {
+        json.set("scene", Json::from(0usize));
+        json.set("scenes", Json::Array(vec![Json::object(vec![("nodes", Json::from(roots))])]));
+        json.set("nodes", Json::Array(node_json));
+    }
+    if !meshes.is_empty() {
+        json.set("meshes", Json::Array(meshes));
+    }
+    if !builder.materials.is_empty() {
+        let materials = builder.materials.iter().map(material_json).collect();
+        json.set("materials", Json::Array(materials));
+    }
+    if !builder.buffer.is_empty() {
+        json.set("accessors", Json::Array(builder.accessors));
+        json.set("bufferViews", Json::Array(builder.buffer_views));
+        json.set(
+            "buffers",
+            Json::Array(vec![Json::object(vec![("byteLength", Json::from(builder.buffer.len()))])]),
+        );
+    }
+
+    (json, builder.buffer)
+}
+
+/// Writes a `.gltf` file, with the buffer embedded as a base64 data URI
+/// so the export is a single file.
+pub fn write_gltf(nodes: &[GltfNode]) -> String {
+    let (mut json, buffer) = build_gltf(nodes);
+    if !buffer.is_empty() {
+        let uri = format!("data:application/octet-stream;base64,{}", base64(&buffer));
+        if let Some(Json::Array(buffers)) = json.get("buffers").cloned() {
+            let mut buffers = buffers;
+            buffers[0].set("uri", Json::from(uri));
+            json.set("buffers", Json::Array(buffers));
+        }
+    }
+    json.to_string()
+}
+
+/// Writes a binary `.glb` file: the header, then the JSON chunk padded
+/// with spaces and the buffer chunk padded with zeros.
+pub fn write_glb(nodes: &[GltfNode]) -> Vec<u8> {
+    let (json, mut buffer) = build_gltf(nodes);
+    let mut json = json.to_string().into_bytes();
+    pad(&mut json, b' ');
+    pad(&mut buffer, 0);
+
+    let mut length = 12 + 8 + json.len();
+    if !buffer.is_empty() {
+        length += 8 + buffer.len();
+    }
+
+    let mut glb = Vec::with_capacity(length);
+    glb.extend_from_slice(&GLB_MAGIC.to_le_bytes());
+    glb.extend_from_slice(&2u32.to_le_bytes());
+    glb.extend_from_slice(&(length as u32).to_le_bytes());
+    glb.extend_from_slice(&(json.len() as u32).to_le_bytes());
+    glb.extend_from_slice(&GLB_JSON_CHUNK.to_le_bytes());
+    glb.extend_from_slice(&json);
+    if !buffer.is_empty() {
+        glb.extend_from_slice(&(buffer.len() as u32).to_le_bytes());
+        glb.extend_from_slice(&GLB_BIN_CHUNK.to_le_bytes());
+        glb.extend_from_slice(&buffer);
+    }
+    glb
+}
+
+#[derive(Default)]
+struct Builder {
+    accessors: Vec<Json>,
+    buffer_views: Vec<Json>,
+    buffer: Vec<u8>,
+    materials: Vec<GltfMaterial>,
+}
+
+impl Builder {
+    /// Index of `material`, added if an equal one isn't there yet.
+    fn material(&mut self, material: GltfMaterial) -> usize {
+        match self.materials.iter().position(|other| *other == material) {
+            Some(index) => index,
+            None => {
+                self.materials.push(material);
+                self.materials.len() - 1
+            }
+        }
+    }
+
+    fn primitive(&mut self, surface: &SurfaceArrays, material: usize) -> Json {
+        let positions: Vec<f32> = surface.positions.iter().flat_map(|p| vec![p.x, p.y, p.z]).collect();
+        let normals: Vec<f32> = surface
+            .normals
+            .iter()
+            .flat_map(|&normal| {
+                let normal = unit_or(normal, Vec3::new(0.0, 1.0, 0.0));
+                vec![normal.x, normal.y, normal.z]
+            })
+            .collect();
+        let tangents: Vec<f32> = surface
+            .tangents
+            .iter()
+            .flat_map(|tangent| {
+                let direction = unit_or(tangent.direction, Vec3::new(1.0, 0.0, 0.0));
+                let sign = if tangent.sign < 0.0 { -1.0 } else { 1.0 };
+                vec![direction.x, direction.y, direction.z, sign]
+            })
+            .collect();
+        let uvs: Vec<f32> = surface.uvs.iter().flat_map(|uv| vec![uv.x, uv.y]).collect();
+        let colors: Vec<f32> = surface
+            .colors
+            .iter()
+            .flat_map(|c| vec![c.r, c.g, c.b, c.a].into_iter().map(|value| value.clamp(0.0, 1.0)))
+            .collect();
+        // Godot's clockwise triangles, turned counter-clockwise
+        let indices: Vec<u32> = surface
+            .indices
+            .chunks_exact(3)
+            .flat_map(|triangle| vec![triangle[0], triangle[2], triangle[1]])
+            .collect();
+
+        let attributes = Json::object(vec![
+            ("POSITION", Json::from(self.float_accessor(&positions, "VEC3", true))),
+            ("NORMAL", Json::from(self.float_accessor(&normals, "VEC3", false))),
+            ("TANGENT", Json::from(self.float_accessor(&tangents, "VEC4", false))),
+            ("TEXCOORD_0", Json::from(self.float_accessor(&uvs, "VEC2", false))),
+            ("COLOR_0", Json::from(self.float_accessor(&colors, "VEC4", false))),
+        ]);
+        Json::object(vec![
+            ("attributes", attributes),
+            ("indices", Json::from(self.index_accessor(&indices))),
+            ("material", Json::from(material)),
+        ])
+    }
+
+    fn buffer_view(&mut self, bytes: Vec<u8>, target: u32) -> usize {
+        self.buffer_views.push(Json::object(vec![
+            ("buffer", Json::from(0usize)),
+            ("byteOffset", Json::from(self.buffer.len())),
+            ("byteLength", Json::from(bytes.len())),
+            ("target", Json::from(target)),
+        ]));
+        self.buffer.extend_from_slice(&bytes);
+        self.buffer_views.len() - 1
+    }
+
+    /// Adds `values` as an accessor of `kind` elements. Positions need
+    /// their bounds written as `min` and `max`.
+    fn float_accessor(&mut self, values: &[f32], kind: &str, bounds: bool) -> usize {
+        let components = match kind {
+            "VEC2" => 2,
+            "VEC3" => 3,
+            _ => 4,
+        };
+        let bytes = values.iter().flat_map(|value| value.to_le_bytes().to_vec()).collect();
+        let view = self.buffer_view(bytes, ARRAY_BUFFER);
+
+        let mut accessor = Json::object(vec![
+            ("bufferView", Json::from(view)),
+            ("componentType", Json::from(FLOAT)),
+            ("count", Json::from(values.len() / components)),
+            ("type", Json::from(kind)),
+        ]);
+        if bounds {
+            let mut min = vec![f32::INFINITY; components];
+            let mut max = vec![f32::NEG_INFINITY; components];
+            for element in values.chunks_exact(components) {
+                for (i, &value) in element.iter().enumerate() {
+                    min[i] = min[i].min(value);
+                    max[i] = max[i].max(value);
+                }
+            }
+            // Validators compare the bounds with the data bit for bit, so
+            // they're written at full precision
+            accessor.set("min", Json::Array(min.into_iter().map(|value| Json::Number(value as f64)).collect()));
+            accessor.set("max", Json::Array(max.into_iter().map(|value| Json::Number(value as f64)).collect()));
+        }
+
+        self.accessors.push(accessor);
+        self.accessors.len() - 1
+    }
+
+    fn index_accessor(&mut self, indices: &[u32]) -> usize {
+        let bytes = indices.iter().flat_map(|index| index.to_le_bytes().to_vec()).collect();
+        let view = self.buffer_view(bytes, ELEMENT_ARRAY_BUFFER);
+        self.accessors.push(Json::object(vec![
+            ("bufferView", Json::from(view)),
+            ("componentType", Json::from(UNSIGNED_INT)),
+            ("count", Json::from(indices.len())),
+            ("type", Json::from("SCALAR")),
+        ]));
+        self.accessors.len() - 1
+    }
+}
+
+fn material_json(material: &GltfMaterial) -> Json {
+    let color = material.base_color;
+    Json::object(vec![
+        ("name", Json::from(material.name.as_str())),
+        (
+            "pbrMetallicRoughness",
+            Json::object(vec![
+                ("baseColorFactor", Json::from(vec![color.r, color.g, color.b, color.a])),
+                ("metallicFactor", Json::from(material.metallic)),
+                ("roughnessFactor", Json::from(material.roughness)),
+            ]),
+        ),
+    ])
+}
+
+/// Column-major 4x4 matrix of `transform`.
+fn matrix(transform: &Transform) -> Json {
+    let Transform { x, y, z, origin } = *transform;
+    Json::from(vec![
+        x.x, x.y, x.z, 0.0, y.x, y.y, y.z, 0.0, z.x, z.y, z.z, 0.0, origin.x, origin.y, origin.z, 1.0,
+    ])
+}
+
+/// glTF requires unit normals and tangents, so degenerate ones are
+/// replaced with `fallback`.
+fn unit_or(vector: Vec3, fallback: Vec3) -> Vec3 {
+    let normalized = vector.normalized();
+    if (normalized.length() - 1.0).abs() < 0.0005 {
+        normalized
+    } else {
+        fallback
+    }
+}
+
+fn pad(bytes: &mut Vec<u8>, with: u8) {
+    while !bytes.len().is_multiple_of(4) {
+        bytes.push(with);
+    }
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poly_mesh::tests::cube;
+
+    fn node<'a>(name: &'a str, mesh: Option<&'a PolyMesh>, parent: Option<usize>) -> GltfNode<'a> {
+        GltfNode {
+            name,
+            parent,
+            transform: Transform::identity(),
+            mesh,
+            materials: &[],
+        }
+    }
+
+    fn index(json: &Json, key: &str, i: usize) -> Json {
+        match json.get(key) {
+            Some(Json::Array(values)) => values[i].clone(),
+            other => panic!("{} is not an array: {:?}", key, other),
+        }
+    }
+
+    #[test]
+    fn cube_becomes_one_primitive() {
+        let mesh = cube();
+        let (json, buffer) = build_gltf(&[node("Cube", Some(&mesh), None)]);
+
+        let primitive = index(&index(&json, "meshes", 0), "primitives", 0);
+        let attributes = primitive.get("attributes").unwrap();
+        let position = index(&json, "accessors", 0);
+        assert_eq!(attributes.get("POSITION"), Some(&Json::from(0usize)));
+        assert_eq!(position.get("count"), Some(&Json::from(24usize)));
+        assert_eq!(position.get("min"), Some(&Json::from(vec![0.0f32, 0.0, 0.0])));
+        assert_eq!(position.get("max"), Some(&Json::from(vec![1.0f32, 1.0, 1.0])));
+
+        let indices = index(&json, "accessors", 5);
+        assert_eq!(primitive.get("indices"), Some(&Json::from(5usize)));
+        assert_eq!(indices.get("count"), Some(&Json::from(36usize)));
+
+        // 24 vertices of 3 + 3 + 4 + 2 + 4 floats, then 36 indices
+        assert_eq!(buffer.len(), 24 * 16 * 4 + 36 * 4);
+        let view = index(&json, "bufferViews", 5);
+        let offset = match view.get("byteOffset") {
+            Some(Json::Number(offset)) => *offset as usize,
+            _ => panic!("no offset"),
+        };
+        let first: Vec<u32> = buffer[offset..offset + 12]
+            .chunks_exact(4)
+            .map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+            .collect();
+        assert_eq!(first, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn hierarchy_and_transforms_are_kept() {
+        let mesh = cube();
+        let mut child = node("Child", Some(&mesh), Some(0));
+        child.transform.origin = Vec3::new(1.0, 2.0, 3.0);
+        let (json, _) = build_gltf(&[node("Root", None, None), child]);
+
+        let root = index(&json, "nodes", 0);
+        assert_eq!(root.get("children"), Some(&Json::from(vec![1usize])));
+        assert_eq!(root.get("mesh"), None);
+        assert_eq!(root.get("matrix"), None);
+
+        let child = index(&json, "nodes", 1);
+        assert_eq!(child.get("mesh"), Some(&Json::from(0usize)));
+        assert_eq!(index(&child, "matrix", 12), Json::from(1.0f32));
+        assert_eq!(index(&child, "matrix", 14), Json::from(3.0f32));
+        assert_eq!(index(&json, "scenes", 0).get("nodes"), Some(&Json::from(vec![0usize])));
+    }
+
+    #[test]
+    fn equal_materials_are_shared() {
+        let mut mesh = cube();
+        mesh.set_face_material(0, 1);
+        let materials = [GltfMaterial::slot_default(0), GltfMaterial::slot_default(1)];
+        let mut first = node("A", Some(&mesh), None);
+        first.materials = &materials;
+        let second = node("B", Some(&mesh), None);
+        let (json, _) = build_gltf(&[first, second]);
+
+        match json.get("materials") {
+            Some(Json::Array(materials)) => assert_eq!(materials.len(), 2),
+            other => panic!("no materials: {:?}", other),
+        }
+        let primitives = index(&json, "meshes", 1);
+        assert_eq!(index(&primitives, "primitives", 1).get("material"), Some(&Json::from(1usize)));
+    }
+
+    #[test]
+    fn glb_chunks_are_aligned() {
+        let mesh = cube();
+        let glb = write_glb(&[node("Cube", Some(&mesh), None)]);
+        let word = |at: usize| u32::from_le_bytes([glb[at], glb[at + 1], glb[at + 2], glb[at + 3]]);
+
+        assert_eq!(&glb[..4], b"glTF");
+        assert_eq!(word(4), 2);
+        assert_eq!(word(8) as usize, glb.len());
+        let json_length = word(12) as usize;
+        assert_eq!(json_length % 4, 0);
+        assert_eq!(word(16), GLB_JSON_CHUNK);
+        assert_eq!(glb[20], b'{');
+        assert_eq!(word(20 + json_length + 4), GLB_BIN_CHUNK);
+    }
+
+    #[test]
+    fn gltf_embeds_the_buffer() {
+        let mesh = cube();
+        let text = write_gltf(&[node("Cube", Some(&mesh), None)]);
+        assert!(text.contains(r#""uri":"data:application/octet-stream;base64,"#));
+        assert_eq!(base64(b"Man"), "TWFu");
+        assert_eq!(base64(b"Ma"), "TWE=");
+        assert_eq!(base64(b"M"), "TQ==");
+    }
+}
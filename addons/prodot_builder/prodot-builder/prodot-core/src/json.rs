@@ -0,0 +1,545 @@
+use std::fmt::{self, Write};
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// A JSON value. Objects keep their keys in insertion order, so the text
+/// they're written as is deterministic.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    /// Builds an object from `(key, value)` pairs.
+    pub fn object<K: Into<String>>(entries: Vec<(K, Json)>) -> Json {
+        Json::Object(entries.into_iter().map(|(key, value)| (key.into(), value)).collect())
+    }
+
+    /// Value of `key`, if this is an object that has it.
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    /// Adds `key` to an object, or replaces its value.
+    pub fn set<K: Into<String>>(&mut self, key: K, value: Json) {
+        if let Json::Object(entries) = self {
+            let key = key.into();
+            match entries.iter_mut().find(|(k, _)| *k == key) {
+                Some(entry) => entry.1 = value,
+                None => entries.push((key, value)),
+            }
+        }
+    }
+
+    /// Parses JSON text. Duplicate object keys keep their last value.
+    pub fn parse(text: &str) -> Result<Json, JsonError> {
+        let mut parser = Parser {
+            chars: text.chars().peekable(),
+            line: 1,
+            depth: 0,
+        };
+        let value = parser.value()?;
+        parser.skip_whitespace();
+        match parser.chars.peek().copied() {
+            None => Ok(value),
+            Some(c) => Err(parser.error(format!("unexpected '{}' after the value", c))),
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            Json::Bool(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            Json::Number(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// The number as an index or count, if it's a whole non-negative one.
+    pub fn as_usize(&self) -> Option<usize> {
+        match *self {
+            Json::Number(value) if value >= 0.0 && value.fract() == 0.0 && value <= u32::MAX as f64 => {
+                Some(value as usize)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Json]> {
+        match self {
+            Json::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    /// Writes the value over several lines for files meant to be read and
+    /// diffed. Arrays of plain values, and objects made only of those,
+    /// stay on one line; everything else gets a line per element.
+    pub fn to_pretty_string(&self) -> String {
+        let mut out = String::new();
+        self.write_pretty(&mut out, 0).expect("writing to a String can't fail");
+        out.push('\n');
+        out
+    }
+
+    fn is_scalar(&self) -> bool {
+        !matches!(self, Json::Array(_) | Json::Object(_))
+    }
+
+    fn is_inline(&self) -> bool {
+        match self {
+            Json::Array(values) => values.iter().all(Json::is_scalar),
+            Json::Object(entries) => entries
+                .iter()
+                .all(|(_, value)| value.is_scalar() || matches!(value, Json::Array(values) if values.iter().all(Json::is_scalar))),
+            _ => true,
+        }
+    }
+
+    fn write_pretty(&self, out: &mut String, indent: usize) -> fmt::Result {
+        if self.is_inline() {
+            return self.write_inline(out);
+        }
+
+        let (open, close) = if let Json::Array(_) = self { ('[', ']') } else { ('{', '}') };
+        out.write_char(open)?;
+        let mut first = true;
+        let mut entry = |out: &mut String, key: Option<&str>, value: &Json| -> fmt::Result {
+            out.write_str(if first { "\n" } else { ",\n" })?;
+            first = false;
+            write!(out, "{:width$}", "", width = indent + 2)?;
+            if let Some(key) = key {
+                write_string(out, key)?;
+                out.write_str(": ")?;
+            }
+            value.write_pretty(out, indent + 2)
+        };
+        match self {
+            Json::Array(values) => {
+                for value in values.iter() {
+                    entry(out, None, value)?;
+                }
+            }
+            Json::Object(entries) => {
+                for (key, value) in entries.iter() {
+                    entry(out, Some(key), value)?;
+                }
+            }
+            _ => unreachable!(),
+        }
+        write!(out, "\n{:width$}{}", "", close, width = indent)
+    }
+
+    /// Same as `write`, with a space after each separator.
+    fn write_inline(&self, out: &mut String) -> fmt::Result {
+        match self {
+            Json::Array(values) => {
+                out.write_char('[')?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        out.write_str(", ")?;
+                    }
+                    value.write_inline(out)?;
+                }
+                out.write_char(']')
+            }
+            Json::Object(entries) => {
+                out.write_char('{')?;
+                for (i, (key, value)) in entries.iter().enumerate() {
+                    if i > 0 {
+                        out.write_str(", ")?;
+                    }
+                    write_string(out, key)?;
+                    out.write_str(": ")?;
+                    value.write_inline(out)?;
+                }
+                out.write_char('}')
+            }
+            value => value.write(out),
+        }
+    }
+
+    fn write(&self, out: &mut String) -> fmt::Result {
+        match self {
+            Json::Null => out.write_str("null"),
+            Json::Bool(value) => write!(out, "{}", value),
+            // JSON has no NaN or infinity
+            Json::Number(value) if !value.is_finite() => out.write_str("null"),
+            Json::Number(value) => write!(out, "{}", value),
+            Json::String(value) => write_string(out, value),
+            Json::Array(values) => {
+                out.write_char('[')?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        out.write_char(',')?;
+                    }
+                    value.write(out)?;
+                }
+                out.write_char(']')
+            }
+            Json::Object(entries) => {
+                out.write_char('{')?;
+                for (i, (key, value)) in entries.iter().enumerate() {
+                    if i > 0 {
+                        out.write_char(',')?;
+                    }
+                    write_string(out, key)?;
+                    out.write_char(':')?;
+                    value.write(out)?;
+                }
+                out.write_char('}')
+            }
+        }
+    }
+}
+
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut out = String::new();
+        self.write(&mut out)?;
+        f.write_str(&out)
+    }
+}
+
+impl From<bool> for Json {
+    fn from(value: bool) -> Self {
+        Json::Bool(value)
+    }
+}
+
+impl From<usize> for Json {
+    fn from(value: usize) -> Self {
+        Json::Number(value as f64)
+    }
+}
+
+impl From<u32> for Json {
+    fn from(value: u32) -> Self {
+        Json::Number(value as f64)
+    }
+}
+
+impl From<f32> for Json {
+    /// Goes through the shortest text that reads back as the same `f32`,
+    /// so 0.1 is written as `0.1` rather than `0.10000000149011612`.
+    fn from(value: f32) -> Self {
+        Json::Number(value.to_string().parse().unwrap_or(f64::NAN))
+    }
+}
+
+impl From<&str> for Json {
+    fn from(value: &str) -> Self {
+        Json::String(value.to_string())
+    }
+}
+
+impl From<String> for Json {
+    fn from(value: String) -> Self {
+        Json::String(value)
+    }
+}
+
+impl<T: Into<Json>> From<Vec<T>> for Json {
+    fn from(values: Vec<T>) -> Self {
+        Json::Array(values.into_iter().map(Into::into).collect())
+    }
+}
+
+/// Why JSON text couldn't be parsed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct JsonError {
+    /// 1-based line number.
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+/// Arrays and objects nested deeper than this are rejected, instead of
+/// running out of stack on hostile files.
+const MAX_DEPTH: usize = 128;
+
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+    line: usize,
+    /// Arrays and objects the parser is inside of.
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: String) -> JsonError {
+        JsonError {
+            line: self.line,
+            message,
+        }
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.chars.next();
+        if c == Some('\n') {
+            self.line += 1;
+        }
+        c
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(' ' | '\t' | '\n' | '\r') = self.chars.peek() {
+            self.next();
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), JsonError> {
+        match self.next() {
+            Some(c) if c == expected => Ok(()),
+            Some(c) => Err(self.error(format!("expected '{}', found '{}'", expected, c))),
+            None => Err(self.error(format!("expected '{}', found the end of the text", expected))),
+        }
+    }
+
+    fn value(&mut self) -> Result<Json, JsonError> {
+        self.skip_whitespace();
+        match self.chars.peek().copied() {
+            Some('n') => self.keyword("null", Json::Null),
+            Some('t') => self.keyword("true", Json::Bool(true)),
+            Some('f') => self.keyword("false", Json::Bool(false)),
+            Some('"') => self.string().map(Json::String),
+            Some(c @ ('[' | '{')) => {
+                if self.depth >= MAX_DEPTH {
+                    return Err(self.error(format!("nested deeper than {} levels", MAX_DEPTH)));
+                }
+                self.depth += 1;
+                let value = if c == '[' { self.array() } else { self.object() };
+                self.depth -= 1;
+                value
+            }
+            Some(c) if c == '-' || c.is_ascii_digit() => self.number(),
+            Some(c) => Err(self.error(format!("unexpected '{}'", c))),
+            None => Err(self.error("expected a value, found the end of the text".to_string())),
+        }
+    }
+
+    fn keyword(&mut self, keyword: &str, value: Json) -> Result<Json, JsonError> {
+        for expected in keyword.chars() {
+            self.expect(expected)?;
+        }
+        Ok(value)
+    }
+
+    fn number(&mut self) -> Result<Json, JsonError> {
+        let mut text = String::new();
+        while let Some(&c) = self.chars.peek() {
+            if !(c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E')) {
+                break;
+            }
+            text.push(c);
+            self.next();
+        }
+        text.parse()
+            .map(Json::Number)
+            .map_err(|_| self.error(format!("invalid number '{}'", text)))
+    }
+
+    fn string(&mut self) -> Result<String, JsonError> {
+        self.expect('"')?;
+        let mut value = String::new();
+        loop {
+            match self.next() {
+                Some('"') => return Ok(value),
+                Some('\\') => {
+                    let escaped = match self.next() {
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('/') => '/',
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('u') => self.unicode_escape()?,
+                        _ => return Err(self.error("invalid escape in a string".to_string())),
+                    };
+                    value.push(escaped);
+                }
+                Some(c) => value.push(c),
+                None => return Err(self.error("unterminated string".to_string())),
+            }
+        }
+    }
+
+    /// The character of a `\u` escape, after the `u`. Surrogate pairs are
+    /// combined.
+    fn unicode_escape(&mut self) -> Result<char, JsonError> {
+        let mut code = self.hex4()?;
+        if (0xd800..0xdc00).contains(&code) {
+            self.expect('\\')?;
+            self.expect('u')?;
+            let low = self.hex4()?;
+            code = 0x10000 + ((code - 0xd800) << 10) + (low.wrapping_sub(0xdc00) & 0x3ff);
+        }
+        char::from_u32(code).ok_or_else(|| self.error("invalid unicode escape".to_string()))
+    }
+
+    fn hex4(&mut self) -> Result<u32, JsonError> {
+        let mut code = 0;
+        for _ in 0..4 {
+            let digit = self
+                .next()
+                .and_then(|c| c.to_digit(16))
+                .ok_or_else(|| self.error("invalid unicode escape".to_string()))?;
+            code = code * 16 + digit;
+        }
+        Ok(code)
+    }
+
+    fn array(&mut self) -> Result<Json, JsonError> {
+        self.expect('[')?;
+        let mut values = Vec::new();
+        self.skip_whitespace();
+        if self.chars.peek() == Some(&']') {
+            self.next();
+            return Ok(Json::Array(values));
+        }
+        loop {
+            values.push(self.value()?);
+            self.skip_whitespace();
+            match self.next() {
+                Some(',') => continue,
+                Some(']') => return Ok(Json::Array(values)),
+                _ => return Err(self.error("expected ',' or ']' in an array".to_string())),
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<Json, JsonError> {
+        self.expect('{')?;
+        let mut object = Json::Object(Vec::new());
+        self.skip_whitespace();
+        if self.chars.peek() == Some(&'}') {
+            self.next();
+            return Ok(object);
+        }
+        loop {
+            self.skip_whitespace();
+            let key = self.string()?;
+            self.skip_whitespace();
+            self.expect(':')?;
+            let value = self.value()?;
+            object.set(key, value);
+            self.skip_whitespace();
+            match self.next() {
+                Some(',') => continue,
+                Some('}') => return Ok(object),
+                _ => return Err(self.error("expected ',' or '}' in an object".to_string())),
+            }
+        }
+    }
+}
+
+fn write_string(out: &mut String, value: &str) -> fmt::Result {
+    out.write_char('"')?;
+    for c in value.chars() {
+        match c {
+            '"' => out.write_str("\\\"")?,
+            '\\' => out.write_str("\\\\")?,
+            '\n' => out.write_str("\\n")?,
+            '\r' => out.write_str("\\r")?,
+            '\t' => out.write_str("\\t")?,
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32)?,
+            c => out.write_char(c)?,
+        }
+    }
+    out.write_char('"')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn objects_keep_their_key_order() {
+        let mut json = Json::object(vec![("b", Json::from(1u32)), ("a", Json::from(vec![true, false]))]);
+        json.set("c", Json::Null);
+        json.set("b", Json::from(2u32));
+
+        assert_eq!(json.to_string(), r#"{"b":2,"a":[true,false],"c":null}"#);
+        assert_eq!(json.get("a"), Some(&Json::from(vec![true, false])));
+    }
+
+    #[test]
+    fn numbers_use_their_shortest_form() {
+        assert_eq!(Json::from(0.1f32).to_string(), "0.1");
+        assert_eq!(Json::from(-2.0f32).to_string(), "-2");
+        assert_eq!(Json::Number(f64::NAN).to_string(), "null");
+    }
+
+    #[test]
+    fn strings_are_escaped() {
+        assert_eq!(Json::from("a \"b\"\\\n\u{1}").to_string(), r#""a \"b\"\\\n\u0001""#);
+    }
+
+    #[test]
+    fn parsed_text_writes_back_the_same() {
+        let text = r#"{"name":"a \"b\"\\\n\u0001","values":[1,-2.5,0.001,1e21],"flags":[true,false,null],"empty":{}}"#;
+        let json = Json::parse(text).unwrap();
+
+        assert_eq!(json.get("values").and_then(|values| values.as_array()).map(|values| values.len()), Some(4));
+        assert_eq!(Json::parse(&json.to_string()).unwrap(), json);
+        assert_eq!(Json::parse(&json.to_pretty_string()).unwrap(), json);
+        assert_eq!(Json::parse(r#""\ud83d\ude00""#).unwrap(), Json::from("\u{1f600}"));
+    }
+
+    #[test]
+    fn errors_have_a_line() {
+        let err = Json::parse("{\n  \"a\": [1, 2,\n  }").unwrap_err();
+        assert_eq!(err.line, 3);
+        assert!(Json::parse("[1] 2").is_err());
+        assert!(Json::parse("\"open").is_err());
+    }
+
+    #[test]
+    fn deep_nesting_is_an_error() {
+        let nested = |depth: usize| format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+        assert!(Json::parse(&nested(MAX_DEPTH)).is_ok());
+        let err = Json::parse(&nested(MAX_DEPTH + 1)).unwrap_err();
+        assert_eq!(err.message, "nested deeper than 128 levels");
+        // Far past what the stack could take
+        assert!(Json::parse(&"[".repeat(100_000)).is_err());
+        assert!(Json::parse(&"{\"a\":".repeat(100_000)).is_err());
+    }
+
+    #[test]
+    fn pretty_text_has_a_line_per_nested_value() {
+        let json = Json::object(vec![
+            ("version", Json::from(1u32)),
+            ("points", Json::from(vec![vec![0u32, 1], vec![2, 3]])),
+            ("faces", Json::Array(vec![Json::object(vec![("material", Json::from(0u32)), ("vertices", Json::from(vec![0u32, 1, 2]))])])),
+        ]);
+
+        let expected = "{\n  \"version\": 1,\n  \"points\": [\n    [0, 1],\n    [2, 3]\n  ],\n  \"faces\": [\n    {\"material\": 0, \"vertices\": [0, 1, 2]}\n  ]\n}\n";
+        assert_eq!(json.to_pretty_string(), expected);
+    }
+}
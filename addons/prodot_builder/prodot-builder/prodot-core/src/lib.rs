@@ -0,0 +1,23 @@
+//! Geometry kernel of Prodot Builder.
+//!
+//! Everything in here is plain Rust with no dependency on Godot, so the
+//! mesh model and its math can be unit tested with `cargo test`. The
+//! `prodot-builder` cdylib converts to and from Godot types at the edges.
+
+pub mod collision;
+pub mod gltf;
+pub mod json;
+pub mod lod;
+pub mod math;
+pub mod normals;
+pub mod obj;
+pub mod picking;
+pub mod ply;
+pub mod poly_mesh;
+pub mod primitives;
+pub mod prodot_file;
+pub mod render;
+pub mod stl;
+pub mod triangles;
+pub mod uv;
+pub mod vertex_buffer;
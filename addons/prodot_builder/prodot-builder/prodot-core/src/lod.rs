@@ -0,0 +1,183 @@
+use std::collections::HashMap;
+
+use crate::math::Vec3;
+use crate::poly_mesh::{PolyMesh, VertexId};
+
+/// Grid cells across the bounding box diagonal for the first level of
+/// detail. Every following level halves it.
+pub const FIRST_LOD_CELLS: f32 = 16.0;
+
+/// Simplifies `mesh` by vertex clustering: vertices in the same cell of a
+/// grid of `cell_size` are merged into one at their average position.
+/// Faces that collapse below 3 corners, lose their area or end up on the
+/// same vertices as an earlier face are dropped. The rest keep their
+/// material slot and corner attributes.
+pub fn simplify(mesh: &PolyMesh, cell_size: f32) -> PolyMesh {
+    let mut simplified = PolyMesh::new();
+    if mesh.vertex_count() == 0 || cell_size <= 0.0 {
+        return simplified;
+    }
+
+    let min = mesh.positions().fold(mesh.position(0), Vec3::min);
+    let mut cells: HashMap<[i64; 3], usize> = HashMap::new();
+    let mut clusters: Vec<(Vec3, usize)> = Vec::new();
+    let vertex_clusters: Vec<usize> = mesh
+        .positions()
+        .map(|position| {
+            let cell = (position - min) * (1.0 / cell_size);
+            let key = [cell.x.floor() as i64, cell.y.floor() as i64, cell.z.floor() as i64];
+            let cluster = *cells.entry(key).or_insert_with(|| {
+                clusters.push((Vec3::zero(), 0));
+                clusters.len() - 1
+            });
+            clusters[cluster].0 += position;
+            clusters[cluster].1 += 1;
+            cluster
+        })
+        .collect();
+    let cluster_positions: Vec<Vec3> = clusters
+        .iter()
+        .map(|&(sum, count)| sum * (1.0 / count as f32))
+        .collect();
+
+    // Clusters only become vertices once a face uses them
+    let mut cluster_vertices: Vec<Option<VertexId>> = vec![None; clusters.len()];
+    let mut kept_faces: Vec<Vec<usize>> = Vec::new();
+
+    for face in 0..mesh.face_count() {
+        let mut corners: Vec<(usize, usize)> = mesh
+            .face_loops(face)
+            .map(|l| (vertex_clusters[mesh.get_loop(l).vertex], l))
+            .collect();
+        corners.dedup_by_key(|corner| corner.0);
+        while corners.len() > 1 && corners[0].0 == corners[corners.len() - 1].0 {
+            corners.pop();
+        }
+        if corners.len() < 3 {
+            continue;
+        }
+
+        let mut key: Vec<usize> = corners.iter().map(|corner| corner.0).collect();
+        key.sort_unstable();
+        if key.windows(2).any(|pair| pair[0] == pair[1]) || kept_faces.contains(&key) {
+            continue;
+        }
+        let positions: Vec<Vec3> = corners.iter().map(|corner| cluster_positions[corner.0]).collect();
+        if newell_normal(&positions).length() <= f32::EPSILON {
+            continue;
+        }
+        kept_faces.push(key);
+
+        let vertices: Vec<VertexId> = corners
+            .iter()
+            .map(|&(cluster, _)| {
+                *cluster_vertices[cluster].get_or_insert_with(|| simplified.add_vertex(cluster_positions[cluster]))
+            })
+            .collect();
+        let new_face = simplified
+            .add_face(&vertices)
+            .expect("clustered faces have distinct vertices");
+        simplified.set_face_material(new_face, mesh.face_material(face));
+        for (new_loop, &(_, l)) in simplified.face_loops(new_face).zip(corners.iter()) {
+            *simplified.loop_attributes_mut(new_loop) = *mesh.loop_attributes(l);
+        }
+    }
+
+    simplified
+}
+
+/// Progressively coarser versions of `mesh`, at most `count` of them.
+/// Levels stop early once a simplification no longer removes faces or
+/// would remove all of them.
+pub fn lod_meshes(mesh: &PolyMesh, count: usize) -> Vec<PolyMesh> {
+    if mesh.vertex_count() == 0 {
+        return Vec::new();
+    }
+    let min = mesh.positions().fold(mesh.position(0), Vec3::min);
+    let max = mesh.positions().fold(mesh.position(0), Vec3::max);
+    let diagonal = min.distance_to(max);
+
+    let mut levels: Vec<PolyMesh> = Vec::new();
+    let mut cells = FIRST_LOD_CELLS;
+    while levels.len() < count && cells >= 1.0 {
+        let previous = levels.last().unwrap_or(mesh);
+        let level = simplify(previous, diagonal / cells);
+        if level.face_count() == 0 || level.face_count() >= previous.face_count() {
+            break;
+        }
+        levels.push(level);
+        cells /= 2.0;
+    }
+    levels
+}
+
+/// Unnormalized normal of a polygon, robust to concave and non-planar
+/// ones.
+fn newell_normal(positions: &[Vec3]) -> Vec3 {
+    let mut normal = Vec3::zero();
+    for (i, current) in positions.iter().enumerate() {
+        let next = positions[(i + 1) % positions.len()];
+        normal.x += (current.y - next.y) * (current.z + next.z);
+        normal.y += (current.z - next.z) * (current.x + next.x);
+        normal.z += (current.x - next.x) * (current.y + next.y);
+    }
+    normal
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poly_mesh::tests::cube;
+
+    /// A flat grid of `size` by `size` unit quads facing up.
+    fn grid(size: usize) -> PolyMesh {
+        let mut mesh = PolyMesh::new();
+        for z in 0..=size {
+            for x in 0..=size {
+                mesh.add_vertex(Vec3::new(x as f32, 0.0, z as f32));
+            }
+        }
+        let row = size + 1;
+        for z in 0..size {
+            for x in 0..size {
+                let corner = z * row + x;
+                mesh.add_face(&[corner, corner + row, corner + row + 1, corner + 1]).unwrap();
+            }
+        }
+        mesh
+    }
+
+    #[test]
+    fn clustering_merges_nearby_vertices() {
+        let mut mesh = grid(4);
+        // The first quad that spans four cells
+        mesh.set_face_material(5, 2);
+        let simplified = simplify(&mesh, 2.0);
+
+        assert_eq!(simplified.face_count(), 4);
+        assert_eq!(simplified.vertex_count(), 9);
+        assert_eq!(simplified.face_material(0), 2);
+        for face in 0..simplified.face_count() {
+            assert_eq!(simplified.face_len(face), 4);
+            assert!(simplified.face_normal(face).y > 0.99);
+        }
+    }
+
+    #[test]
+    fn collapsed_faces_are_dropped() {
+        assert_eq!(simplify(&cube(), 2.0).face_count(), 0);
+        // Cells smaller than the cube keep every corner apart
+        assert_eq!(simplify(&cube(), 0.5).face_count(), 6);
+    }
+
+    #[test]
+    fn levels_get_coarser_until_nothing_is_left() {
+        let levels = lod_meshes(&grid(32), 8);
+        let faces: Vec<usize> = levels.iter().map(|level| level.face_count()).collect();
+
+        assert!(!faces.is_empty() && faces.len() < 8);
+        assert!(faces[0] < 32 * 32);
+        assert!(faces.windows(2).all(|pair| pair[1] < pair[0]));
+        assert!(lod_meshes(&cube(), 2).is_empty());
+    }
+}
@@ -0,0 +1,393 @@
+use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+
+/// Same tolerance Godot uses for its geometry helpers (`CMP_EPSILON`).
+pub const CMP_EPSILON: f32 = 0.00001;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Vec2 {
+    pub x: f32,
+    pub y: f32,
+}
+
+impl Vec2 {
+    pub const fn new(x: f32, y: f32) -> Self {
+        Vec2 { x, y }
+    }
+
+    pub const fn zero() -> Self {
+        Vec2 { x: 0.0, y: 0.0 }
+    }
+
+    pub fn dot(self, other: Vec2) -> f32 {
+        self.x * other.x + self.y * other.y
+    }
+
+    pub fn length(self) -> f32 {
+        self.dot(self).sqrt()
+    }
+
+    /// Rotates the vector counter-clockwise by `angle` radians.
+    pub fn rotated(self, angle: f32) -> Vec2 {
+        let (sin, cos) = angle.sin_cos();
+        Vec2::new(self.x * cos - self.y * sin, self.x * sin + self.y * cos)
+    }
+}
+
+impl Add for Vec2 {
+    type Output = Vec2;
+
+    fn add(self, other: Vec2) -> Vec2 {
+        Vec2::new(self.x + other.x, self.y + other.y)
+    }
+}
+
+impl Sub for Vec2 {
+    type Output = Vec2;
+
+    fn sub(self, other: Vec2) -> Vec2 {
+        Vec2::new(self.x - other.x, self.y - other.y)
+    }
+}
+
+impl Mul<f32> for Vec2 {
+    type Output = Vec2;
+
+    fn mul(self, scalar: f32) -> Vec2 {
+        Vec2::new(self.x * scalar, self.y * scalar)
+    }
+}
+
+impl Div<f32> for Vec2 {
+    type Output = Vec2;
+
+    fn div(self, scalar: f32) -> Vec2 {
+        Vec2::new(self.x / scalar, self.y / scalar)
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Vec3 {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+impl Vec3 {
+    pub const fn new(x: f32, y: f32, z: f32) -> Self {
+        Vec3 { x, y, z }
+    }
+
+    pub const fn zero() -> Self {
+        Vec3 {
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+        }
+    }
+
+    pub const fn one() -> Self {
+        Vec3 {
+            x: 1.0,
+            y: 1.0,
+            z: 1.0,
+        }
+    }
+
+    pub fn dot(self, other: Vec3) -> f32 {
+        self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    pub fn cross(self, other: Vec3) -> Vec3 {
+        Vec3::new(
+            self.y * other.z - self.z * other.y,
+            self.z * other.x - self.x * other.z,
+            self.x * other.y - self.y * other.x,
+        )
+    }
+
+    pub fn length(self) -> f32 {
+        self.dot(self).sqrt()
+    }
+
+    pub fn length_squared(self) -> f32 {
+        self.dot(self)
+    }
+
+    /// Returns the vector scaled to unit length, or zero for a zero vector.
+    pub fn normalized(self) -> Vec3 {
+        let length = self.length();
+        if length > 0.0 {
+            self / length
+        } else {
+            Vec3::zero()
+        }
+    }
+
+    pub fn distance_to(self, other: Vec3) -> f32 {
+        (other - self).length()
+    }
+
+    pub fn abs(self) -> Vec3 {
+        Vec3::new(self.x.abs(), self.y.abs(), self.z.abs())
+    }
+
+    pub fn min(self, other: Vec3) -> Vec3 {
+        Vec3::new(self.x.min(other.x), self.y.min(other.y), self.z.min(other.z))
+    }
+
+    pub fn max(self, other: Vec3) -> Vec3 {
+        Vec3::new(self.x.max(other.x), self.y.max(other.y), self.z.max(other.z))
+    }
+
+    pub fn lerp(self, other: Vec3, weight: f32) -> Vec3 {
+        self + (other - self) * weight
+    }
+
+    /// Whether no component is NaN or infinite.
+    pub fn is_finite(self) -> bool {
+        self.x.is_finite() && self.y.is_finite() && self.z.is_finite()
+    }
+
+    pub fn is_equal_approx(self, other: Vec3, epsilon: f32) -> bool {
+        (self.x - other.x).abs() <= epsilon
+            && (self.y - other.y).abs() <= epsilon
+            && (self.z - other.z).abs() <= epsilon
+    }
+}
+
+impl Add for Vec3 {
+    type Output = Vec3;
+
+    fn add(self, other: Vec3) -> Vec3 {
+        Vec3::new(self.x + other.x, self.y + other.y, self.z + other.z)
+    }
+}
+
+impl AddAssign for Vec3 {
+    fn add_assign(&mut self, other: Vec3) {
+        *self = *self + other;
+    }
+}
+
+impl Sub for Vec3 {
+    type Output = Vec3;
+
+    fn sub(self, other: Vec3) -> Vec3 {
+        Vec3::new(self.x - other.x, self.y - other.y, self.z - other.z)
+    }
+}
+
+impl SubAssign for Vec3 {
+    fn sub_assign(&mut self, other: Vec3) {
+        *self = *self - other;
+    }
+}
+
+impl Mul<f32> for Vec3 {
+    type Output = Vec3;
+
+    fn mul(self, scalar: f32) -> Vec3 {
+        Vec3::new(self.x * scalar, self.y * scalar, self.z * scalar)
+    }
+}
+
+impl MulAssign<f32> for Vec3 {
+    fn mul_assign(&mut self, scalar: f32) {
+        *self = *self * scalar;
+    }
+}
+
+impl Div<f32> for Vec3 {
+    type Output = Vec3;
+
+    fn div(self, scalar: f32) -> Vec3 {
+        Vec3::new(self.x / scalar, self.y / scalar, self.z / scalar)
+    }
+}
+
+impl DivAssign<f32> for Vec3 {
+    fn div_assign(&mut self, scalar: f32) {
+        *self = *self / scalar;
+    }
+}
+
+impl Neg for Vec3 {
+    type Output = Vec3;
+
+    fn neg(self) -> Vec3 {
+        Vec3::new(-self.x, -self.y, -self.z)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Color {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    pub a: f32,
+}
+
+impl Color {
+    pub const fn rgba(r: f32, g: f32, b: f32, a: f32) -> Self {
+        Color { r, g, b, a }
+    }
+
+    pub const fn white() -> Self {
+        Color::rgba(1.0, 1.0, 1.0, 1.0)
+    }
+}
+
+impl Default for Color {
+    fn default() -> Self {
+        Color::white()
+    }
+}
+
+/// Tangent of a vertex, laid out like Godot's `ARRAY_TANGENT`: the
+/// direction of +U plus the sign that gives the binormal as
+/// `normal.cross(direction) * sign`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Tangent {
+    pub direction: Vec3,
+    pub sign: f32,
+}
+
+impl Tangent {
+    pub const fn new(direction: Vec3, sign: f32) -> Self {
+        Tangent { direction, sign }
+    }
+}
+
+impl Default for Tangent {
+    fn default() -> Self {
+        Tangent::new(Vec3::new(1.0, 0.0, 0.0), 1.0)
+    }
+}
+
+/// A plane in Hessian normal form, matching Godot's `Plane`: every point
+/// `p` on it satisfies `normal.dot(p) == d`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Plane {
+    pub normal: Vec3,
+    pub d: f32,
+}
+
+impl Plane {
+    pub const fn new(normal: Vec3, d: f32) -> Self {
+        Plane { normal, d }
+    }
+
+    pub fn distance_to(&self, point: Vec3) -> f32 {
+        self.normal.dot(point) - self.d
+    }
+
+    /// Returns where the ray from `from` along `dir` hits the plane, or
+    /// `None` when it runs parallel to it or points away from it.
+    pub fn intersects_ray(&self, from: Vec3, dir: Vec3) -> Option<Vec3> {
+        let den = self.normal.dot(dir);
+        if den.abs() <= CMP_EPSILON {
+            return None;
+        }
+
+        let dist = (self.normal.dot(from) - self.d) / den;
+        if dist > CMP_EPSILON {
+            return None;
+        }
+
+        Some(from + dir * -dist)
+    }
+}
+
+/// An affine transform laid out like Godot's `Transform`: a basis given
+/// by its three column vectors, applied before `origin`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Transform {
+    pub x: Vec3,
+    pub y: Vec3,
+    pub z: Vec3,
+    pub origin: Vec3,
+}
+
+impl Transform {
+    pub const fn identity() -> Self {
+        Transform {
+            x: Vec3::new(1.0, 0.0, 0.0),
+            y: Vec3::new(0.0, 1.0, 0.0),
+            z: Vec3::new(0.0, 0.0, 1.0),
+            origin: Vec3::zero(),
+        }
+    }
+
+    pub fn xform(&self, point: Vec3) -> Vec3 {
+        self.x * point.x + self.y * point.y + self.z * point.z + self.origin
+    }
+
+    /// Transforms a normal by the inverse transpose of the basis, so it
+    /// stays perpendicular to its surface under non-uniform scale.
+    pub fn xform_normal(&self, normal: Vec3) -> Vec3 {
+        let cofactor = self.y.cross(self.z) * normal.x + self.z.cross(self.x) * normal.y + self.x.cross(self.y) * normal.z;
+        if self.is_mirrored() {
+            -cofactor.normalized()
+        } else {
+            cofactor.normalized()
+        }
+    }
+
+    /// Whether the basis has a negative determinant. Mirroring turns
+    /// counter-clockwise faces clockwise.
+    pub fn is_mirrored(&self) -> bool {
+        self.x.cross(self.y).dot(self.z) < 0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cross_follows_the_right_hand_rule() {
+        let x = Vec3::new(1.0, 0.0, 0.0);
+        let y = Vec3::new(0.0, 1.0, 0.0);
+        assert_eq!(x.cross(y), Vec3::new(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn normalizing_zero_stays_zero() {
+        assert_eq!(Vec3::zero().normalized(), Vec3::zero());
+        assert_eq!(Vec3::new(0.0, 3.0, 4.0).normalized().length(), 1.0);
+    }
+
+    #[test]
+    fn ray_hits_plane_in_front_of_it() {
+        let plane = Plane::new(Vec3::new(0.0, 0.0, 1.0), 1.0);
+        let hit = plane.intersects_ray(Vec3::new(2.0, 3.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        assert_eq!(hit, Some(Vec3::new(2.0, 3.0, 1.0)));
+    }
+
+    #[test]
+    fn ray_misses_plane_behind_it() {
+        let plane = Plane::new(Vec3::new(0.0, 0.0, 1.0), 1.0);
+        assert_eq!(plane.intersects_ray(Vec3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, 1.0)), None);
+        assert_eq!(plane.intersects_ray(Vec3::new(0.0, 0.0, 5.0), Vec3::new(1.0, 0.0, 0.0)), None);
+    }
+
+    #[test]
+    fn normals_follow_non_uniform_scale_and_mirroring() {
+        let squash = Transform {
+            x: Vec3::new(2.0, 0.0, 0.0),
+            y: Vec3::new(0.0, 1.0, 0.0),
+            z: Vec3::new(0.0, 0.0, 1.0),
+            origin: Vec3::new(0.0, 5.0, 0.0),
+        };
+        assert_eq!(squash.xform(Vec3::new(1.0, 1.0, 1.0)), Vec3::new(2.0, 6.0, 1.0));
+        let diagonal = squash.xform_normal(Vec3::new(1.0, 1.0, 0.0).normalized());
+        assert!(diagonal.is_equal_approx(Vec3::new(1.0, 2.0, 0.0).normalized(), 1e-6));
+
+        let mirror = Transform {
+            x: Vec3::new(-1.0, 0.0, 0.0),
+            ..Transform::identity()
+        };
+        assert!(mirror.is_mirrored());
+        assert_eq!(mirror.xform_normal(Vec3::new(1.0, 0.0, 0.0)), Vec3::new(-1.0, 0.0, 0.0));
+    }
+}
@@ -0,0 +1,155 @@
+use crate::math::{Vec3, CMP_EPSILON};
+use crate::poly_mesh::{LoopId, PolyMesh, VertexId};
+
+/// How corner normals are derived from the faces around each vertex.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum NormalMode {
+    /// Every corner takes its face normal, so all edges are hard.
+    #[default]
+    Flat,
+    /// Every corner takes the average normal of the faces on its vertex.
+    Smooth,
+    /// Like `Smooth`, but only faces within `angle` radians of the
+    /// corner's own face are averaged, so sharp edges stay hard.
+    AutoSmooth { angle: f32 },
+}
+
+/// Recomputes the normal of every face corner. Corners that end up with
+/// the same normal and UV share a render vertex, the others split into
+/// hard edges.
+pub fn recalculate_normals(mesh: &mut PolyMesh, mode: NormalMode) {
+    for l in 0..mesh.loop_count() {
+        let normal = corner_normal(mesh, l, mode);
+        mesh.loop_attributes_mut(l).normal = normal;
+    }
+}
+
+/// Recomputes the normals of the corners on `vertices` only. After moving
+/// some vertices, passing every vertex of the faces around them updates
+/// all the normals the move affected.
+pub fn recalculate_vertex_normals(mesh: &mut PolyMesh, mode: NormalMode, vertices: &[VertexId]) {
+    for &vertex in vertices {
+        for l in mesh.vertex_loops(vertex) {
+            let normal = corner_normal(mesh, l, mode);
+            mesh.loop_attributes_mut(l).normal = normal;
+        }
+    }
+}
+
+fn corner_normal(mesh: &PolyMesh, l: LoopId, mode: NormalMode) -> Vec3 {
+    let corner = mesh.get_loop(l);
+    let own = mesh.face_normal(corner.face);
+    match mode {
+        NormalMode::Flat => own,
+        NormalMode::Smooth => average_normal(mesh, corner.vertex, |_| true),
+        NormalMode::AutoSmooth { angle } => {
+            let min_cos = angle.cos() - CMP_EPSILON;
+            average_normal(mesh, corner.vertex, |normal| normal.dot(own) >= min_cos)
+        }
+    }
+}
+
+/// Average of the normals of the faces on `vertex` accepted by `include`.
+/// Faces are visited in id order, so corners that accept the same faces
+/// get bit-identical normals.
+fn average_normal<F>(mesh: &PolyMesh, vertex: VertexId, include: F) -> Vec3
+where
+    F: Fn(Vec3) -> bool,
+{
+    let mut normal = Vec3::zero();
+    for face in mesh.vertex_faces(vertex) {
+        let face_normal = mesh.face_normal(face);
+        if include(face_normal) {
+            normal += face_normal;
+        }
+    }
+    normal.normalized()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poly_mesh::tests::cube;
+    use crate::render::build_surfaces;
+
+    /// Two quads meeting at a shallow fold along x == 1.
+    fn folded_strip(height: f32) -> PolyMesh {
+        let mut mesh = PolyMesh::new();
+        let ids: Vec<VertexId> = [
+            (0.0, 0.0, 0.0),
+            (1.0, 0.0, height),
+            (1.0, 1.0, height),
+            (0.0, 1.0, 0.0),
+            (2.0, 0.0, 0.0),
+            (2.0, 1.0, 0.0),
+        ]
+        .iter()
+        .map(|&(x, y, z)| mesh.add_vertex(Vec3::new(x, y, z)))
+        .collect();
+        mesh.add_face(&[ids[0], ids[1], ids[2], ids[3]]).unwrap();
+        mesh.add_face(&[ids[1], ids[4], ids[5], ids[2]]).unwrap();
+        mesh
+    }
+
+    #[test]
+    fn flat_cube_splits_every_corner() {
+        let mut mesh = cube();
+        recalculate_normals(&mut mesh, NormalMode::Flat);
+        mesh.recalculate_tangents();
+
+        assert_eq!(mesh.loop_attributes(0).normal, Vec3::new(0.0, 0.0, 1.0));
+        assert_eq!(build_surfaces(&mesh).vertex_count(), 24);
+    }
+
+    #[test]
+    fn smooth_cube_shares_every_corner() {
+        let mut mesh = cube();
+        recalculate_normals(&mut mesh, NormalMode::Smooth);
+        mesh.recalculate_tangents();
+
+        for l in mesh.vertex_loops(0) {
+            assert_eq!(mesh.loop_attributes(l).normal, mesh.vertex_normal(0));
+        }
+        assert_eq!(build_surfaces(&mesh).vertex_count(), 8);
+    }
+
+    #[test]
+    fn auto_smooth_keeps_sharp_edges_hard() {
+        let angle = 30f32.to_radians();
+
+        let mut cube = cube();
+        recalculate_normals(&mut cube, NormalMode::AutoSmooth { angle });
+        cube.recalculate_tangents();
+        assert_eq!(build_surfaces(&cube).vertex_count(), 24);
+
+        // A fold of about 23 degrees is under the threshold
+        let mut strip = folded_strip(0.2);
+        recalculate_normals(&mut strip, NormalMode::AutoSmooth { angle });
+        strip.recalculate_tangents();
+        assert_eq!(build_surfaces(&strip).vertex_count(), 6);
+
+        // One of 90 degrees is over it
+        let mut strip = folded_strip(1.0);
+        recalculate_normals(&mut strip, NormalMode::AutoSmooth { angle });
+        strip.recalculate_tangents();
+        assert_eq!(build_surfaces(&strip).vertex_count(), 8);
+    }
+
+    #[test]
+    fn vertex_normals_match_a_full_recalculation() {
+        let mut mesh = cube();
+        let mode = NormalMode::Smooth;
+        recalculate_normals(&mut mesh, mode);
+
+        mesh.set_position(2, Vec3::new(1.5, 1.5, 1.5));
+        let faces = mesh.vertex_faces(2);
+        let vertices = mesh.faces_vertices(&faces);
+        recalculate_vertex_normals(&mut mesh, mode, &vertices);
+
+        let mut expected = mesh.clone();
+        recalculate_normals(&mut expected, mode);
+        for l in 0..mesh.loop_count() {
+            assert_eq!(mesh.loop_attributes(l).normal, expected.loop_attributes(l).normal);
+        }
+    }
+}
@@ -0,0 +1,732 @@
+use std::collections::HashMap;
+use std::fmt::{self, Write};
+
+use crate::math::{Color, Transform, Vec2, Vec3};
+use crate::poly_mesh::{PolyMesh, TopologyError, VertexId};
+
+/// One mesh to write as an `o` block of an OBJ file.
+#[derive(Clone, Debug)]
+pub struct ObjObject<'a> {
+    pub name: &'a str,
+    pub mesh: &'a PolyMesh,
+    /// Material name of each slot. Slots past the end are written as
+    /// `material_<slot>`.
+    pub materials: &'a [String],
+    /// Applied to positions and normals, e.g. the node's global transform.
+    pub transform: Transform,
+}
+
+/// A `newmtl` entry of an MTL file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ObjMaterial {
+    pub name: String,
+    pub diffuse: Color,
+    /// Path of the albedo texture, written as `map_Kd`.
+    pub diffuse_texture: Option<String>,
+}
+
+/// Makes `name` usable as an OBJ object or material name, which can't
+/// contain whitespace.
+pub fn obj_name(name: &str) -> String {
+    let name: String = name
+        .trim()
+        .chars()
+        .map(|c| if c.is_whitespace() { '_' } else { c })
+        .collect();
+    if name.is_empty() {
+        "unnamed".to_string()
+    } else {
+        name
+    }
+}
+
+/// Name slot `slot` is written with when `materials` doesn't name it.
+pub fn slot_material_name(materials: &[String], slot: usize) -> String {
+    match materials.get(slot) {
+        Some(name) => obj_name(name),
+        None => format!("material_{}", slot),
+    }
+}
+
+/// Writes the objects as Wavefront OBJ text. Faces are written as they
+/// are, so quads and n-gons stay whole, grouped by material slot. Every
+/// corner references its own UV and normal, which are shared between
+/// corners when they're identical. `mtl_file` is referenced by `mtllib`.
+///
+/// OBJ's V axis points up the texture, so V is flipped from Godot's.
+pub fn write_obj(objects: &[ObjObject], mtl_file: Option<&str>) -> String {
+    let mut out = String::new();
+    writeln!(out, "# Exported by Prodot Builder").unwrap();
+    if let Some(mtl_file) = mtl_file {
+        writeln!(out, "mtllib {}", mtl_file).unwrap();
+    }
+
+    // OBJ indices are 1-based and count across every object in the file
+    let mut position_offset = 1;
+    let mut uv_offset = 1;
+    let mut normal_offset = 1;
+
+    for object in objects {
+        let mesh = object.mesh;
+        let transform = object.transform;
+        writeln!(out, "o {}", obj_name(object.name)).unwrap();
+
+        for position in mesh.positions() {
+            let position = transform.xform(position);
+            writeln!(out, "v {} {} {}", position.x, position.y, position.z).unwrap();
+        }
+
+        let mut uvs: Vec<Vec2> = Vec::new();
+        let mut uv_ids: HashMap<[u32; 2], usize> = HashMap::new();
+        let mut normals: Vec<Vec3> = Vec::new();
+        let mut normal_ids: HashMap<[u32; 3], usize> = HashMap::new();
+        let mut corners: Vec<(usize, usize)> = Vec::with_capacity(mesh.loop_count());
+        for l in 0..mesh.loop_count() {
+            let attributes = mesh.loop_attributes(l);
+            let uv = Vec2::new(attributes.uv.x, 1.0 - attributes.uv.y);
+            let uv_id = *uv_ids.entry([uv.x.to_bits(), uv.y.to_bits()]).or_insert_with(|| {
+                uvs.push(uv);
+                uvs.len() - 1
+            });
+            let normal = transform.xform_normal(attributes.normal);
+            let normal_id = *normal_ids
+                .entry([normal.x.to_bits(), normal.y.to_bits(), normal.z.to_bits()])
+                .or_insert_with(|| {
+                    normals.push(normal);
+                    normals.len() - 1
+                });
+            corners.push((uv_id, normal_id));
+        }
+
+        for uv in uvs.iter() {
+            writeln!(out, "vt {} {}", uv.x, uv.y).unwrap();
+        }
+        for normal in normals.iter() {
+            writeln!(out, "vn {} {} {}", normal.x, normal.y, normal.z).unwrap();
+        }
+
+        let mut slots: Vec<usize> = (0..mesh.face_count()).map(|face| mesh.face_material(face)).collect();
+        slots.sort_unstable();
+        slots.dedup();
+        for slot in slots {
+            writeln!(out, "usemtl {}", slot_material_name(object.materials, slot)).unwrap();
+            for face in (0..mesh.face_count()).filter(|&face| mesh.face_material(face) == slot) {
+                let mut loops: Vec<_> = mesh.face_loops(face).collect();
+                // A mirrored transform turns the faces inside out otherwise
+                if transform.is_mirrored() {
+                    loops.reverse();
+                }
+
+                out.push('f');
+                for l in loops {
+                    let (uv_id, normal_id) = corners[l];
+                    write!(
+                        out,
+                        " {}/{}/{}",
+                        mesh.get_loop(l).vertex + position_offset,
+                        uv_id + uv_offset,
+                        normal_id + normal_offset
+                    )
+                    .unwrap();
+                }
+                out.push('\n');
+            }
+        }
+
+        position_offset += mesh.vertex_count();
+        uv_offset += uvs.len();
+        normal_offset += normals.len();
+    }
+
+    out
+}
+
+/// Writes the materials as MTL text, skipping repeated names.
+pub fn write_mtl(materials: &[ObjMaterial]) -> String {
+    let mut out = String::new();
+    writeln!(out, "# Exported by Prodot Builder").unwrap();
+
+    let mut written: Vec<&str> = Vec::new();
+    for material in materials {
+        if written.contains(&material.name.as_str()) {
+            continue;
+        }
+        written.push(&material.name);
+
+        let diffuse = material.diffuse;
+        writeln!(out).unwrap();
+        writeln!(out, "newmtl {}", obj_name(&material.name)).unwrap();
+        writeln!(out, "Kd {} {} {}", diffuse.r, diffuse.g, diffuse.b).unwrap();
+        if diffuse.a < 1.0 {
+            writeln!(out, "d {}", diffuse.a).unwrap();
+        }
+        if let Some(texture) = &material.diffuse_texture {
+            writeln!(out, "map_Kd {}", texture).unwrap();
+        }
+    }
+
+    out
+}
+
+/// A mesh read from an OBJ file.
+#[derive(Clone, Debug)]
+pub struct ObjImport {
+    /// Name of the first `o` or `g` record, empty if there is none.
+    pub name: String,
+    pub mesh: PolyMesh,
+    /// `usemtl` name of each material slot, in the order they first
+    /// appear. Faces before the first `usemtl` use a slot named "".
+    pub materials: Vec<String>,
+    /// Files named by `mtllib` records.
+    pub mtl_files: Vec<String>,
+    /// Whether any face corner references a `vn` record. Corners without
+    /// one get their face normal.
+    pub has_normals: bool,
+    /// Faces left out because welding collapsed them below 3 corners, or
+    /// made them pass through the same vertex twice.
+    pub skipped_faces: usize,
+}
+
+/// Why an OBJ or MTL file couldn't be read, and on which line.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ObjError {
+    /// 1-based line number, 0 for errors about the whole file.
+    pub line: usize,
+    pub message: String,
+}
+
+impl ObjError {
+    fn new(line: usize, message: impl Into<String>) -> Self {
+        ObjError {
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.line == 0 {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "line {}: {}", self.line, self.message)
+        }
+    }
+}
+
+/// Reads Wavefront OBJ text into one mesh. Faces keep their corner
+/// count, and positions that are equal are welded into a shared vertex
+/// even when the file repeats them, so the faces stay connected. Corners
+/// get the file's UVs, with V flipped back to Godot's, and normals when
+/// they have them. Vertex colors written after the position (`v x y z r
+/// g b`) are kept as well.
+///
+/// Objects and groups are merged, and records other than `v`, `vt`,
+/// `vn`, `f`, `usemtl`, `mtllib`, `o` and `g` are ignored.
+pub fn read_obj(text: &str) -> Result<ObjImport, ObjError> {
+    let mut positions: Vec<(Vec3, Option<Color>)> = Vec::new();
+    let mut uvs: Vec<Vec2> = Vec::new();
+    let mut normals: Vec<Vec3> = Vec::new();
+
+    let mut import = ObjImport {
+        name: String::new(),
+        mesh: PolyMesh::new(),
+        materials: Vec::new(),
+        mtl_files: Vec::new(),
+        has_normals: false,
+        skipped_faces: 0,
+    };
+    // File position index to mesh vertex, and welded position to vertex
+    let mut position_vertices: Vec<Option<VertexId>> = Vec::new();
+    let mut welded: HashMap<[u32; 3], VertexId> = HashMap::new();
+    let mut current_material: Option<usize> = None;
+
+    for (number, line) in text.lines().enumerate() {
+        let number = number + 1;
+        let line = line.split('#').next().unwrap_or("").trim();
+        let mut tokens = line.split_whitespace();
+        let keyword = match tokens.next() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+        let values: Vec<&str> = tokens.collect();
+
+        match keyword {
+            "v" => {
+                let numbers = parse_floats(number, &values, 3)?;
+                let position = Vec3::new(numbers[0], numbers[1], numbers[2]);
+                let color = match numbers.len() {
+                    6 | 7 => Some(Color::rgba(numbers[3], numbers[4], numbers[5], 1.0)),
+                    _ => None,
+                };
+                positions.push((position, color));
+                position_vertices.push(None);
+            }
+            "vt" => {
+                let numbers = parse_floats(number, &values, 1)?;
+                let v = numbers.get(1).copied().unwrap_or(0.0);
+                uvs.push(Vec2::new(numbers[0], 1.0 - v));
+            }
+            "vn" => {
+                let numbers = parse_floats(number, &values, 3)?;
+                normals.push(Vec3::new(numbers[0], numbers[1], numbers[2]).normalized());
+            }
+            "f" => {
+                if values.len() < 3 {
+                    return Err(ObjError::new(number, format!("a face needs at least 3 corners, got {}", values.len())));
+                }
+
+                let mut corners: Vec<ObjCorner> = Vec::new();
+                for corner in values.iter() {
+                    let mut indices = corner.split('/');
+                    let position = parse_index(number, indices.next(), positions.len(), "position")?
+                        .ok_or_else(|| ObjError::new(number, format!("face corner '{}' has no position", corner)))?;
+                    let uv = parse_index(number, indices.next(), uvs.len(), "texture coordinate")?;
+                    let normal = parse_index(number, indices.next(), normals.len(), "normal")?;
+
+                    let (point, color) = positions[position];
+                    let vertex = match position_vertices[position] {
+                        Some(vertex) => vertex,
+                        None => {
+                            // -0.0 and 0.0 are the same position
+                            let key = [(point.x + 0.0).to_bits(), (point.y + 0.0).to_bits(), (point.z + 0.0).to_bits()];
+                            let vertex = *welded.entry(key).or_insert_with(|| import.mesh.add_vertex(point));
+                            position_vertices[position] = Some(vertex);
+                            vertex
+                        }
+                    };
+                    corners.push(ObjCorner {
+                        vertex,
+                        uv: uv.map(|uv| uvs[uv]),
+                        normal: normal.map(|normal| normals[normal]),
+                        color,
+                    });
+                }
+
+                // Welding can turn neighbouring corners into the same vertex
+                corners.dedup_by_key(|corner| corner.vertex);
+                while corners.len() > 1 && corners[0].vertex == corners[corners.len() - 1].vertex {
+                    corners.pop();
+                }
+                if corners.len() < 3 {
+                    import.skipped_faces += 1;
+                    continue;
+                }
+
+                let vertices: Vec<VertexId> = corners.iter().map(|corner| corner.vertex).collect();
+                let face = match import.mesh.add_face(&vertices) {
+                    Ok(face) => face,
+                    // A bowtie, which shouldn't cost the rest of the file
+                    Err(TopologyError::RepeatedVertex(_)) => {
+                        import.skipped_faces += 1;
+                        continue;
+                    }
+                    Err(err) => return Err(ObjError::new(number, err.to_string())),
+                };
+
+                let slot = match current_material {
+                    Some(slot) => slot,
+                    None => material_slot(&mut import.materials, ""),
+                };
+                import.mesh.set_face_material(face, slot);
+                let face_normal = import.mesh.face_normal(face);
+                for (l, corner) in import.mesh.face_loops(face).zip(corners) {
+                    let attributes = import.mesh.loop_attributes_mut(l);
+                    if let Some(uv) = corner.uv {
+                        attributes.uv = uv;
+                    }
+                    match corner.normal {
+                        Some(normal) => {
+                            attributes.normal = normal;
+                            import.has_normals = true;
+                        }
+                        None => attributes.normal = face_normal,
+                    }
+                    if let Some(color) = corner.color {
+                        attributes.color = color;
+                    }
+                }
+            }
+            "usemtl" => {
+                let name = values.join(" ");
+                current_material = Some(material_slot(&mut import.materials, &name));
+            }
+            "mtllib" => {
+                import.mtl_files.extend(values.iter().map(|file| file.to_string()));
+            }
+            "o" | "g" if import.name.is_empty() => import.name = values.join(" "),
+            _ => {}
+        }
+    }
+
+    if import.mesh.face_count() == 0 {
+        return Err(ObjError::new(0, "the file has no faces"));
+    }
+    import.mesh.recalculate_tangents();
+    Ok(import)
+}
+
+/// Reads the `newmtl` entries of MTL text: their `Kd` color, `d` or `Tr`
+/// opacity and `map_Kd` texture.
+pub fn read_mtl(text: &str) -> Result<Vec<ObjMaterial>, ObjError> {
+    let mut materials: Vec<ObjMaterial> = Vec::new();
+
+    for (number, line) in text.lines().enumerate() {
+        let number = number + 1;
+        let line = line.trim();
+        if line.starts_with('#') {
+            continue;
+        }
+        let mut tokens = line.split_whitespace();
+        let keyword = match tokens.next() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+        let values: Vec<&str> = tokens.collect();
+
+        if keyword == "newmtl" {
+            materials.push(ObjMaterial {
+                name: values.join(" "),
+                diffuse: Color::white(),
+                diffuse_texture: None,
+            });
+            continue;
+        }
+
+        let material = match materials.last_mut() {
+            Some(material) => material,
+            None if ["Kd", "d", "Tr", "map_Kd"].contains(&keyword) => {
+                return Err(ObjError::new(number, format!("'{}' comes before any newmtl", keyword)));
+            }
+            None => continue,
+        };
+        match keyword {
+            "Kd" => {
+                let numbers = parse_floats(number, &values, 3)?;
+                material.diffuse = Color::rgba(numbers[0], numbers[1], numbers[2], material.diffuse.a);
+            }
+            "d" => material.diffuse.a = parse_floats(number, &values, 1)?[0],
+            "Tr" => material.diffuse.a = 1.0 - parse_floats(number, &values, 1)?[0],
+            // Texture options come before the file name
+            "map_Kd" => material.diffuse_texture = values.last().map(|file| file.to_string()),
+            _ => {}
+        }
+    }
+
+    Ok(materials)
+}
+
+/// One corner of a face record, after welding.
+struct ObjCorner {
+    vertex: VertexId,
+    uv: Option<Vec2>,
+    normal: Option<Vec3>,
+    color: Option<Color>,
+}
+
+fn material_slot(materials: &mut Vec<String>, name: &str) -> usize {
+    match materials.iter().position(|material| material == name) {
+        Some(slot) => slot,
+        None => {
+            materials.push(name.to_string());
+            materials.len() - 1
+        }
+    }
+}
+
+/// Parses every value as a number, requiring at least `min_count`.
+fn parse_floats(line: usize, values: &[&str], min_count: usize) -> Result<Vec<f32>, ObjError> {
+    if values.len() < min_count {
+        return Err(ObjError::new(line, format!("expected {} numbers, found {}", min_count, values.len())));
+    }
+    values
+        .iter()
+        .map(|value| match value.parse::<f32>() {
+            Ok(number) if number.is_finite() => Ok(number),
+            _ => Err(ObjError::new(line, format!("'{}' is not a number", value))),
+        })
+        .collect()
+}
+
+/// Turns one index of a face corner into a 0-based one. Indices are
+/// 1-based, or count back from the latest element when negative. Missing
+/// and empty indices, like the UV of `1//1`, are `None`.
+fn parse_index(line: usize, index: Option<&str>, count: usize, kind: &str) -> Result<Option<usize>, ObjError> {
+    let index = match index {
+        Some(index) if !index.is_empty() => index,
+        _ => return Ok(None),
+    };
+    let value: i64 = index
+        .parse()
+        .map_err(|_| ObjError::new(line, format!("'{}' is not a valid {} index", index, kind)))?;
+
+    let resolved = if value < 0 { count as i64 + value } else { value - 1 };
+    if value == 0 || resolved < 0 || resolved >= count as i64 {
+        return Err(ObjError::new(
+            line,
+            format!("{} index {} is out of range, there are {}", kind, value, count),
+        ));
+    }
+    Ok(Some(resolved as usize))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poly_mesh::tests::cube;
+
+    fn lines<'a>(text: &'a str, prefix: &str) -> Vec<&'a str> {
+        text.lines().filter(|line| line.starts_with(prefix)).collect()
+    }
+
+    #[test]
+    fn cube_keeps_its_quads() {
+        let mesh = cube();
+        let object = ObjObject {
+            name: "My Cube",
+            mesh: &mesh,
+            materials: &[],
+            transform: Transform::identity(),
+        };
+        let text = write_obj(&[object], Some("cube.mtl"));
+
+        assert!(text.contains("mtllib cube.mtl\no My_Cube\n"));
+        assert_eq!(lines(&text, "v ").len(), 8);
+        // Every corner has the default UV, and each side its own normal
+        assert_eq!(lines(&text, "vt ").len(), 1);
+        assert_eq!(lines(&text, "vn ").len(), 6);
+        assert_eq!(lines(&text, "usemtl "), vec!["usemtl material_0"]);
+
+        let faces = lines(&text, "f ");
+        assert_eq!(faces.len(), 6);
+        assert_eq!(faces[0], "f 1/1/1 4/1/1 3/1/1 2/1/1");
+    }
+
+    #[test]
+    fn faces_are_grouped_by_material() {
+        let mut mesh = cube();
+        mesh.set_face_material(0, 1);
+        let materials = vec!["Floor".to_string(), "Wall Paint".to_string()];
+        let object = ObjObject {
+            name: "Cube",
+            mesh: &mesh,
+            materials: &materials,
+            transform: Transform::identity(),
+        };
+        let text = write_obj(&[object], None);
+
+        assert_eq!(lines(&text, "usemtl "), vec!["usemtl Floor", "usemtl Wall_Paint"]);
+        assert!(text.ends_with("usemtl Wall_Paint\nf 1/1/1 4/1/1 3/1/1 2/1/1\n"));
+    }
+
+    #[test]
+    fn indices_continue_across_objects_and_uvs_are_flipped() {
+        let mut mesh = cube();
+        mesh.loop_attributes_mut(0).uv = Vec2::new(0.25, 0.25);
+        let objects = [
+            ObjObject {
+                name: "A",
+                mesh: &mesh,
+                materials: &[],
+                transform: Transform::identity(),
+            },
+            ObjObject {
+                name: "B",
+                mesh: &mesh,
+                materials: &[],
+                transform: Transform::identity(),
+            },
+        ];
+        let text = write_obj(&objects, None);
+
+        assert_eq!(lines(&text, "vt ")[..2], ["vt 0.25 0.75", "vt 0 1"]);
+        let faces = lines(&text, "f ");
+        assert_eq!(faces[6], "f 9/3/7 12/4/7 11/4/7 10/4/7");
+    }
+
+    #[test]
+    fn mirrored_transforms_keep_faces_outward() {
+        let mesh = cube();
+        let object = ObjObject {
+            name: "Mirrored",
+            mesh: &mesh,
+            materials: &[],
+            transform: Transform {
+                x: Vec3::new(-1.0, 0.0, 0.0),
+                ..Transform::identity()
+            },
+        };
+        let text = write_obj(&[object], None);
+
+        assert_eq!(lines(&text, "v ")[2], "v -1 1 1");
+        assert_eq!(lines(&text, "f ")[0], "f 2/1/1 3/1/1 4/1/1 1/1/1");
+    }
+
+    #[test]
+    fn mtl_lists_each_material_once() {
+        let materials = [
+            ObjMaterial {
+                name: "Brick".to_string(),
+                diffuse: Color::rgba(1.0, 0.5, 0.25, 1.0),
+                diffuse_texture: Some("textures/brick.png".to_string()),
+            },
+            ObjMaterial {
+                name: "Brick".to_string(),
+                diffuse: Color::white(),
+                diffuse_texture: None,
+            },
+        ];
+        let text = write_mtl(&materials);
+
+        assert_eq!(lines(&text, "newmtl ").len(), 1);
+        assert!(text.contains("newmtl Brick\nKd 1 0.5 0.25\nmap_Kd textures/brick.png\n"));
+    }
+
+    #[test]
+    fn reads_ngons_and_welds_repeated_positions() {
+        // Two faces that repeat the positions of their shared edge
+        let text = "\
+o Prop
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+v 1 0 0
+v 1 1 0
+v 2 0 0
+v 2.5 0.5 0
+v 2 1 0
+vt 0 0
+vt 1 1
+f 1/1 2/1 3/2 4/1
+f 5 7 8 9 6
+";
+        let import = read_obj(text).unwrap();
+
+        assert_eq!(import.name, "Prop");
+        assert_eq!(import.mesh.vertex_count(), 7);
+        assert_eq!(import.mesh.face_count(), 2);
+        assert_eq!(import.mesh.face_len(1), 5);
+        let shared = import.mesh.find_edge(1, 2).unwrap();
+        assert_eq!(import.mesh.edge_faces(shared), vec![0, 1]);
+        assert_eq!(import.mesh.loop_attributes(2).uv, Vec2::new(1.0, 0.0));
+    }
+
+    #[test]
+    fn usemtl_groups_become_material_slots() {
+        let text = "\
+mtllib props.mtl
+v 0 0 0
+v 1 0 0
+v 0 1 0
+v 1 1 0
+usemtl Wood
+f 1 2 3
+usemtl Metal
+f 2 4 3
+usemtl Wood
+f -4 -2 -1
+";
+        let import = read_obj(text).unwrap();
+
+        assert_eq!(import.mtl_files, vec!["props.mtl"]);
+        assert_eq!(import.materials, vec!["Wood", "Metal"]);
+        let slots: Vec<usize> = (0..3).map(|face| import.mesh.face_material(face)).collect();
+        assert_eq!(slots, vec![0, 1, 0]);
+    }
+
+    #[test]
+    fn exported_meshes_read_back() {
+        let mesh = cube();
+        let object = ObjObject {
+            name: "Cube",
+            mesh: &mesh,
+            materials: &[],
+            transform: Transform::identity(),
+        };
+        let import = read_obj(&write_obj(&[object], None)).unwrap();
+
+        assert_eq!(import.mesh.vertex_count(), 8);
+        assert_eq!(import.mesh.face_count(), 6);
+        // Vertices are numbered in the order the faces first use them
+        let positions: Vec<Vec3> = import.mesh.face_vertices(0).map(|vertex| import.mesh.position(vertex)).collect();
+        let expected: Vec<Vec3> = mesh.face_vertices(0).map(|vertex| mesh.position(vertex)).collect();
+        assert_eq!(positions, expected);
+        assert_eq!(import.mesh.loop_attributes(0).normal, mesh.loop_attributes(0).normal);
+    }
+
+    #[test]
+    fn file_normals_survive_into_the_stored_arrays() {
+        let text = "v 0 0 0\nv 1 0 0\nv 0 1 0\nvn -1 0 0\nvn 0 2 0\nf 1//1 2//2 3//2\n";
+        let import = read_obj(text).unwrap();
+        assert!(import.has_normals);
+
+        let stored = PolyMesh::from_arrays(&import.mesh.to_arrays()).unwrap();
+        let normals: Vec<Vec3> = stored.face_loops(0).map(|l| stored.loop_attributes(l).normal).collect();
+        let expected = [Vec3::new(-1.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 1.0, 0.0)];
+        assert_eq!(normals, expected);
+
+        assert!(!read_obj("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n").unwrap().has_normals);
+    }
+
+    #[test]
+    fn malformed_files_report_the_line() {
+        let error = |text: &str| read_obj(text).unwrap_err().to_string();
+
+        assert_eq!(error("v 0 0\n"), "line 1: expected 3 numbers, found 2");
+        assert_eq!(error("v 0 0 zero\n"), "line 1: 'zero' is not a number");
+        assert_eq!(error("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 4\n"), "line 4: position index 4 is out of range, there are 3");
+        assert_eq!(error("v 0 0 0\nv 1 0 0\nf 1 2\n"), "line 3: a face needs at least 3 corners, got 2");
+        assert_eq!(error("v 0 0 0\nf 0 1 1\n"), "line 2: position index 0 is out of range, there are 1");
+        assert_eq!(error("# nothing\n"), "the file has no faces");
+    }
+
+    #[test]
+    fn collapsed_faces_are_skipped() {
+        let text = "v 0 0 0\nv 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\nf 1 3 4\n";
+        let import = read_obj(text).unwrap();
+
+        assert_eq!(import.skipped_faces, 1);
+        assert_eq!(import.mesh.face_count(), 1);
+    }
+
+    #[test]
+    fn faces_through_a_vertex_twice_are_skipped() {
+        // The fourth position welds with the second, pinching the first
+        // face into a bowtie
+        let text = "v 0 0 0\nv 1 0 0\nv 2 1 0\nv 1 0 0\nv 0 1 0\nf 1 2 3 4 5\nf 1 2 5\n";
+        let import = read_obj(text).unwrap();
+
+        assert_eq!(import.skipped_faces, 1);
+        assert_eq!(import.mesh.face_count(), 1);
+        assert_eq!(import.mesh.face_len(0), 3);
+    }
+
+    #[test]
+    fn concave_faces_triangulate_to_their_area() {
+        let text = "v 2 0 0\nv 2 1 0\nv 1 1 0\nv 1 2 0\nv 0 2 0\nv 0 0 0\nf 1 2 3 4 5 6\n";
+        let mesh = read_obj(text).unwrap().mesh;
+
+        let area: f32 = mesh
+            .face_triangles(0)
+            .into_iter()
+            .map(|[a, b, c]| {
+                let [a, b, c] = [mesh.position(a), mesh.position(b), mesh.position(c)];
+                (b - a).cross(c - a).length() / 2.0
+            })
+            .sum();
+        assert!((area - 3.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn mtl_materials_read_back() {
+        let text = "newmtl Glass\nKd 0.5 0.5 1\nd 0.25\nmap_Kd -s 1 1 1 glass.png\nnewmtl Plain\n";
+        let materials = read_mtl(text).unwrap();
+
+        assert_eq!(materials.len(), 2);
+        assert_eq!(materials[0].diffuse, Color::rgba(0.5, 0.5, 1.0, 0.25));
+        assert_eq!(materials[0].diffuse_texture.as_deref(), Some("glass.png"));
+        assert_eq!(materials[1].diffuse, Color::white());
+        assert_eq!(read_mtl("Kd 1 1 1\n").unwrap_err().to_string(), "line 1: 'Kd' comes before any newmtl");
+    }
+}
@@ -0,0 +1,225 @@
+use crate::math::{Plane, Vec3, CMP_EPSILON};
+use crate::poly_mesh::{FaceId, PolyMesh, VertexId};
+
+/// Distance between the gizmo's center and each of its axis handles.
+pub const GIZMO_HANDLE_DIST: f32 = 0.15;
+/// Half the size of a gizmo handle's pickable square.
+pub const GIZMO_HANDLE_SIZE: f32 = 0.05;
+
+/// Möller–Trumbore ray/triangle test, two-sided like Godot's
+/// `Geometry.ray_intersects_triangle`. Returns the hit point.
+pub fn ray_intersects_triangle(from: Vec3, dir: Vec3, a: Vec3, b: Vec3, c: Vec3) -> Option<Vec3> {
+    let edge_one = b - a;
+    let edge_two = c - a;
+    let h = dir.cross(edge_two);
+    let det = edge_one.dot(h);
+    if det.abs() < CMP_EPSILON {
+        return None;
+    }
+
+    let f = 1.0 / det;
+    let s = from - a;
+    let u = f * s.dot(h);
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+
+    let q = s.cross(edge_one);
+    let v = f * dir.dot(q);
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+
+    let t = f * edge_two.dot(q);
+    if t > CMP_EPSILON {
+        Some(from + dir * t)
+    } else {
+        None
+    }
+}
+
+/// Finds the face closest to `from` that the ray hits. `offset` moves the
+/// mesh into the ray's space (the node's global position).
+pub fn pick_face(mesh: &PolyMesh, offset: Vec3, from: Vec3, dir: Vec3) -> Option<FaceId> {
+    let mut closest: Option<(FaceId, f32)> = None;
+
+    for face in 0..mesh.face_count() {
+        for triangle in mesh.face_triangles(face) {
+            let hit = ray_intersects_triangle(
+                from,
+                dir,
+                offset + mesh.position(triangle[0]),
+                offset + mesh.position(triangle[1]),
+                offset + mesh.position(triangle[2]),
+            );
+
+            if let Some(point) = hit {
+                let dist = from.distance_to(point);
+                if closest.is_none_or(|(_, closest_dist)| dist < closest_dist) {
+                    closest = Some((face, dist));
+                }
+            }
+        }
+    }
+
+    closest.map(|(face, _)| face)
+}
+
+/// Finds the vertex whose handle box (of half size `box_size`) is under
+/// the ray. Each handle is tested on a view plane facing +Z through the
+/// vertex, and the one nearest to `from` wins.
+pub fn pick_vertex(mesh: &PolyMesh, offset: Vec3, from: Vec3, dir: Vec3, box_size: f32) -> Option<VertexId> {
+    let mut closest: Option<(VertexId, f32)> = None;
+
+    for vertex in 0..mesh.vertex_count() {
+        let vertex_pos = offset + mesh.position(vertex);
+        let plane = Plane::new(Vec3::new(0.0, 0.0, 1.0), vertex_pos.z);
+
+        if let Some(proj_pos) = plane.intersects_ray(from, dir) {
+            if proj_pos.x > vertex_pos.x - box_size
+                && proj_pos.x < vertex_pos.x + box_size
+                && proj_pos.y > vertex_pos.y - box_size
+                && proj_pos.y < vertex_pos.y + box_size
+            {
+                let dist = from.distance_to(vertex_pos);
+                if closest.is_none_or(|(_, closest_dist)| dist < closest_dist) {
+                    closest = Some((vertex, dist));
+                }
+            }
+        }
+    }
+
+    closest.map(|(vertex, _)| vertex)
+}
+
+/// Returns the axis of the gizmo handle under the ray, or zero when the
+/// ray misses all of them. The X and Y handles are tested on the XY plane
+/// through `center`, the Z handle on the YZ plane.
+pub fn detect_gizmo_axis(center: Vec3, from: Vec3, dir: Vec3) -> Vec3 {
+    let gizmo_dist = GIZMO_HANDLE_DIST;
+    let box_size = GIZMO_HANDLE_SIZE;
+    let mut axis = Vec3::zero();
+
+    let plane = Plane::new(Vec3::new(0.0, 0.0, 1.0), center.z);
+    if let Some(proj_pos) = plane.intersects_ray(from, dir) {
+        // X_Plane
+        if proj_pos.x > center.x - box_size + gizmo_dist
+            && proj_pos.x < center.x + box_size + gizmo_dist
+            && proj_pos.y > center.y - box_size
+            && proj_pos.y < center.y + box_size
+        {
+            axis = Vec3::new(1.0, 0.0, 0.0);
+        }
+
+        // Y_Plane
+        if proj_pos.x > center.x - box_size
+            && proj_pos.x < center.x + box_size
+            && proj_pos.y > center.y - box_size + gizmo_dist
+            && proj_pos.y < center.y + box_size + gizmo_dist
+        {
+            axis = Vec3::new(0.0, 1.0, 0.0);
+        }
+    }
+
+    let plane = Plane::new(Vec3::new(1.0, 0.0, 0.0), center.x);
+    if let Some(proj_pos) = plane.intersects_ray(from, dir) {
+        // Z_Plane
+        if proj_pos.y > center.y - box_size
+            && proj_pos.y < center.y + box_size
+            && proj_pos.z > center.z - box_size + gizmo_dist
+            && proj_pos.z < center.z + box_size + gizmo_dist
+        {
+            axis = Vec3::new(0.0, 0.0, 1.0);
+        }
+    }
+
+    axis
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quad() -> PolyMesh {
+        let mut mesh = PolyMesh::new();
+        let a = mesh.add_vertex(Vec3::new(0.0, 0.0, 0.0));
+        let b = mesh.add_vertex(Vec3::new(1.0, 0.0, 0.0));
+        let c = mesh.add_vertex(Vec3::new(1.0, 1.0, 0.0));
+        let d = mesh.add_vertex(Vec3::new(0.0, 1.0, 0.0));
+        mesh.add_face(&[a, b, c, d]).unwrap();
+        mesh
+    }
+
+    #[test]
+    fn ray_hits_triangle_from_either_side() {
+        let a = Vec3::new(0.0, 0.0, 0.0);
+        let b = Vec3::new(1.0, 0.0, 0.0);
+        let c = Vec3::new(0.0, 1.0, 0.0);
+
+        let front = ray_intersects_triangle(Vec3::new(0.25, 0.25, 1.0), Vec3::new(0.0, 0.0, -1.0), a, b, c);
+        let back = ray_intersects_triangle(Vec3::new(0.25, 0.25, -1.0), Vec3::new(0.0, 0.0, 1.0), a, b, c);
+        assert_eq!(front, Some(Vec3::new(0.25, 0.25, 0.0)));
+        assert_eq!(back, Some(Vec3::new(0.25, 0.25, 0.0)));
+    }
+
+    #[test]
+    fn ray_misses_triangle_outside_or_behind() {
+        let a = Vec3::new(0.0, 0.0, 0.0);
+        let b = Vec3::new(1.0, 0.0, 0.0);
+        let c = Vec3::new(0.0, 1.0, 0.0);
+
+        let outside = ray_intersects_triangle(Vec3::new(0.75, 0.75, 1.0), Vec3::new(0.0, 0.0, -1.0), a, b, c);
+        let behind = ray_intersects_triangle(Vec3::new(0.25, 0.25, 1.0), Vec3::new(0.0, 0.0, 1.0), a, b, c);
+        assert_eq!(outside, None);
+        assert_eq!(behind, None);
+    }
+
+    #[test]
+    fn pick_face_respects_mesh_offset() {
+        let mesh = quad();
+        let offset = Vec3::new(10.0, 0.0, 0.0);
+        let dir = Vec3::new(0.0, 0.0, -1.0);
+
+        assert_eq!(pick_face(&mesh, offset, Vec3::new(10.75, 0.75, 5.0), dir), Some(0));
+        assert_eq!(pick_face(&mesh, offset, Vec3::new(0.75, 0.75, 5.0), dir), None);
+    }
+
+    #[test]
+    fn pick_face_prefers_the_nearest_face() {
+        let mut mesh = quad();
+        let a = mesh.add_vertex(Vec3::new(0.0, 0.0, 1.0));
+        let b = mesh.add_vertex(Vec3::new(1.0, 0.0, 1.0));
+        let c = mesh.add_vertex(Vec3::new(1.0, 1.0, 1.0));
+        let d = mesh.add_vertex(Vec3::new(0.0, 1.0, 1.0));
+        mesh.add_face(&[a, b, c, d]).unwrap();
+
+        let picked = pick_face(&mesh, Vec3::zero(), Vec3::new(0.5, 0.5, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        assert_eq!(picked, Some(1));
+    }
+
+    #[test]
+    fn pick_vertex_uses_handle_box() {
+        let mesh = quad();
+        let dir = Vec3::new(0.0, 0.0, -1.0);
+
+        assert_eq!(pick_vertex(&mesh, Vec3::zero(), Vec3::new(1.05, 0.95, 5.0), dir, 0.1), Some(2));
+        assert_eq!(pick_vertex(&mesh, Vec3::zero(), Vec3::new(0.5, 0.5, 5.0), dir, 0.1), None);
+    }
+
+    #[test]
+    fn gizmo_axis_detection() {
+        let center = Vec3::new(1.0, 1.0, 1.0);
+        let down_z = Vec3::new(0.0, 0.0, -1.0);
+        let down_x = Vec3::new(-1.0, 0.0, 0.0);
+
+        let x = detect_gizmo_axis(center, Vec3::new(1.15, 1.0, 5.0), down_z);
+        let y = detect_gizmo_axis(center, Vec3::new(1.0, 1.15, 5.0), down_z);
+        let z = detect_gizmo_axis(center, Vec3::new(5.0, 1.0, 1.15), down_x);
+        let none = detect_gizmo_axis(center, Vec3::new(1.5, 1.5, 5.0), down_z);
+
+        assert_eq!(x, Vec3::new(1.0, 0.0, 0.0));
+        assert_eq!(y, Vec3::new(0.0, 1.0, 0.0));
+        assert_eq!(z, Vec3::new(0.0, 0.0, 1.0));
+        assert_eq!(none, Vec3::zero());
+    }
+}
@@ -0,0 +1,855 @@
+use std::collections::HashMap;
+use std::fmt::{self, Write};
+
+use crate::math::{Color, Transform, Vec2, Vec3};
+use crate::poly_mesh::{PolyMesh, TopologyError, VertexId};
+
+/// Encoding of the data after a PLY header.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum PlyFormat {
+    Ascii,
+    BinaryLittleEndian,
+}
+
+/// One mesh to write into a PLY file.
+#[derive(Clone, Debug)]
+pub struct PlyObject<'a> {
+    pub mesh: &'a PolyMesh,
+    /// Applied to positions and normals, e.g. the node's global transform.
+    pub transform: Transform,
+}
+
+/// A mesh read from a PLY file.
+#[derive(Clone, Debug)]
+pub struct PlyImport {
+    pub mesh: PolyMesh,
+    /// Whether the vertices have `nx ny nz`, which the corners then keep.
+    pub has_normals: bool,
+    /// Faces left out because welding collapsed them below 3 corners, or
+    /// made them pass through the same vertex twice.
+    pub skipped_faces: usize,
+}
+
+/// Why a PLY file couldn't be read.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlyError {
+    pub message: String,
+}
+
+impl PlyError {
+    fn new(message: impl Into<String>) -> Self {
+        PlyError { message: message.into() }
+    }
+}
+
+impl fmt::Display for PlyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+/// Writes the objects as one PLY mesh. Faces are written as they are,
+/// so quads and n-gons stay whole. PLY only has vertex attributes, so a
+/// vertex is written once for every different normal, UV and color its
+/// corners have; `read_ply` welds them back together.
+///
+/// Vertices get `x y z`, `nx ny nz`, `s t` and 8-bit `red green blue
+/// alpha` properties. PLY's V axis points up the texture, so V is flipped
+/// from Godot's.
+pub fn write_ply(objects: &[PlyObject], format: PlyFormat) -> Vec<u8> {
+    let mut vertices: Vec<PlyVertex> = Vec::new();
+    let mut faces: Vec<Vec<u32>> = Vec::new();
+
+    for object in objects {
+        let mesh = object.mesh;
+        let transform = object.transform;
+        let mut corner_vertices: HashMap<(VertexId, [u32; 6]), u32> = HashMap::new();
+
+        for face in 0..mesh.face_count() {
+            let mut indices: Vec<u32> = mesh
+                .face_loops(face)
+                .map(|l| {
+                    let attributes = mesh.loop_attributes(l);
+                    let vertex = PlyVertex {
+                        position: transform.xform(mesh.position(mesh.get_loop(l).vertex)),
+                        normal: transform.xform_normal(attributes.normal),
+                        uv: Vec2::new(attributes.uv.x, 1.0 - attributes.uv.y),
+                        color: color_bytes(attributes.color),
+                    };
+                    let key = (mesh.get_loop(l).vertex, vertex.attribute_bits());
+                    *corner_vertices.entry(key).or_insert_with(|| {
+                        vertices.push(vertex);
+                        vertices.len() as u32 - 1
+                    })
+                })
+                .collect();
+            // A mirrored transform turns the faces inside out otherwise
+            if transform.is_mirrored() {
+                indices.reverse();
+            }
+            faces.push(indices);
+        }
+    }
+
+    // Counts above 255 need a wider type than the usual uchar
+    let count_type = if faces.iter().all(|face| face.len() <= u8::MAX as usize) {
+        "uchar"
+    } else {
+        "uint"
+    };
+
+    let mut header = String::new();
+    writeln!(header, "ply").unwrap();
+    match format {
+        PlyFormat::Ascii => writeln!(header, "format ascii 1.0").unwrap(),
+        PlyFormat::BinaryLittleEndian => writeln!(header, "format binary_little_endian 1.0").unwrap(),
+    }
+    writeln!(header, "comment Exported by Prodot Builder").unwrap();
+    writeln!(header, "element vertex {}", vertices.len()).unwrap();
+    for property in &["x", "y", "z", "nx", "ny", "nz", "s", "t"] {
+        writeln!(header, "property float {}", property).unwrap();
+    }
+    for property in &["red", "green", "blue", "alpha"] {
+        writeln!(header, "property uchar {}", property).unwrap();
+    }
+    writeln!(header, "element face {}", faces.len()).unwrap();
+    writeln!(header, "property list {} uint vertex_indices", count_type).unwrap();
+    writeln!(header, "end_header").unwrap();
+
+    match format {
+        PlyFormat::Ascii => {
+            let mut out = header;
+            for vertex in &vertices {
+                let [r, g, b, a] = vertex.color;
+                writeln!(
+                    out,
+                    "{} {} {} {} {} {} {} {} {} {} {} {}",
+                    vertex.position.x,
+                    vertex.position.y,
+                    vertex.position.z,
+                    vertex.normal.x,
+                    vertex.normal.y,
+                    vertex.normal.z,
+                    vertex.uv.x,
+                    vertex.uv.y,
+                    r,
+                    g,
+                    b,
+                    a
+                )
+                .unwrap();
+            }
+            for face in &faces {
+                write!(out, "{}", face.len()).unwrap();
+                for index in face {
+                    write!(out, " {}", index).unwrap();
+                }
+                out.push('\n');
+            }
+            out.into_bytes()
+        }
+        PlyFormat::BinaryLittleEndian => {
+            let mut out = header.into_bytes();
+            for vertex in &vertices {
+                for value in &vertex.floats() {
+                    out.extend_from_slice(&value.to_le_bytes());
+                }
+                out.extend_from_slice(&vertex.color);
+            }
+            for face in &faces {
+                if count_type == "uchar" {
+                    out.push(face.len() as u8);
+                } else {
+                    out.extend_from_slice(&(face.len() as u32).to_le_bytes());
+                }
+                for index in face {
+                    out.extend_from_slice(&index.to_le_bytes());
+                }
+            }
+            out
+        }
+    }
+}
+
+/// Reads an ASCII or binary little-endian PLY file into one mesh. Faces
+/// keep their corner count, and vertices at the same position are welded
+/// into one, so faces the file splits apart by their attributes stay
+/// connected. Corners get the normal, UV and color of their file vertex;
+/// faces without normals get their face normal.
+///
+/// Colors are read from `red green blue alpha`, as 8 or 16-bit integers
+/// or as floats. UVs are read from `s t`, `u v`, `texture_u texture_v` or
+/// `texture_s texture_t`. Elements other than `vertex` and `face` are
+/// skipped.
+pub fn read_ply(bytes: &[u8]) -> Result<PlyImport, PlyError> {
+    let (header, body_start) = read_header(bytes)?;
+    let mut body = match header.format {
+        PlyFormat::Ascii => {
+            let text = std::str::from_utf8(&bytes[body_start..])
+                .map_err(|_| PlyError::new("the ASCII data isn't valid text"))?;
+            Body::Ascii(text.split_ascii_whitespace())
+        }
+        PlyFormat::BinaryLittleEndian => Body::Binary(&bytes[body_start..]),
+    };
+
+    let mut vertex_values: Vec<f64> = Vec::new();
+    let mut vertex_properties: Vec<&Property> = Vec::new();
+    let mut faces: Vec<Vec<usize>> = Vec::new();
+
+    // Counts come from the file, so nothing is reserved up front: a
+    // count larger than the data runs into its end instead
+    for element in &header.elements {
+        if element.properties.is_empty() {
+            continue;
+        }
+        match element.name.as_str() {
+            "vertex" => {
+                vertex_properties = element
+                    .properties
+                    .iter()
+                    .filter(|property| matches!(property.kind, PropertyKind::Scalar(_)))
+                    .collect();
+                for _ in 0..element.count {
+                    for property in &element.properties {
+                        match property.kind {
+                            PropertyKind::Scalar(scalar) => vertex_values.push(body.read(scalar)?),
+                            kind => body.skip(kind)?,
+                        }
+                    }
+                }
+            }
+            "face" => {
+                let indices_property = element
+                    .properties
+                    .iter()
+                    .position(|property| property.name == "vertex_indices" || property.name == "vertex_index")
+                    .ok_or_else(|| PlyError::new("the face element has no vertex_indices list"))?;
+                for _ in 0..element.count {
+                    for (index, property) in element.properties.iter().enumerate() {
+                        match property.kind {
+                            PropertyKind::List(count, item) if index == indices_property => {
+                                let len = body.read_len(count)?;
+                                let mut indices = Vec::new();
+                                for _ in 0..len {
+                                    let value = body.read(item)?;
+                                    if value < 0.0 || value.fract() != 0.0 {
+                                        return Err(PlyError::new(format!(
+                                            "face {} has an invalid vertex index",
+                                            faces.len()
+                                        )));
+                                    }
+                                    indices.push(value as usize);
+                                }
+                                faces.push(indices);
+                            }
+                            kind => body.skip(kind)?,
+                        }
+                    }
+                }
+            }
+            _ => {
+                for _ in 0..element.count {
+                    for property in &element.properties {
+                        body.skip(property.kind)?;
+                    }
+                }
+            }
+        }
+    }
+
+    let stride = vertex_properties.len();
+    let vertex_count = vertex_values.len().checked_div(stride).unwrap_or(0);
+    let find = |names: &[&str]| -> Option<Vec<(usize, ScalarType)>> {
+        names
+            .iter()
+            .map(|name| {
+                vertex_properties.iter().position(|property| property.name == *name).map(|index| {
+                    match vertex_properties[index].kind {
+                        PropertyKind::Scalar(scalar) => (index, scalar),
+                        PropertyKind::List(..) => unreachable!("vertex_properties only has scalars"),
+                    }
+                })
+            })
+            .collect()
+    };
+    let position = find(&["x", "y", "z"]).ok_or_else(|| PlyError::new("vertices have no x, y and z"))?;
+    let normal = find(&["nx", "ny", "nz"]);
+    let uv = [["s", "t"], ["u", "v"], ["texture_u", "texture_v"], ["texture_s", "texture_t"]]
+        .iter()
+        .find_map(|names| find(names));
+    let color = find(&["red", "green", "blue"]);
+    let alpha = find(&["alpha"]);
+
+    let value = |vertex: usize, (index, _): (usize, ScalarType)| vertex_values[vertex * stride + index] as f32;
+    let color_value = |vertex: usize, property: (usize, ScalarType)| value(vertex, property) / property.1.color_scale();
+    let vec3 = |vertex: usize, properties: &[(usize, ScalarType)]| {
+        Vec3::new(value(vertex, properties[0]), value(vertex, properties[1]), value(vertex, properties[2]))
+    };
+
+    let mut import = PlyImport {
+        mesh: PolyMesh::new(),
+        has_normals: normal.is_some(),
+        skipped_faces: 0,
+    };
+    // File vertex to mesh vertex, and welded position to vertex
+    let mut file_vertices: Vec<Option<VertexId>> = vec![None; vertex_count];
+    let mut welded: HashMap<[u32; 3], VertexId> = HashMap::new();
+
+    for (number, indices) in faces.iter().enumerate() {
+        if indices.len() < 3 {
+            return Err(PlyError::new(format!("face {} has {} corner(s), it needs at least 3", number, indices.len())));
+        }
+
+        let mut corners: Vec<(VertexId, usize)> = Vec::with_capacity(indices.len());
+        for &index in indices {
+            if index >= vertex_count {
+                return Err(PlyError::new(format!(
+                    "face {} uses vertex {}, but there are only {}",
+                    number, index, vertex_count
+                )));
+            }
+            let vertex = match file_vertices[index] {
+                Some(vertex) => vertex,
+                None => {
+                    let point = vec3(index, &position);
+                    // -0.0 and 0.0 are the same position
+                    let key = [(point.x + 0.0).to_bits(), (point.y + 0.0).to_bits(), (point.z + 0.0).to_bits()];
+                    let vertex = *welded.entry(key).or_insert_with(|| import.mesh.add_vertex(point));
+                    file_vertices[index] = Some(vertex);
+                    vertex
+                }
+            };
+            corners.push((vertex, index));
+        }
+
+        // Welding can turn neighbouring corners into the same vertex
+        corners.dedup_by_key(|corner| corner.0);
+        while corners.len() > 1 && corners[0].0 == corners[corners.len() - 1].0 {
+            corners.pop();
+        }
+        if corners.len() < 3 {
+            import.skipped_faces += 1;
+            continue;
+        }
+
+        let vertices: Vec<VertexId> = corners.iter().map(|corner| corner.0).collect();
+        let face = match import.mesh.add_face(&vertices) {
+            Ok(face) => face,
+            // A bowtie, which shouldn't cost the rest of the file
+            Err(TopologyError::RepeatedVertex(_)) => {
+                import.skipped_faces += 1;
+                continue;
+            }
+            Err(err) => return Err(PlyError::new(format!("face {}: {}", number, err))),
+        };
+
+        let face_normal = import.mesh.face_normal(face);
+        let loops: Vec<_> = import.mesh.face_loops(face).collect();
+        for (l, &(_, index)) in loops.into_iter().zip(corners.iter()) {
+            let attributes = import.mesh.loop_attributes_mut(l);
+            attributes.normal = match &normal {
+                Some(normal) => vec3(index, normal).normalized(),
+                None => face_normal,
+            };
+            if let Some(uv) = &uv {
+                attributes.uv = Vec2::new(value(index, uv[0]), 1.0 - value(index, uv[1]));
+            }
+            if let Some(color) = &color {
+                attributes.color = Color::rgba(
+                    color_value(index, color[0]),
+                    color_value(index, color[1]),
+                    color_value(index, color[2]),
+                    alpha.as_ref().map_or(1.0, |alpha| color_value(index, alpha[0])),
+                );
+            }
+        }
+    }
+
+    if import.mesh.face_count() == 0 {
+        return Err(PlyError::new("the file has no faces"));
+    }
+    import.mesh.recalculate_tangents();
+    Ok(import)
+}
+
+/// One vertex as written to the file.
+struct PlyVertex {
+    position: Vec3,
+    normal: Vec3,
+    uv: Vec2,
+    color: [u8; 4],
+}
+
+impl PlyVertex {
+    fn floats(&self) -> [f32; 8] {
+        [
+            self.position.x,
+            self.position.y,
+            self.position.z,
+            self.normal.x,
+            self.normal.y,
+            self.normal.z,
+            self.uv.x,
+            self.uv.y,
+        ]
+    }
+
+    /// What tells the corners of one mesh vertex apart.
+    fn attribute_bits(&self) -> [u32; 6] {
+        [
+            self.normal.x.to_bits(),
+            self.normal.y.to_bits(),
+            self.normal.z.to_bits(),
+            self.uv.x.to_bits(),
+            self.uv.y.to_bits(),
+            u32::from_le_bytes(self.color),
+        ]
+    }
+}
+
+fn color_bytes(color: Color) -> [u8; 4] {
+    let byte = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+    [byte(color.r), byte(color.g), byte(color.b), byte(color.a)]
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum ScalarType {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl ScalarType {
+    fn parse(name: &str) -> Option<ScalarType> {
+        match name {
+            "char" | "int8" => Some(ScalarType::I8),
+            "uchar" | "uint8" => Some(ScalarType::U8),
+            "short" | "int16" => Some(ScalarType::I16),
+            "ushort" | "uint16" => Some(ScalarType::U16),
+            "int" | "int32" => Some(ScalarType::I32),
+            "uint" | "uint32" => Some(ScalarType::U32),
+            "float" | "float32" => Some(ScalarType::F32),
+            "double" | "float64" => Some(ScalarType::F64),
+            _ => None,
+        }
+    }
+
+    fn size(self) -> usize {
+        match self {
+            ScalarType::I8 | ScalarType::U8 => 1,
+            ScalarType::I16 | ScalarType::U16 => 2,
+            ScalarType::I32 | ScalarType::U32 | ScalarType::F32 => 4,
+            ScalarType::F64 => 8,
+        }
+    }
+
+    /// What a color component of this type is divided by to get 0 to 1.
+    fn color_scale(self) -> f32 {
+        match self {
+            ScalarType::U16 => u16::MAX as f32,
+            ScalarType::F32 | ScalarType::F64 => 1.0,
+            _ => u8::MAX as f32,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum PropertyKind {
+    Scalar(ScalarType),
+    /// A list with the type of its length, then of its items.
+    List(ScalarType, ScalarType),
+}
+
+#[derive(Clone, Debug)]
+struct Property {
+    name: String,
+    kind: PropertyKind,
+}
+
+#[derive(Clone, Debug)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+struct Header {
+    format: PlyFormat,
+    elements: Vec<Element>,
+}
+
+/// Parses the header, returning it with the offset of the data after it.
+fn read_header(bytes: &[u8]) -> Result<(Header, usize), PlyError> {
+    let mut format = None;
+    let mut elements: Vec<Element> = Vec::new();
+    let mut offset = 0;
+    let mut first = true;
+
+    loop {
+        let end = bytes[offset..]
+            .iter()
+            .position(|&byte| byte == b'\n')
+            .map(|end| offset + end)
+            .ok_or_else(|| PlyError::new("the header has no end_header"))?;
+        let line = String::from_utf8_lossy(&bytes[offset..end]);
+        offset = end + 1;
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+
+        if first {
+            if tokens != ["ply"] {
+                return Err(PlyError::new("not a PLY file"));
+            }
+            first = false;
+            continue;
+        }
+        match tokens.as_slice() {
+            ["format", "ascii", _] => format = Some(PlyFormat::Ascii),
+            ["format", "binary_little_endian", _] => format = Some(PlyFormat::BinaryLittleEndian),
+            ["format", other, ..] => return Err(PlyError::new(format!("the {} format isn't supported", other))),
+            ["element", name, count] => elements.push(Element {
+                name: name.to_string(),
+                count: count
+                    .parse()
+                    .map_err(|_| PlyError::new(format!("element {} has an invalid count '{}'", name, count)))?,
+                properties: Vec::new(),
+            }),
+            ["property", "list", count, item, name] => {
+                let kind = PropertyKind::List(scalar_type(count)?, scalar_type(item)?);
+                push_property(&mut elements, name, kind)?;
+            }
+            ["property", scalar, name] => {
+                let kind = PropertyKind::Scalar(scalar_type(scalar)?);
+                push_property(&mut elements, name, kind)?;
+            }
+            ["end_header"] => break,
+            ["comment", ..] | ["obj_info", ..] | [] => (),
+            _ => return Err(PlyError::new(format!("unknown header line '{}'", line.trim()))),
+        }
+    }
+
+    let format = format.ok_or_else(|| PlyError::new("the header has no format"))?;
+    Ok((Header { format, elements }, offset))
+}
+
+fn scalar_type(name: &str) -> Result<ScalarType, PlyError> {
+    ScalarType::parse(name).ok_or_else(|| PlyError::new(format!("unknown property type '{}'", name)))
+}
+
+fn push_property(elements: &mut [Element], name: &str, kind: PropertyKind) -> Result<(), PlyError> {
+    let element = elements
+        .last_mut()
+        .ok_or_else(|| PlyError::new(format!("property {} comes before any element", name)))?;
+    element.properties.push(Property {
+        name: name.to_string(),
+        kind,
+    });
+    Ok(())
+}
+
+/// The data after the header, read one value at a time.
+enum Body<'a> {
+    Ascii(std::str::SplitAsciiWhitespace<'a>),
+    Binary(&'a [u8]),
+}
+
+impl<'a> Body<'a> {
+    fn read(&mut self, scalar: ScalarType) -> Result<f64, PlyError> {
+        match self {
+            Body::Ascii(tokens) => {
+                let token = tokens.next().ok_or_else(|| PlyError::new("the file ends early"))?;
+                match token.parse::<f64>() {
+                    Ok(value) if value.is_finite() => Ok(value),
+                    _ => Err(PlyError::new(format!("'{}' is not a number", token))),
+                }
+            }
+            Body::Binary(bytes) => {
+                let size = scalar.size();
+                if bytes.len() < size {
+                    return Err(PlyError::new("the file ends early"));
+                }
+                let (value, rest) = bytes.split_at(size);
+                *bytes = rest;
+                let value = match scalar {
+                    ScalarType::I8 => value[0] as i8 as f64,
+                    ScalarType::U8 => value[0] as f64,
+                    ScalarType::I16 => i16::from_le_bytes([value[0], value[1]]) as f64,
+                    ScalarType::U16 => u16::from_le_bytes([value[0], value[1]]) as f64,
+                    ScalarType::I32 => i32::from_le_bytes([value[0], value[1], value[2], value[3]]) as f64,
+                    ScalarType::U32 => u32::from_le_bytes([value[0], value[1], value[2], value[3]]) as f64,
+                    ScalarType::F32 => f32::from_le_bytes([value[0], value[1], value[2], value[3]]) as f64,
+                    ScalarType::F64 => f64::from_le_bytes([
+                        value[0], value[1], value[2], value[3], value[4], value[5], value[6], value[7],
+                    ]),
+                };
+                if value.is_finite() {
+                    Ok(value)
+                } else {
+                    Err(PlyError::new("the file has a value that isn't a number"))
+                }
+            }
+        }
+    }
+
+    /// Reads the length of a list, which has to be a whole number that
+    /// isn't negative.
+    fn read_len(&mut self, count: ScalarType) -> Result<usize, PlyError> {
+        let len = self.read(count)?;
+        if len < 0.0 || len.fract() != 0.0 {
+            return Err(PlyError::new(format!("{} is not a valid list length", len)));
+        }
+        Ok(len as usize)
+    }
+
+    /// Reads past one value of a property that isn't used.
+    fn skip(&mut self, kind: PropertyKind) -> Result<(), PlyError> {
+        match kind {
+            PropertyKind::Scalar(scalar) => {
+                self.read(scalar)?;
+            }
+            PropertyKind::List(count, item) => {
+                let len = self.read_len(count)?;
+                for _ in 0..len {
+                    self.read(item)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poly_mesh::tests::cube;
+
+    /// The corner positions of every face, which don't depend on the
+    /// order vertices were added in.
+    fn face_positions(mesh: &PolyMesh) -> Vec<Vec<Vec3>> {
+        (0..mesh.face_count())
+            .map(|face| mesh.face_vertices(face).map(|vertex| mesh.position(vertex)).collect())
+            .collect()
+    }
+
+    fn colored_cube() -> PolyMesh {
+        let mut mesh = cube();
+        let l = mesh.face_loops(2).start;
+        mesh.loop_attributes_mut(l).uv = Vec2::new(0.25, 0.75);
+        mesh.loop_attributes_mut(l).color = Color::rgba(1.0, 0.0, 0.0, 1.0);
+        mesh.recalculate_tangents();
+        mesh
+    }
+
+    #[test]
+    fn cube_reads_back_in_both_formats() {
+        let mesh = colored_cube();
+        let objects = [PlyObject {
+            mesh: &mesh,
+            transform: Transform::identity(),
+        }];
+        for &format in &[PlyFormat::Ascii, PlyFormat::BinaryLittleEndian] {
+            let bytes = write_ply(&objects, format);
+            let import = read_ply(&bytes).unwrap();
+            let read = import.mesh;
+
+            assert_eq!(import.skipped_faces, 0);
+            assert_eq!(read.vertex_count(), 8);
+            assert_eq!(face_positions(&read), face_positions(&mesh));
+            let l = read.face_loops(2).start;
+            assert_eq!(read.loop_attributes(l), mesh.loop_attributes(mesh.face_loops(2).start));
+            assert_eq!(read.loop_attributes(l + 1).color, Color::white());
+        }
+    }
+
+    #[test]
+    fn corners_with_different_attributes_are_split() {
+        let mesh = cube();
+        let bytes = write_ply(&[PlyObject { mesh: &mesh, transform: Transform::identity() }], PlyFormat::Ascii);
+        let text = String::from_utf8(bytes).unwrap();
+
+        // Each side has its own normal, so every corner is its own vertex
+        assert!(text.contains("element vertex 24\n"));
+        assert!(text.contains("element face 6\nproperty list uchar uint vertex_indices\n"));
+        assert!(text.lines().any(|line| line == "4 0 1 2 3"));
+    }
+
+    #[test]
+    fn mirrored_faces_keep_facing_out() {
+        let mesh = cube();
+        let mut mirror = Transform::identity();
+        mirror.x = Vec3::new(-1.0, 0.0, 0.0);
+        let bytes = write_ply(&[PlyObject { mesh: &mesh, transform: mirror }], PlyFormat::BinaryLittleEndian);
+        let read = read_ply(&bytes).unwrap().mesh;
+
+        for face in 0..read.face_count() {
+            let l = read.face_loops(face).start;
+            assert!(read.face_normal(face).dot(read.loop_attributes(l).normal) > 0.99);
+        }
+    }
+
+    #[test]
+    fn files_from_other_tools_are_read() {
+        let text = "ply\n\
+            format ascii 1.0\n\
+            comment made by a scanner\n\
+            element vertex 5\n\
+            property float x\n\
+            property float y\n\
+            property float z\n\
+            property float texture_u\n\
+            property float texture_v\n\
+            property uchar red\n\
+            property uchar green\n\
+            property uchar blue\n\
+            element face 2\n\
+            property uchar flags\n\
+            property list uchar int vertex_index\n\
+            element edge 1\n\
+            property int vertex1\n\
+            property int vertex2\n\
+            end_header\n\
+            0 0 0 0 1 255 0 0\n\
+            1 0 0 1 1 0 255 0\n\
+            1 0 -1 1 0 0 0 255\n\
+            0 0 -1 0 0 255 255 255\n\
+            2 0 0 0 0 0 0 0\n\
+            0 4 0 1 2 3\n\
+            0 3 1 4 2\n\
+            0 1\n";
+        let mesh = read_ply(text.as_bytes()).unwrap().mesh;
+
+        assert_eq!(mesh.face_count(), 2);
+        assert_eq!(mesh.face_len(0), 4);
+        // The faces share an edge
+        assert_eq!(mesh.vertex_count(), 5);
+        let attributes = mesh.loop_attributes(1);
+        assert_eq!(attributes.color, Color::rgba(0.0, 1.0, 0.0, 1.0));
+        assert_eq!(attributes.uv, Vec2::new(1.0, 0.0));
+        assert!((attributes.normal - Vec3::new(0.0, 1.0, 0.0)).length() < 1e-6);
+    }
+
+    #[test]
+    fn normals_survive_into_the_stored_arrays() {
+        let text = "ply\n\
+            format ascii 1.0\n\
+            element vertex 3\n\
+            property float x\n\
+            property float y\n\
+            property float z\n\
+            property float nx\n\
+            property float ny\n\
+            property float nz\n\
+            element face 1\n\
+            property list uchar int vertex_indices\n\
+            end_header\n\
+            0 0 0 -1 0 0\n\
+            1 0 0 1 0 0\n\
+            0 1 0 0 1 0\n\
+            3 0 1 2\n";
+        let import = read_ply(text.as_bytes()).unwrap();
+        assert!(import.has_normals);
+
+        let stored = PolyMesh::from_arrays(&import.mesh.to_arrays()).unwrap();
+        let normals: Vec<Vec3> = stored.face_loops(0).map(|l| stored.loop_attributes(l).normal).collect();
+        let expected = [Vec3::new(-1.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0)];
+        assert_eq!(normals, expected);
+    }
+
+    #[test]
+    fn faces_through_a_vertex_twice_are_skipped() {
+        // The fourth vertex welds with the second, pinching the first face
+        // into a bowtie
+        let text = "ply\n\
+            format ascii 1.0\n\
+            element vertex 5\n\
+            property float x\n\
+            property float y\n\
+            property float z\n\
+            element face 2\n\
+            property list uchar int vertex_indices\n\
+            end_header\n\
+            0 0 0\n\
+            1 0 0\n\
+            2 1 0\n\
+            1 0 0\n\
+            0 1 0\n\
+            5 0 1 2 3 4\n\
+            3 0 1 4\n";
+        let import = read_ply(text.as_bytes()).unwrap();
+
+        assert_eq!(import.skipped_faces, 1);
+        assert_eq!(import.mesh.face_count(), 1);
+    }
+
+    #[test]
+    fn invalid_files_are_reported() {
+        assert_eq!(read_ply(b"solid cube\n").unwrap_err().message, "not a PLY file");
+        let big_endian = b"ply\nformat binary_big_endian 1.0\nend_header\n";
+        assert!(read_ply(big_endian).unwrap_err().message.contains("isn't supported"));
+        let out_of_range = b"ply\nformat ascii 1.0\nelement vertex 3\nproperty float x\nproperty float y\nproperty float z\n\
+            element face 1\nproperty list uchar int vertex_indices\nend_header\n0 0 0\n1 0 0\n0 1 0\n3 0 1 7\n";
+        assert_eq!(read_ply(out_of_range).unwrap_err().message, "face 0 uses vertex 7, but there are only 3");
+        let negative = b"ply\nformat ascii 1.0\nelement vertex 3\nproperty float x\nproperty float y\nproperty float z\n\
+            element face 2\nproperty list uchar int vertex_indices\nend_header\n0 0 0\n1 0 0\n0 1 0\n3 0 1 2\n3 1 2 -3\n";
+        assert_eq!(read_ply(negative).unwrap_err().message, "face 1 has an invalid vertex index");
+        let fractional = b"ply\nformat ascii 1.0\nelement vertex 3\nproperty float x\nproperty float y\nproperty float z\n\
+            element face 1\nproperty list uchar float vertex_indices\nend_header\n0 0 0\n1 0 0\n0 1 0\n3 0 1.5 2\n";
+        assert_eq!(read_ply(fractional).unwrap_err().message, "face 0 has an invalid vertex index");
+        let mut binary = b"ply\nformat binary_little_endian 1.0\nelement vertex 3\nproperty float x\nproperty float y\n\
+            property float z\nelement face 1\nproperty list uchar int vertex_indices\nend_header\n"
+            .to_vec();
+        for value in &[0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0] {
+            binary.extend_from_slice(&value.to_le_bytes());
+        }
+        binary.push(3);
+        for index in &[0i32, 1, -1] {
+            binary.extend_from_slice(&index.to_le_bytes());
+        }
+        assert_eq!(read_ply(&binary).unwrap_err().message, "face 0 has an invalid vertex index");
+        let points = b"ply\nformat ascii 1.0\nelement vertex 1\nproperty float x\nproperty float y\nproperty float z\nend_header\n0 0 0\n";
+        assert_eq!(read_ply(points).unwrap_err().message, "the file has no faces");
+    }
+
+    #[test]
+    fn counts_past_the_data_are_reported() {
+        let vertices = b"ply\nformat ascii 1.0\nelement vertex 18446744073709551615\nproperty float x\nproperty float y\n\
+            property float z\nend_header\n0 0 0\n";
+        assert_eq!(read_ply(vertices).unwrap_err().message, "the file ends early");
+        let faces = b"ply\nformat ascii 1.0\nelement vertex 3\nproperty float x\nproperty float y\nproperty float z\n\
+            element face 18446744073709551615\nproperty list uchar int vertex_indices\nend_header\n0 0 0\n1 0 0\n0 1 0\n3 0 1 2\n";
+        assert_eq!(read_ply(faces).unwrap_err().message, "the file ends early");
+        // Nothing to read, so the count doesn't matter
+        let empty = b"ply\nformat ascii 1.0\nelement vertex 3\nproperty float x\nproperty float y\nproperty float z\n\
+            element face 1\nproperty list uchar int vertex_indices\nelement nothing 18446744073709551615\nend_header\n\
+            0 0 0\n1 0 0\n0 1 0\n3 0 1 2\n";
+        assert_eq!(read_ply(empty).unwrap().mesh.face_count(), 1);
+    }
+
+    #[test]
+    fn list_lengths_past_the_data_are_reported() {
+        let mut binary = b"ply\nformat binary_little_endian 1.0\nelement vertex 3\nproperty float x\nproperty float y\n\
+            property float z\nelement face 1\nproperty list uint int vertex_indices\nend_header\n"
+            .to_vec();
+        for value in &[0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0] {
+            binary.extend_from_slice(&value.to_le_bytes());
+        }
+        binary.extend_from_slice(&u32::MAX.to_le_bytes());
+        binary.extend_from_slice(&0i32.to_le_bytes());
+        assert_eq!(read_ply(&binary).unwrap_err().message, "the file ends early");
+
+        let header = "ply\nformat ascii 1.0\nelement vertex 3\nproperty float x\nproperty float y\nproperty float z\n\
+            element face 1\nproperty list int int vertex_indices\nend_header\n0 0 0\n1 0 0\n0 1 0\n";
+        for (face, message) in [
+            ("4294967295 0 1 2\n", "the file ends early"),
+            ("-3 0 1 2\n", "-3 is not a valid list length"),
+            ("2.5 0 1 2\n", "2.5 is not a valid list length"),
+        ] {
+            let text = format!("{}{}", header, face);
+            assert_eq!(read_ply(text.as_bytes()).unwrap_err().message, message);
+        }
+    }
+}
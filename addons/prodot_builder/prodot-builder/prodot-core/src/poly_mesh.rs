@@ -0,0 +1,1047 @@
+use crate::math::{Color, Tangent, Vec2, Vec3, CMP_EPSILON};
+
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Range;
+
+pub type VertexId = usize;
+pub type EdgeId = usize;
+pub type LoopId = usize;
+pub type FaceId = usize;
+
+/// A shared corner position. Every face that uses the vertex does so
+/// through one of its loops, so moving it keeps those faces connected.
+#[derive(Clone, Debug)]
+pub struct Vertex {
+    pub position: Vec3,
+    edges: Vec<EdgeId>,
+}
+
+/// An undirected edge between two vertices. The radial list holds every
+/// loop that runs along the edge, which lets non-manifold edges exist
+/// without breaking adjacency queries.
+#[derive(Clone, Debug)]
+pub struct Edge {
+    pub vertices: [VertexId; 2],
+    loops: Vec<LoopId>,
+}
+
+/// Shading data of one face corner. Positions are shared through the
+/// vertex, but each face keeps its own normals, UVs, colors and tangents.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct LoopAttributes {
+    pub normal: Vec3,
+    pub uv: Vec2,
+    pub color: Color,
+    pub tangent: Tangent,
+}
+
+/// One corner of a face (a half-edge). It starts at `vertex` and runs
+/// along `edge` to the vertex of the next loop in the face.
+#[derive(Copy, Clone, Debug)]
+pub struct Loop {
+    pub vertex: VertexId,
+    pub edge: EdgeId,
+    pub face: FaceId,
+    pub attributes: LoopAttributes,
+}
+
+/// A polygon. Its loops are stored contiguously, wound counter-clockwise
+/// when looking at the front of the face.
+#[derive(Copy, Clone, Debug)]
+pub struct Face {
+    loop_start: LoopId,
+    loop_count: usize,
+    /// Index into the material list of the mesh.
+    pub material: usize,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TopologyError {
+    TooFewVertices(usize),
+    InvalidVertex(VertexId),
+    RepeatedVertex(VertexId),
+    /// The face sizes of a `MeshArrays` don't add up to its index count.
+    FaceIndexMismatch { expected: usize, found: usize },
+    /// A stored per-corner attribute array doesn't have one entry per loop.
+    LoopAttributeMismatch { expected: usize, found: usize },
+    /// The stored material slots don't have one entry per face.
+    FaceMaterialMismatch { expected: usize, found: usize },
+}
+
+impl fmt::Display for TopologyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            TopologyError::TooFewVertices(count) => {
+                write!(f, "a face needs at least 3 vertices, got {}", count)
+            }
+            TopologyError::InvalidVertex(id) => write!(f, "vertex {} does not exist", id),
+            TopologyError::RepeatedVertex(id) => write!(f, "vertex {} is used twice in one face", id),
+            TopologyError::FaceIndexMismatch { expected, found } => {
+                write!(f, "face sizes need {} vertex indices, found {}", expected, found)
+            }
+            TopologyError::LoopAttributeMismatch { expected, found } => {
+                write!(f, "expected {} face corner attributes, found {}", expected, found)
+            }
+            TopologyError::FaceMaterialMismatch { expected, found } => {
+                write!(f, "expected {} face material slots, found {}", expected, found)
+            }
+        }
+    }
+}
+
+/// Flat form of a `PolyMesh` used for storage: vertex positions, the
+/// number of corners of each face, and every face's vertex indices laid
+/// out back to back. The corner attributes follow `face_vertices` and the
+/// material slots follow `face_sizes`; an empty array means the attribute
+/// wasn't stored and gets its default.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MeshArrays {
+    pub positions: Vec<Vec3>,
+    pub face_sizes: Vec<usize>,
+    pub face_materials: Vec<usize>,
+    pub face_vertices: Vec<VertexId>,
+    pub normals: Vec<Vec3>,
+    pub uvs: Vec<Vec2>,
+    pub colors: Vec<Color>,
+    pub tangents: Vec<Tangent>,
+}
+
+/// Polygonal mesh topology made of vertices, edges, faces and face loops,
+/// all addressed by integer ids.
+#[derive(Clone, Debug, Default)]
+pub struct PolyMesh {
+    vertices: Vec<Vertex>,
+    edges: Vec<Edge>,
+    loops: Vec<Loop>,
+    faces: Vec<Face>,
+    edge_lookup: HashMap<(VertexId, VertexId), EdgeId>,
+}
+
+/// Gram-Schmidt orthogonalizes `tangent` against `normal`. Falls back to
+/// any direction perpendicular to the normal when the UVs are degenerate.
+fn orthogonal_tangent(normal: Vec3, tangent: Vec3, bitangent: Vec3) -> Tangent {
+    let mut direction = (tangent - normal * normal.dot(tangent)).normalized();
+    if direction == Vec3::zero() {
+        let axis = if normal.x.abs() < 0.9 {
+            Vec3::new(1.0, 0.0, 0.0)
+        } else {
+            Vec3::new(0.0, 1.0, 0.0)
+        };
+        direction = (axis - normal * normal.dot(axis)).normalized();
+    }
+
+    let sign = if normal.cross(direction).dot(bitangent) < 0.0 {
+        -1.0
+    } else {
+        1.0
+    };
+    Tangent::new(direction, sign)
+}
+
+impl PolyMesh {
+    pub fn new() -> Self {
+        PolyMesh::default()
+    }
+
+    /// Rebuilds the topology from its flat storage form.
+    pub fn from_arrays(arrays: &MeshArrays) -> Result<Self, TopologyError> {
+        let expected: usize = arrays.face_sizes.iter().sum();
+        if expected != arrays.face_vertices.len() {
+            return Err(TopologyError::FaceIndexMismatch {
+                expected,
+                found: arrays.face_vertices.len(),
+            });
+        }
+
+        let loop_count = arrays.face_vertices.len();
+        for found in [
+            arrays.normals.len(),
+            arrays.uvs.len(),
+            arrays.colors.len(),
+            arrays.tangents.len(),
+        ] {
+            if found != 0 && found != loop_count {
+                return Err(TopologyError::LoopAttributeMismatch {
+                    expected: loop_count,
+                    found,
+                });
+            }
+        }
+
+        let face_count = arrays.face_sizes.len();
+        if !arrays.face_materials.is_empty() && arrays.face_materials.len() != face_count {
+            return Err(TopologyError::FaceMaterialMismatch {
+                expected: face_count,
+                found: arrays.face_materials.len(),
+            });
+        }
+
+        let mut mesh = PolyMesh::new();
+        for &position in &arrays.positions {
+            mesh.add_vertex(position);
+        }
+
+        let mut start = 0;
+        for &size in &arrays.face_sizes {
+            mesh.add_face(&arrays.face_vertices[start..start + size])?;
+            start += size;
+        }
+
+        for (face, &material) in mesh.faces.iter_mut().zip(arrays.face_materials.iter()) {
+            face.material = material;
+        }
+
+        for (l, corner) in mesh.loops.iter_mut().enumerate() {
+            let attributes = &mut corner.attributes;
+            if let Some(&normal) = arrays.normals.get(l) {
+                attributes.normal = normal;
+            }
+            if let Some(&uv) = arrays.uvs.get(l) {
+                attributes.uv = uv;
+            }
+            if let Some(&color) = arrays.colors.get(l) {
+                attributes.color = color;
+            }
+            if let Some(&tangent) = arrays.tangents.get(l) {
+                attributes.tangent = tangent;
+            }
+        }
+
+        Ok(mesh)
+    }
+
+    /// Flattens the topology for storage. Edges are not stored since
+    /// `from_arrays` derives them from the faces again.
+    pub fn to_arrays(&self) -> MeshArrays {
+        MeshArrays {
+            positions: self.positions().collect(),
+            face_sizes: self.faces.iter().map(|face| face.loop_count).collect(),
+            face_materials: self.faces.iter().map(|face| face.material).collect(),
+            face_vertices: self.loops.iter().map(|l| l.vertex).collect(),
+            normals: self.loops.iter().map(|l| l.attributes.normal).collect(),
+            uvs: self.loops.iter().map(|l| l.attributes.uv).collect(),
+            colors: self.loops.iter().map(|l| l.attributes.color).collect(),
+            tangents: self.loops.iter().map(|l| l.attributes.tangent).collect(),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        *self = PolyMesh::default();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.vertices.is_empty()
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.vertices.len()
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.edges.len()
+    }
+
+    pub fn loop_count(&self) -> usize {
+        self.loops.len()
+    }
+
+    pub fn face_count(&self) -> usize {
+        self.faces.len()
+    }
+
+    pub fn vertex(&self, id: VertexId) -> &Vertex {
+        &self.vertices[id]
+    }
+
+    pub fn edge(&self, id: EdgeId) -> &Edge {
+        &self.edges[id]
+    }
+
+    pub fn get_loop(&self, id: LoopId) -> &Loop {
+        &self.loops[id]
+    }
+
+    pub fn face(&self, id: FaceId) -> &Face {
+        &self.faces[id]
+    }
+
+    pub fn face_material(&self, id: FaceId) -> usize {
+        self.faces[id].material
+    }
+
+    pub fn set_face_material(&mut self, id: FaceId, material: usize) {
+        self.faces[id].material = material;
+    }
+
+    /// Highest material slot used by a face plus one, so every face has a
+    /// slot in a material list of this length.
+    pub fn material_count(&self) -> usize {
+        self.faces.iter().map(|face| face.material + 1).max().unwrap_or(0)
+    }
+
+    pub fn loop_attributes(&self, id: LoopId) -> &LoopAttributes {
+        &self.loops[id].attributes
+    }
+
+    pub fn loop_attributes_mut(&mut self, id: LoopId) -> &mut LoopAttributes {
+        &mut self.loops[id].attributes
+    }
+
+    pub fn position(&self, id: VertexId) -> Vec3 {
+        self.vertices[id].position
+    }
+
+    pub fn set_position(&mut self, id: VertexId, position: Vec3) {
+        self.vertices[id].position = position;
+    }
+
+    pub fn positions(&self) -> impl Iterator<Item = Vec3> + '_ {
+        self.vertices.iter().map(|vertex| vertex.position)
+    }
+
+    pub fn add_vertex(&mut self, position: Vec3) -> VertexId {
+        self.vertices.push(Vertex {
+            position,
+            edges: Vec::new(),
+        });
+        self.vertices.len() - 1
+    }
+
+    /// Adds a polygon through `vertices`, given in counter-clockwise order.
+    /// Edges shared with existing faces are reused. The face uses material
+    /// slot 0 and its corners start out with the face normal and default
+    /// UVs, colors and tangents.
+    pub fn add_face(&mut self, vertices: &[VertexId]) -> Result<FaceId, TopologyError> {
+        if vertices.len() < 3 {
+            return Err(TopologyError::TooFewVertices(vertices.len()));
+        }
+        for (i, &vertex) in vertices.iter().enumerate() {
+            if vertex >= self.vertices.len() {
+                return Err(TopologyError::InvalidVertex(vertex));
+            }
+            if vertices[..i].contains(&vertex) {
+                return Err(TopologyError::RepeatedVertex(vertex));
+            }
+        }
+
+        let face = self.faces.len();
+        let loop_start = self.loops.len();
+        for i in 0..vertices.len() {
+            let from = vertices[i];
+            let to = vertices[(i + 1) % vertices.len()];
+            let edge = self.find_or_add_edge(from, to);
+            self.edges[edge].loops.push(loop_start + i);
+            self.loops.push(Loop {
+                vertex: from,
+                edge,
+                face,
+                attributes: LoopAttributes::default(),
+            });
+        }
+        self.faces.push(Face {
+            loop_start,
+            loop_count: vertices.len(),
+            material: 0,
+        });
+
+        let normal = self.face_normal(face);
+        for l in self.face_loops(face) {
+            self.loops[l].attributes.normal = normal;
+        }
+
+        Ok(face)
+    }
+
+    fn find_or_add_edge(&mut self, a: VertexId, b: VertexId) -> EdgeId {
+        let key = if a < b { (a, b) } else { (b, a) };
+        if let Some(&edge) = self.edge_lookup.get(&key) {
+            return edge;
+        }
+
+        let edge = self.edges.len();
+        self.edges.push(Edge {
+            vertices: [a, b],
+            loops: Vec::new(),
+        });
+        self.vertices[a].edges.push(edge);
+        self.vertices[b].edges.push(edge);
+        self.edge_lookup.insert(key, edge);
+        edge
+    }
+
+    pub fn find_edge(&self, a: VertexId, b: VertexId) -> Option<EdgeId> {
+        let key = if a < b { (a, b) } else { (b, a) };
+        self.edge_lookup.get(&key).copied()
+    }
+
+    pub fn face_loops(&self, face: FaceId) -> Range<LoopId> {
+        let face = &self.faces[face];
+        face.loop_start..face.loop_start + face.loop_count
+    }
+
+    pub fn face_vertices(&self, face: FaceId) -> impl Iterator<Item = VertexId> + '_ {
+        self.face_loops(face).map(move |l| self.loops[l].vertex)
+    }
+
+    pub fn face_edges(&self, face: FaceId) -> impl Iterator<Item = EdgeId> + '_ {
+        self.face_loops(face).map(move |l| self.loops[l].edge)
+    }
+
+    pub fn face_len(&self, face: FaceId) -> usize {
+        self.faces[face].loop_count
+    }
+
+    pub fn loop_next(&self, id: LoopId) -> LoopId {
+        let face = &self.faces[self.loops[id].face];
+        if id + 1 == face.loop_start + face.loop_count {
+            face.loop_start
+        } else {
+            id + 1
+        }
+    }
+
+    pub fn loop_prev(&self, id: LoopId) -> LoopId {
+        let face = &self.faces[self.loops[id].face];
+        if id == face.loop_start {
+            face.loop_start + face.loop_count - 1
+        } else {
+            id - 1
+        }
+    }
+
+    pub fn edge_loops(&self, edge: EdgeId) -> &[LoopId] {
+        &self.edges[edge].loops
+    }
+
+    /// Faces that share `edge`.
+    pub fn edge_faces(&self, edge: EdgeId) -> Vec<FaceId> {
+        self.edges[edge]
+            .loops
+            .iter()
+            .map(|&l| self.loops[l].face)
+            .collect()
+    }
+
+    pub fn vertex_edges(&self, vertex: VertexId) -> &[EdgeId] {
+        &self.vertices[vertex].edges
+    }
+
+    /// Loops (face corners) that sit on `vertex`.
+    pub fn vertex_loops(&self, vertex: VertexId) -> Vec<LoopId> {
+        let mut loops = Vec::new();
+        for &edge in &self.vertices[vertex].edges {
+            for &l in &self.edges[edge].loops {
+                if self.loops[l].vertex == vertex {
+                    loops.push(l);
+                }
+            }
+        }
+        loops
+    }
+
+    /// Faces that use `vertex` as one of their corners.
+    pub fn vertex_faces(&self, vertex: VertexId) -> Vec<FaceId> {
+        let mut faces: Vec<FaceId> = self
+            .vertex_loops(vertex)
+            .into_iter()
+            .map(|l| self.loops[l].face)
+            .collect();
+        faces.sort_unstable();
+        faces.dedup();
+        faces
+    }
+
+    /// Vertices connected to `vertex` by an edge.
+    pub fn vertex_neighbours(&self, vertex: VertexId) -> Vec<VertexId> {
+        self.vertices[vertex]
+            .edges
+            .iter()
+            .map(|&edge| {
+                let [a, b] = self.edges[edge].vertices;
+                if a == vertex {
+                    b
+                } else {
+                    a
+                }
+            })
+            .collect()
+    }
+
+    /// Faces that share at least one edge with `face`.
+    pub fn face_neighbours(&self, face: FaceId) -> Vec<FaceId> {
+        let mut faces = Vec::new();
+        for edge in self.face_edges(face) {
+            for other in self.edge_faces(edge) {
+                if other != face && !faces.contains(&other) {
+                    faces.push(other);
+                }
+            }
+        }
+        faces
+    }
+
+    /// An edge is on the boundary when only one face uses it.
+    pub fn is_boundary_edge(&self, edge: EdgeId) -> bool {
+        self.edges[edge].loops.len() < 2
+    }
+
+    /// An edge is non-manifold when more than two faces use it.
+    pub fn is_manifold_edge(&self, edge: EdgeId) -> bool {
+        self.edges[edge].loops.len() <= 2
+    }
+
+    pub fn is_boundary_vertex(&self, vertex: VertexId) -> bool {
+        self.vertices[vertex].edges.is_empty()
+            || self.vertices[vertex]
+                .edges
+                .iter()
+                .any(|&edge| self.is_boundary_edge(edge))
+    }
+
+    pub fn boundary_edges(&self) -> Vec<EdgeId> {
+        (0..self.edges.len())
+            .filter(|&edge| self.is_boundary_edge(edge))
+            .collect()
+    }
+
+    /// Average of the face's corner positions.
+    pub fn face_center(&self, face: FaceId) -> Vec3 {
+        let mut sum = Vec3::zero();
+        for vertex in self.face_vertices(face) {
+            sum += self.vertices[vertex].position;
+        }
+        sum / self.face_len(face) as f32
+    }
+
+    /// Face normal computed with Newell's method, so it is stable for
+    /// quads and n-gons that aren't perfectly planar.
+    pub fn face_normal(&self, face: FaceId) -> Vec3 {
+        let mut normal = Vec3::zero();
+        for l in self.face_loops(face) {
+            let current = self.vertices[self.loops[l].vertex].position;
+            let next = self.vertices[self.loops[self.loop_next(l)].vertex].position;
+            normal.x += (current.y - next.y) * (current.z + next.z);
+            normal.y += (current.z - next.z) * (current.x + next.x);
+            normal.z += (current.x - next.x) * (current.y + next.y);
+        }
+        normal.normalized()
+    }
+
+    /// Average of the normals of the faces around `vertex`.
+    pub fn vertex_normal(&self, vertex: VertexId) -> Vec3 {
+        let mut normal = Vec3::zero();
+        for face in self.vertex_faces(vertex) {
+            normal += self.face_normal(face);
+        }
+        normal.normalized()
+    }
+
+    /// Splits the face into triangles that keep its counter-clockwise
+    /// winding. Convex faces become a fan around their first corner, and
+    /// concave ones are split by ear clipping so no triangle falls
+    /// outside the face.
+    pub fn face_triangles(&self, face: FaceId) -> Vec<[VertexId; 3]> {
+        self.face_loop_triangles(face)
+            .into_iter()
+            .map(|triangle| triangle.map(|l| self.loops[l].vertex))
+            .collect()
+    }
+
+    /// Same triangles as `face_triangles`, but made of the face's loops so
+    /// the corner attributes can be looked up.
+    pub fn face_loop_triangles(&self, face: FaceId) -> Vec<[LoopId; 3]> {
+        let loops = self.face_loops(face);
+        let normal = self.face_normal(face);
+        let position = |l: LoopId| self.vertices[self.loops[l].vertex].position;
+        // Positive when the corner at `b` turns the same way as the face
+        let turn = |a: LoopId, b: LoopId, c: LoopId| {
+            (position(b) - position(a)).cross(position(c) - position(b)).dot(normal)
+        };
+
+        let mut corners: Vec<LoopId> = loops.clone().collect();
+        let corner = |corners: &[LoopId], i: usize| {
+            let count = corners.len();
+            [corners[(i + count - 1) % count], corners[i], corners[(i + 1) % count]]
+        };
+        let convex = (0..corners.len()).all(|i| {
+            let [a, b, c] = corner(&corners, i);
+            turn(a, b, c) >= -CMP_EPSILON
+        });
+        if convex {
+            return (loops.start + 1..loops.end - 1)
+                .map(|l| [loops.start, l, l + 1])
+                .collect();
+        }
+
+        let mut triangles = Vec::with_capacity(corners.len() - 2);
+        while corners.len() > 3 {
+            // An ear is a convex corner whose triangle holds no other corner
+            let is_ear = |i: usize| {
+                let [a, b, c] = corner(&corners, i);
+                turn(a, b, c) > CMP_EPSILON
+                    && corners.iter().all(|&l| {
+                        l == a || l == b || l == c || {
+                            let p = position(l);
+                            let [pa, pb, pc] = [position(a), position(b), position(c)];
+                            (pb - pa).cross(p - pa).dot(normal) < 0.0
+                                || (pc - pb).cross(p - pb).dot(normal) < 0.0
+                                || (pa - pc).cross(p - pc).dot(normal) < 0.0
+                        }
+                    })
+            };
+            // A face folded onto itself may have no clean ear left, so the
+            // sharpest corner is cut instead of giving up
+            let ear = (0..corners.len()).find(|&i| is_ear(i)).unwrap_or_else(|| {
+                (0..corners.len())
+                    .max_by(|&i, &j| {
+                        let [a, b, c] = corner(&corners, i);
+                        let [d, e, f] = corner(&corners, j);
+                        turn(a, b, c).partial_cmp(&turn(d, e, f)).unwrap_or(std::cmp::Ordering::Equal)
+                    })
+                    .unwrap()
+            });
+            triangles.push(corner(&corners, ear));
+            corners.remove(ear);
+        }
+        triangles.push([corners[0], corners[1], corners[2]]);
+        triangles
+    }
+
+    /// Recomputes every corner tangent from the UVs and corner normals,
+    /// so normal maps follow the texture layout of each face. Corners on
+    /// the same vertex that share a normal and UV average their faces'
+    /// tangents, which keeps them identical across smooth, unseamed edges.
+    pub fn recalculate_tangents(&mut self) {
+        let vertices: Vec<VertexId> = (0..self.vertices.len()).collect();
+        self.recalculate_vertex_tangents(&vertices);
+    }
+
+    /// Recomputes the tangents of the corners on `vertices` only, see
+    /// `recalculate_tangents`.
+    pub fn recalculate_vertex_tangents(&mut self, vertices: &[VertexId]) {
+        for &vertex in vertices {
+            let loops = self.vertex_loops(vertex);
+            let face_frames: Vec<(Vec3, Vec3)> = loops
+                .iter()
+                .map(|&l| self.face_uv_frame(self.loops[l].face))
+                .collect();
+
+            let tangents: Vec<Tangent> = loops
+                .iter()
+                .map(|&l| {
+                    let attributes = self.loops[l].attributes;
+                    let mut tangent = Vec3::zero();
+                    let mut bitangent = Vec3::zero();
+                    for (&other, &(face_tangent, face_bitangent)) in loops.iter().zip(face_frames.iter()) {
+                        let other_attributes = self.loops[other].attributes;
+                        if other_attributes.normal == attributes.normal && other_attributes.uv == attributes.uv {
+                            tangent += face_tangent;
+                            bitangent += face_bitangent;
+                        }
+                    }
+                    orthogonal_tangent(attributes.normal, tangent, bitangent)
+                })
+                .collect();
+
+            for (&l, tangent) in loops.iter().zip(tangents) {
+                self.loops[l].attributes.tangent = tangent;
+            }
+        }
+    }
+
+    /// Directions of +U and +V across the face, summed over its triangles.
+    fn face_uv_frame(&self, face: FaceId) -> (Vec3, Vec3) {
+        let mut tangent = Vec3::zero();
+        let mut bitangent = Vec3::zero();
+
+        for [a, b, c] in self.face_loop_triangles(face) {
+            let (pos_a, uv_a) = self.loop_position_uv(a);
+            let (pos_b, uv_b) = self.loop_position_uv(b);
+            let (pos_c, uv_c) = self.loop_position_uv(c);
+
+            let edge_one = pos_b - pos_a;
+            let edge_two = pos_c - pos_a;
+            let delta_one = uv_b - uv_a;
+            let delta_two = uv_c - uv_a;
+
+            let det = delta_one.x * delta_two.y - delta_two.x * delta_one.y;
+            if det.abs() <= CMP_EPSILON {
+                continue;
+            }
+            tangent += (edge_one * delta_two.y - edge_two * delta_one.y) / det;
+            bitangent += (edge_two * delta_one.x - edge_one * delta_two.x) / det;
+        }
+
+        (tangent, bitangent)
+    }
+
+    fn loop_position_uv(&self, id: LoopId) -> (Vec3, Vec2) {
+        let corner = &self.loops[id];
+        (self.vertices[corner.vertex].position, corner.attributes.uv)
+    }
+
+    /// Average position of the given vertices.
+    pub fn vertices_center(&self, vertices: &[VertexId]) -> Vec3 {
+        if vertices.is_empty() {
+            return Vec3::zero();
+        }
+        let mut sum = Vec3::zero();
+        for &vertex in vertices {
+            sum += self.vertices[vertex].position;
+        }
+        sum / vertices.len() as f32
+    }
+
+    /// Average of the centers of the given faces.
+    pub fn faces_center(&self, faces: &[FaceId]) -> Vec3 {
+        if faces.is_empty() {
+            return Vec3::zero();
+        }
+        let mut sum = Vec3::zero();
+        for &face in faces {
+            sum += self.face_center(face);
+        }
+        sum / faces.len() as f32
+    }
+
+    /// Every vertex used by the given faces, without duplicates.
+    pub fn faces_vertices(&self, faces: &[FaceId]) -> Vec<VertexId> {
+        let mut vertices = Vec::new();
+        for &face in faces {
+            for vertex in self.face_vertices(face) {
+                if !vertices.contains(&vertex) {
+                    vertices.push(vertex);
+                }
+            }
+        }
+        vertices
+    }
+
+    /// Groups the faces into parts that are connected through shared
+    /// vertices. Parts are ordered by their lowest face id, and each
+    /// part's faces are sorted.
+    pub fn face_islands(&self) -> Vec<Vec<FaceId>> {
+        let mut island_of: Vec<Option<usize>> = vec![None; self.faces.len()];
+        let mut islands = Vec::new();
+
+        for start in 0..self.faces.len() {
+            if island_of[start].is_some() {
+                continue;
+            }
+            let island = islands.len();
+            let mut faces = vec![start];
+            let mut stack = vec![start];
+            island_of[start] = Some(island);
+            while let Some(face) = stack.pop() {
+                for vertex in self.face_vertices(face) {
+                    for other in self.vertex_faces(vertex) {
+                        if island_of[other].is_none() {
+                            island_of[other] = Some(island);
+                            faces.push(other);
+                            stack.push(other);
+                        }
+                    }
+                }
+            }
+            faces.sort_unstable();
+            islands.push(faces);
+        }
+
+        islands
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Unit cube with outward facing, counter-clockwise quads.
+    pub(crate) fn cube() -> PolyMesh {
+        let mut mesh = PolyMesh::new();
+        for &(x, y, z) in &[
+            (0.0, 0.0, 1.0),
+            (0.0, 1.0, 1.0),
+            (1.0, 1.0, 1.0),
+            (1.0, 0.0, 1.0),
+            (1.0, 1.0, 0.0),
+            (1.0, 0.0, 0.0),
+            (0.0, 0.0, 0.0),
+            (0.0, 1.0, 0.0),
+        ] {
+            mesh.add_vertex(Vec3::new(x, y, z));
+        }
+        for face in &[
+            [0, 3, 2, 1],
+            [3, 5, 4, 2],
+            [6, 0, 1, 7],
+            [5, 6, 7, 4],
+            [1, 2, 4, 7],
+            [6, 5, 3, 0],
+        ] {
+            mesh.add_face(face).unwrap();
+        }
+        mesh
+    }
+
+    #[test]
+    fn face_islands_split_disconnected_parts() {
+        let mut mesh = cube();
+        let ids: Vec<VertexId> = [(3.0, 0.0, 0.0), (4.0, 0.0, 0.0), (4.0, 1.0, 0.0)]
+            .iter()
+            .map(|&(x, y, z)| mesh.add_vertex(Vec3::new(x, y, z)))
+            .collect();
+        mesh.add_face(&ids).unwrap();
+
+        assert_eq!(mesh.face_islands(), vec![vec![0, 1, 2, 3, 4, 5], vec![6]]);
+    }
+
+    #[test]
+    fn cube_has_closed_topology() {
+        let mesh = cube();
+        assert_eq!(mesh.vertex_count(), 8);
+        assert_eq!(mesh.edge_count(), 12);
+        assert_eq!(mesh.loop_count(), 24);
+        assert_eq!(mesh.face_count(), 6);
+        assert!(mesh.boundary_edges().is_empty());
+        assert!((0..mesh.edge_count()).all(|edge| mesh.is_manifold_edge(edge)));
+    }
+
+    #[test]
+    fn vertex_adjacency() {
+        let mesh = cube();
+        assert_eq!(mesh.vertex_faces(0), vec![0, 2, 5]);
+        let mut neighbours = mesh.vertex_neighbours(0);
+        neighbours.sort_unstable();
+        assert_eq!(neighbours, vec![1, 3, 6]);
+        assert_eq!(mesh.vertex_loops(0).len(), 3);
+    }
+
+    #[test]
+    fn edge_and_face_neighbours() {
+        let mesh = cube();
+        let edge = mesh.find_edge(3, 2).unwrap();
+        let mut faces = mesh.edge_faces(edge);
+        faces.sort_unstable();
+        assert_eq!(faces, vec![0, 1]);
+
+        let mut neighbours = mesh.face_neighbours(0);
+        neighbours.sort_unstable();
+        assert_eq!(neighbours, vec![1, 2, 4, 5]);
+        assert_eq!(mesh.find_edge(0, 2), None);
+    }
+
+    #[test]
+    fn loops_wrap_around_their_face() {
+        let mesh = cube();
+        let loops = mesh.face_loops(1);
+        assert_eq!(mesh.loop_next(loops.end - 1), loops.start);
+        assert_eq!(mesh.loop_prev(loops.start), loops.end - 1);
+        assert_eq!(mesh.get_loop(mesh.loop_next(loops.start)).vertex, 5);
+    }
+
+    #[test]
+    fn open_quad_is_all_boundary() {
+        let mut mesh = PolyMesh::new();
+        let a = mesh.add_vertex(Vec3::new(0.0, 0.0, 0.0));
+        let b = mesh.add_vertex(Vec3::new(1.0, 0.0, 0.0));
+        let c = mesh.add_vertex(Vec3::new(1.0, 1.0, 0.0));
+        let d = mesh.add_vertex(Vec3::new(0.0, 1.0, 0.0));
+        let lonely = mesh.add_vertex(Vec3::new(5.0, 5.0, 5.0));
+        mesh.add_face(&[a, b, c, d]).unwrap();
+
+        assert_eq!(mesh.boundary_edges().len(), 4);
+        assert!(mesh.is_boundary_vertex(a));
+        assert!(mesh.is_boundary_vertex(lonely));
+        assert!(mesh.vertex_faces(lonely).is_empty());
+    }
+
+    #[test]
+    fn invalid_faces_are_rejected() {
+        let mut mesh = PolyMesh::new();
+        let a = mesh.add_vertex(Vec3::zero());
+        let b = mesh.add_vertex(Vec3::one());
+
+        assert_eq!(mesh.add_face(&[a, b]), Err(TopologyError::TooFewVertices(2)));
+        assert_eq!(mesh.add_face(&[a, b, 7]), Err(TopologyError::InvalidVertex(7)));
+        assert_eq!(mesh.add_face(&[a, b, a]), Err(TopologyError::RepeatedVertex(a)));
+        assert_eq!(mesh.face_count(), 0);
+        assert_eq!(mesh.edge_count(), 0);
+    }
+
+    #[test]
+    fn ngon_triangulates_into_a_fan() {
+        let mut mesh = PolyMesh::new();
+        let ids: Vec<VertexId> = (0..5)
+            .map(|i| {
+                let angle = i as f32 / 5.0 * std::f32::consts::PI * 2.0;
+                mesh.add_vertex(Vec3::new(angle.cos(), angle.sin(), 0.0))
+            })
+            .collect();
+        let face = mesh.add_face(&ids).unwrap();
+
+        assert_eq!(mesh.face_triangles(face), vec![[0, 1, 2], [0, 2, 3], [0, 3, 4]]);
+        assert!(mesh.face_normal(face).is_equal_approx(Vec3::new(0.0, 0.0, 1.0), 1e-6));
+    }
+
+    #[test]
+    fn concave_ngon_triangulates_inside_the_face() {
+        // An L shape whose first corner can't see the whole face
+        let mut mesh = PolyMesh::new();
+        let corners = [(2.0, 0.0), (2.0, 1.0), (1.0, 1.0), (1.0, 2.0), (0.0, 2.0), (0.0, 0.0)];
+        let ids: Vec<VertexId> = corners.iter().map(|&(x, y)| mesh.add_vertex(Vec3::new(x, y, 0.0))).collect();
+        let face = mesh.add_face(&ids).unwrap();
+
+        let triangles = mesh.face_triangles(face);
+        assert_eq!(triangles.len(), 4);
+        for [a, b, c] in triangles {
+            let [a, b, c] = [mesh.position(a), mesh.position(b), mesh.position(c)];
+            let cross = (b - a).cross(c - a);
+            assert!(cross.z > 0.0);
+            // Every triangle's center lies inside the L
+            let center = (a + b + c) / 3.0;
+            assert!(center.x < 1.0 || center.y < 1.0);
+        }
+    }
+
+    #[test]
+    fn face_normals_point_outwards() {
+        let mesh = cube();
+        assert_eq!(mesh.face_normal(0), Vec3::new(0.0, 0.0, 1.0));
+        assert_eq!(mesh.face_normal(1), Vec3::new(1.0, 0.0, 0.0));
+        assert_eq!(mesh.face_normal(4), Vec3::new(0.0, 1.0, 0.0));
+        assert_eq!(mesh.face_normal(5), Vec3::new(0.0, -1.0, 0.0));
+    }
+
+    #[test]
+    fn vertex_normal_averages_its_faces() {
+        let mesh = cube();
+        let expected = Vec3::new(-1.0, -1.0, 1.0).normalized();
+        assert!(mesh.vertex_normal(0).is_equal_approx(expected, 1e-6));
+    }
+
+    #[test]
+    fn centers() {
+        let mesh = cube();
+        assert_eq!(mesh.face_center(0), Vec3::new(0.5, 0.5, 1.0));
+        assert_eq!(mesh.faces_center(&[0, 3]), Vec3::new(0.5, 0.5, 0.5));
+        assert_eq!(mesh.vertices_center(&[0, 2]), Vec3::new(0.5, 0.5, 1.0));
+        assert_eq!(mesh.vertices_center(&[]), Vec3::zero());
+    }
+
+    #[test]
+    fn arrays_round_trip() {
+        let mesh = cube();
+        let arrays = mesh.to_arrays();
+        assert_eq!(arrays.face_sizes, vec![4; 6]);
+        assert_eq!(arrays.face_vertices[..4], [0, 3, 2, 1]);
+
+        let restored = PolyMesh::from_arrays(&arrays).unwrap();
+        assert_eq!(restored.edge_count(), mesh.edge_count());
+        assert_eq!(restored.to_arrays(), arrays);
+    }
+
+    #[test]
+    fn arrays_with_missing_indices_are_rejected() {
+        let mut arrays = cube().to_arrays();
+        arrays.face_vertices.pop();
+        assert_eq!(
+            PolyMesh::from_arrays(&arrays).unwrap_err(),
+            TopologyError::FaceIndexMismatch {
+                expected: 24,
+                found: 23
+            }
+        );
+
+        arrays.face_vertices.push(42);
+        assert_eq!(PolyMesh::from_arrays(&arrays).unwrap_err(), TopologyError::InvalidVertex(42));
+    }
+
+    #[test]
+    fn moving_a_vertex_moves_every_face_using_it() {
+        let mut mesh = cube();
+        mesh.set_position(2, Vec3::new(2.0, 2.0, 2.0));
+        for face in mesh.vertex_faces(2) {
+            assert!(mesh.face_vertices(face).any(|vertex| vertex == 2));
+            assert_ne!(mesh.face_center(face), cube().face_center(face));
+        }
+    }
+
+    #[test]
+    fn corners_start_with_the_face_normal() {
+        let mesh = cube();
+        for face in 0..mesh.face_count() {
+            for l in mesh.face_loops(face) {
+                assert_eq!(mesh.loop_attributes(l).normal, mesh.face_normal(face));
+            }
+        }
+    }
+
+    #[test]
+    fn corner_attributes_round_trip() {
+        let mut mesh = cube();
+        mesh.loop_attributes_mut(5).uv = Vec2::new(0.25, 0.75);
+        mesh.loop_attributes_mut(5).color = Color::rgba(1.0, 0.0, 0.0, 1.0);
+
+        let restored = PolyMesh::from_arrays(&mesh.to_arrays()).unwrap();
+        assert_eq!(restored.loop_attributes(5), mesh.loop_attributes(5));
+
+        let mut arrays = mesh.to_arrays();
+        arrays.uvs.clear();
+        let restored = PolyMesh::from_arrays(&arrays).unwrap();
+        assert_eq!(restored.loop_attributes(5).uv, Vec2::zero());
+
+        arrays.colors.pop();
+        assert_eq!(
+            PolyMesh::from_arrays(&arrays).unwrap_err(),
+            TopologyError::LoopAttributeMismatch {
+                expected: 24,
+                found: 23
+            }
+        );
+    }
+
+    #[test]
+    fn tangents_follow_the_uv_layout() {
+        let mut mesh = cube();
+        let uvs = [
+            Vec2::new(0.0, 0.0),
+            Vec2::new(1.0, 0.0),
+            Vec2::new(1.0, 1.0),
+            Vec2::new(0.0, 1.0),
+        ];
+        for face in 0..mesh.face_count() {
+            for (l, &uv) in mesh.face_loops(face).zip(uvs.iter()) {
+                mesh.loop_attributes_mut(l).uv = uv;
+            }
+        }
+        mesh.recalculate_tangents();
+
+        // The front face runs (0,0,1) -> (1,0,1) -> (1,1,1), so +U is +X
+        let tangent = mesh.loop_attributes(0).tangent;
+        assert!(tangent.direction.is_equal_approx(Vec3::new(1.0, 0.0, 0.0), 1e-6));
+        assert_eq!(tangent.sign, 1.0);
+        for l in 0..mesh.loop_count() {
+            let attributes = mesh.loop_attributes(l);
+            assert!(attributes.tangent.direction.dot(attributes.normal).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn face_materials_round_trip() {
+        let mut mesh = cube();
+        assert_eq!(mesh.material_count(), 1);
+        mesh.set_face_material(4, 2);
+        assert_eq!(mesh.material_count(), 3);
+
+        let mut arrays = mesh.to_arrays();
+        assert_eq!(arrays.face_materials, vec![0, 0, 0, 0, 2, 0]);
+        assert_eq!(PolyMesh::from_arrays(&arrays).unwrap().face_material(4), 2);
+
+        arrays.face_materials.pop();
+        assert_eq!(
+            PolyMesh::from_arrays(&arrays).unwrap_err(),
+            TopologyError::FaceMaterialMismatch { expected: 6, found: 5 }
+        );
+    }
+}
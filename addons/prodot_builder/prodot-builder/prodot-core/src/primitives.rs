@@ -0,0 +1,1447 @@
+use std::collections::HashMap;
+use std::f32::consts::PI;
+
+use crate::math::{Vec2, Vec3};
+use crate::normals::{self, NormalMode};
+use crate::poly_mesh::{FaceId, PolyMesh, VertexId};
+use crate::uv::{self, UvProjection, UvSettings};
+
+/// Most quads a box can have along each axis. Counts past a limit are
+/// read as the limit, so scripts can't ask for more geometry than the
+/// editor can hold.
+pub const MAX_BOX_SEGMENTS: usize = 64;
+/// Most segments, rings or steps along any other direction of a primitive.
+pub const MAX_SEGMENTS: usize = 256;
+/// Most times an icosphere is subdivided, each of which quadruples its
+/// triangles.
+pub const MAX_ICOSPHERE_SUBDIVISIONS: usize = 6;
+
+/// Where the origin of a generated primitive is.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Pivot {
+    /// The corner with the lowest X, Y and Z.
+    Corner,
+    Center,
+    /// The center of the bottom face, so the primitive stands on the
+    /// origin.
+    BottomCenter,
+}
+
+impl Pivot {
+    pub fn from_index(index: usize) -> Option<Pivot> {
+        match index {
+            0 => Some(Pivot::Corner),
+            1 => Some(Pivot::Center),
+            2 => Some(Pivot::BottomCenter),
+            _ => None,
+        }
+    }
+
+    /// Lowest corner of a box of `size` around this pivot.
+    fn min_corner(self, size: Vec3) -> Vec3 {
+        match self {
+            Pivot::Corner => Vec3::zero(),
+            Pivot::Center => size * -0.5,
+            Pivot::BottomCenter => Vec3::new(size.x * -0.5, 0.0, size.z * -0.5),
+        }
+    }
+}
+
+/// Parameters of `box_mesh`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BoxParams {
+    /// Width, height and depth.
+    pub size: Vec3,
+    /// Quads along X, Y and Z. Zero counts as one, at most
+    /// `MAX_BOX_SEGMENTS`.
+    pub segments: [usize; 3],
+    pub pivot: Pivot,
+}
+
+impl Default for BoxParams {
+    /// The unit cube the dock always created, with a corner on the origin.
+    fn default() -> Self {
+        BoxParams {
+            size: Vec3::new(1.0, 1.0, 1.0),
+            segments: [1, 1, 1],
+            pivot: Pivot::Corner,
+        }
+    }
+}
+
+/// Direction the front of a plane faces.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Facing {
+    Up,
+    Down,
+    PositiveX,
+    NegativeX,
+    PositiveZ,
+    NegativeZ,
+}
+
+impl Facing {
+    pub fn from_index(index: usize) -> Option<Facing> {
+        match index {
+            0 => Some(Facing::Up),
+            1 => Some(Facing::Down),
+            2 => Some(Facing::PositiveX),
+            3 => Some(Facing::NegativeX),
+            4 => Some(Facing::PositiveZ),
+            5 => Some(Facing::NegativeZ),
+            _ => None,
+        }
+    }
+
+    /// The side of a box facing the same way, in `BOX_SIDES`.
+    fn box_side(self) -> usize {
+        match self {
+            Facing::PositiveZ => 0,
+            Facing::PositiveX => 1,
+            Facing::NegativeX => 2,
+            Facing::NegativeZ => 3,
+            Facing::Up => 4,
+            Facing::Down => 5,
+        }
+    }
+}
+
+/// Parameters of `plane_mesh`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PlaneParams {
+    /// Width along the plane's U axis, X for planes facing up or down.
+    pub width: f32,
+    /// Depth along the plane's V axis, Z for planes facing up or down.
+    pub depth: f32,
+    /// Quads along U and V. Zero counts as one, at most `MAX_SEGMENTS`.
+    pub segments: [usize; 2],
+    pub facing: Facing,
+}
+
+impl Default for PlaneParams {
+    fn default() -> Self {
+        PlaneParams {
+            width: 2.0,
+            depth: 2.0,
+            segments: [1, 1],
+            facing: Facing::Up,
+        }
+    }
+}
+
+/// How the ends of a cylinder or cone are closed.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Caps {
+    /// One polygon with a corner on every radial segment.
+    NGon,
+    /// Triangles around a vertex in the middle.
+    TriangleFan,
+    /// Left open.
+    None,
+}
+
+impl Caps {
+    pub fn from_index(index: usize) -> Option<Caps> {
+        match index {
+            0 => Some(Caps::NGon),
+            1 => Some(Caps::TriangleFan),
+            2 => Some(Caps::None),
+            _ => None,
+        }
+    }
+}
+
+/// Parameters of `cylinder_mesh`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct CylinderParams {
+    /// Radius of the top end, zero for a cone pointing up.
+    pub radius_top: f32,
+    /// Radius of the bottom end, zero for a cone pointing down.
+    pub radius_bottom: f32,
+    pub height: f32,
+    /// Quads around the side. Fewer than three count as three, at most
+    /// `MAX_SEGMENTS`.
+    pub radial_segments: usize,
+    /// Rings of quads up the side. Zero counts as one, at most
+    /// `MAX_SEGMENTS`.
+    pub height_segments: usize,
+    pub caps: Caps,
+    /// Smooth normals around the side, see `normal_mode`.
+    pub smooth: bool,
+}
+
+impl CylinderParams {
+    /// A cone standing on its base, otherwise like the default cylinder.
+    pub fn cone() -> Self {
+        CylinderParams {
+            radius_top: 0.0,
+            ..CylinderParams::default()
+        }
+    }
+
+    /// Normals the side is smoothed with, while its edges with the caps
+    /// stay hard. Flat when `smooth` is off, or when the side bends more
+    /// between two radial segments than it does into a cap, as it does
+    /// with only three.
+    pub fn normal_mode(&self) -> NormalMode {
+        if !self.smooth {
+            return NormalMode::Flat;
+        }
+        // Normal of the side in the plane through the axis, and its
+        // angle with the next side face around
+        let slope = (self.height.powi(2) + (self.radius_bottom - self.radius_top).powi(2)).sqrt();
+        let rise = (self.radius_bottom - self.radius_top) / slope;
+        let bend = (2.0 * PI / self.radial_segments.clamp(3, MAX_SEGMENTS) as f32).cos();
+        let side_angle = (rise * rise + (1.0 - rise * rise) * bend).min(1.0).acos();
+
+        let mut cap_angle = PI;
+        if self.caps != Caps::None {
+            if self.radius_top > 0.0 {
+                cap_angle = cap_angle.min(rise.acos());
+            }
+            if self.radius_bottom > 0.0 {
+                cap_angle = cap_angle.min((-rise).acos());
+            }
+        }
+        if cap_angle >= PI {
+            NormalMode::Smooth
+        } else if side_angle < cap_angle {
+            NormalMode::AutoSmooth { angle: (side_angle + cap_angle) * 0.5 }
+        } else {
+            NormalMode::Flat
+        }
+    }
+}
+
+impl Default for CylinderParams {
+    fn default() -> Self {
+        CylinderParams {
+            radius_top: 1.0,
+            radius_bottom: 1.0,
+            height: 2.0,
+            radial_segments: 16,
+            height_segments: 1,
+            caps: Caps::NGon,
+            smooth: true,
+        }
+    }
+}
+
+/// Parameters of `uv_sphere_mesh`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct UvSphereParams {
+    pub radius: f32,
+    /// Faces around the equator. Fewer than three count as three, at most
+    /// `MAX_SEGMENTS`.
+    pub segments: usize,
+    /// Bands of faces from pole to pole. Fewer than two count as two, at
+    /// most `MAX_SEGMENTS`.
+    pub rings: usize,
+}
+
+impl Default for UvSphereParams {
+    fn default() -> Self {
+        UvSphereParams {
+            radius: 1.0,
+            segments: 32,
+            rings: 16,
+        }
+    }
+}
+
+/// Parameters of `icosphere_mesh`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct IcosphereParams {
+    pub radius: f32,
+    /// How often each triangle of the icosahedron is split into four, at
+    /// most `MAX_ICOSPHERE_SUBDIVISIONS`.
+    pub subdivisions: usize,
+}
+
+impl Default for IcosphereParams {
+    fn default() -> Self {
+        IcosphereParams {
+            radius: 1.0,
+            subdivisions: 2,
+        }
+    }
+}
+
+/// Parameters of `torus_mesh`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TorusParams {
+    /// From the center to the middle of the tube.
+    pub major_radius: f32,
+    /// Radius of the tube.
+    pub minor_radius: f32,
+    /// Quads around the ring. Fewer than three count as three, at most
+    /// `MAX_SEGMENTS`.
+    pub major_segments: usize,
+    /// Quads around the tube. Fewer than three count as three, at most
+    /// `MAX_SEGMENTS`.
+    pub minor_segments: usize,
+}
+
+impl Default for TorusParams {
+    fn default() -> Self {
+        TorusParams {
+            major_radius: 1.0,
+            minor_radius: 0.25,
+            major_segments: 32,
+            minor_segments: 12,
+        }
+    }
+}
+
+/// How a stair is laid out in plan.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum StairLayout {
+    /// One flight climbing towards -Z.
+    Straight,
+    /// A flight towards -Z, a square landing halfway up, then a flight
+    /// turning left towards -X.
+    LShaped,
+    /// Steps winding around Y, each `run` deep along the middle of its
+    /// tread.
+    Spiral,
+}
+
+impl StairLayout {
+    pub fn from_index(index: usize) -> Option<StairLayout> {
+        match index {
+            0 => Some(StairLayout::Straight),
+            1 => Some(StairLayout::LShaped),
+            2 => Some(StairLayout::Spiral),
+            _ => None,
+        }
+    }
+}
+
+/// What holds a stair up.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum StairSupport {
+    /// Every step is filled down to the floor, so the stair is one closed
+    /// solid.
+    Solid,
+    /// Every step is a block of its own, one rise thick, resting on a
+    /// stringer along each side of the flight.
+    Stringers,
+}
+
+impl StairSupport {
+    pub fn from_index(index: usize) -> Option<StairSupport> {
+        match index {
+            0 => Some(StairSupport::Solid),
+            1 => Some(StairSupport::Stringers),
+            _ => None,
+        }
+    }
+}
+
+/// Material slot of the tops of the steps and landings of `stair_mesh`.
+pub const STAIR_TREAD_MATERIAL: usize = 0;
+/// Material slot of the fronts of the steps.
+pub const STAIR_RISER_MATERIAL: usize = 1;
+/// Material slot of everything else: sides, backs, undersides and
+/// stringers.
+pub const STAIR_SIDE_MATERIAL: usize = 2;
+
+/// Parameters of `stair_mesh`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct StairParams {
+    /// Steps from the floor to the top, a landing counting as one. Zero
+    /// counts as one, at most `MAX_SEGMENTS`.
+    pub steps: usize,
+    /// Height of each step.
+    pub rise: f32,
+    /// Depth of each tread.
+    pub run: f32,
+    pub width: f32,
+    pub layout: StairLayout,
+    pub support: StairSupport,
+    /// Thickness of the stringers, across the flight.
+    pub stringer_width: f32,
+    /// Height of the stringers, measured straight down from their top.
+    pub stringer_depth: f32,
+    /// Radius of the open middle of a spiral stair. At zero the steps
+    /// meet in a point.
+    pub inner_radius: f32,
+}
+
+impl Default for StairParams {
+    fn default() -> Self {
+        StairParams {
+            steps: 8,
+            rise: 0.2,
+            run: 0.3,
+            width: 1.0,
+            layout: StairLayout::Straight,
+            support: StairSupport::Solid,
+            stringer_width: 0.05,
+            stringer_depth: 0.3,
+            inner_radius: 0.25,
+        }
+    }
+}
+
+/// An axis index and whether it runs towards its positive end.
+type SignedAxis = (usize, bool);
+
+/// Sides of a box as the axis they face along, then the axes their U and
+/// V run along. A negative axis runs from the far end of the box. U cross
+/// V faces out, so quads laid out along them are counter-clockwise from
+/// outside.
+const BOX_SIDES: [(SignedAxis, SignedAxis, SignedAxis); 6] = [
+    ((2, true), (0, true), (1, true)),   // Front
+    ((0, true), (2, false), (1, true)),  // Right
+    ((0, false), (2, true), (1, true)),  // Left
+    ((2, false), (0, false), (1, true)), // Back
+    ((1, true), (0, true), (2, false)),  // Top
+    ((1, false), (0, true), (2, true)),  // Bottom
+];
+
+/// A closed box made of a grid of quads on each side. Vertices on the
+/// edges between sides are shared, so the box is one connected solid.
+/// Each side gets the whole texture, with flat normals.
+pub fn box_mesh(params: &BoxParams) -> PolyMesh {
+    let segments = params.segments.map(|count| count.clamp(1, MAX_BOX_SEGMENTS));
+    let size = [params.size.x, params.size.y, params.size.z];
+    let min = params.pivot.min_corner(params.size);
+    let min = [min.x, min.y, min.z];
+
+    let mut mesh = PolyMesh::new();
+    // Lattice point to vertex, so the sides share their edges
+    let mut lattice: HashMap<[usize; 3], VertexId> = HashMap::new();
+
+    for &((axis, positive), (u_axis, u_positive), (v_axis, v_positive)) in BOX_SIDES.iter() {
+        let (u_count, v_count) = (segments[u_axis], segments[v_axis]);
+        // Lattice coordinates of the point `u` and `v` steps along the side
+        let point = |u: usize, v: usize| {
+            let mut point = [0; 3];
+            point[axis] = if positive { segments[axis] } else { 0 };
+            point[u_axis] = if u_positive { u } else { u_count - u };
+            point[v_axis] = if v_positive { v } else { v_count - v };
+            point
+        };
+
+        add_quad_grid(&mut mesh, u_count, v_count, |mesh, u, v| {
+            let point = point(u, v);
+            *lattice.entry(point).or_insert_with(|| {
+                let position = |i: usize| min[i] + size[i] * point[i] as f32 / segments[i] as f32;
+                mesh.add_vertex(Vec3::new(position(0), position(1), position(2)))
+            })
+        });
+    }
+
+    normals::recalculate_normals(&mut mesh, NormalMode::Flat);
+    mesh.recalculate_tangents();
+    mesh
+}
+
+/// A flat grid of quads centered on the origin, facing `facing`. Its
+/// vertices are shared between neighbouring quads, and the grid gets the
+/// whole texture. Laid out like the matching side of `box_mesh`.
+pub fn plane_mesh(params: &PlaneParams) -> PolyMesh {
+    let [u_count, v_count] = params.segments.map(|count| count.clamp(1, MAX_SEGMENTS));
+    let (_, (u_axis, u_positive), (v_axis, v_positive)) = BOX_SIDES[params.facing.box_side()];
+    let u_direction = axis_vector(u_axis, u_positive) * params.width;
+    let v_direction = axis_vector(v_axis, v_positive) * params.depth;
+
+    let mut mesh = PolyMesh::new();
+    for v in 0..=v_count {
+        for u in 0..=u_count {
+            let u = u as f32 / u_count as f32 - 0.5;
+            let v = v as f32 / v_count as f32 - 0.5;
+            mesh.add_vertex(u_direction * u + v_direction * v);
+        }
+    }
+    add_quad_grid(&mut mesh, u_count, v_count, |_, u, v| v * (u_count + 1) + u);
+
+    normals::recalculate_normals(&mut mesh, NormalMode::Flat);
+    mesh.recalculate_tangents();
+    mesh
+}
+
+/// A cylinder or cone along Y, centered on the origin. The side is a
+/// grid of quads wrapping once around the texture, with triangles where
+/// an end narrows into a point. Each end with a radius is closed as
+/// `caps` says, and gets the whole texture seen from outside.
+pub fn cylinder_mesh(params: &CylinderParams) -> PolyMesh {
+    let radial = params.radial_segments.clamp(3, MAX_SEGMENTS);
+    let rows = params.height_segments.clamp(1, MAX_SEGMENTS);
+
+    let mut mesh = PolyMesh::new();
+    // Vertices of each ring from the bottom up, one where it's a point
+    let mut rings: Vec<Vec<VertexId>> = Vec::with_capacity(rows + 1);
+    for row in 0..=rows {
+        let t = row as f32 / rows as f32;
+        let radius = params.radius_bottom + (params.radius_top - params.radius_bottom) * t;
+        let center = Vec3::new(0.0, params.height * (t - 0.5), 0.0);
+        let ring = if radius > 0.0 {
+            (0..radial).map(|i| mesh.add_vertex(center + ring_point(radius, i, radial))).collect()
+        } else {
+            vec![mesh.add_vertex(center)]
+        };
+        rings.push(ring);
+    }
+
+    add_quad_grid(&mut mesh, radial, rows, |_, u, v| rings[v][u % rings[v].len()]);
+    add_cap(&mut mesh, &rings[0], params.caps, false);
+    add_cap(&mut mesh, &rings[rows], params.caps, true);
+
+    normals::recalculate_normals(&mut mesh, params.normal_mode());
+    mesh.recalculate_tangents();
+    mesh
+}
+
+/// A sphere centered on the origin, made of quads between rings of
+/// latitude and triangles around the poles on Y. The texture wraps once
+/// around it like on `cylinder_mesh`, with V running from the bottom
+/// pole to the top one. Vertices on the seam are shared, so the normals
+/// are smooth across it.
+pub fn uv_sphere_mesh(params: &UvSphereParams) -> PolyMesh {
+    let segments = params.segments.clamp(3, MAX_SEGMENTS);
+    let rings = params.rings.clamp(2, MAX_SEGMENTS);
+
+    let mut mesh = PolyMesh::new();
+    let mut latitudes: Vec<Vec<VertexId>> = Vec::with_capacity(rings + 1);
+    for row in 0..=rings {
+        let latitude = PI * (row as f32 / rings as f32 - 0.5);
+        let center = Vec3::new(0.0, params.radius * latitude.sin(), 0.0);
+        let radius = params.radius * latitude.cos();
+        let ring = if row == 0 || row == rings {
+            vec![mesh.add_vertex(center)]
+        } else {
+            (0..segments).map(|i| mesh.add_vertex(center + ring_point(radius, i, segments))).collect()
+        };
+        latitudes.push(ring);
+    }
+    add_quad_grid(&mut mesh, segments, rings, |_, u, v| latitudes[v][u % latitudes[v].len()]);
+
+    normals::recalculate_normals(&mut mesh, NormalMode::Smooth);
+    mesh.recalculate_tangents();
+    mesh
+}
+
+/// A sphere centered on the origin made of evenly sized triangles, from
+/// an icosahedron with a vertex on each pole. The texture is wrapped
+/// around it as on `uv_sphere_mesh`. Faces across the seam carry on past
+/// a U of 1 into the repeated texture, and corners on a pole take the U
+/// of the middle of their face, so no face is stretched across it.
+pub fn icosphere_mesh(params: &IcosphereParams) -> PolyMesh {
+    // Unit directions of the vertices, and triangles on them
+    let mut directions = vec![Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, -1.0, 0.0)];
+    let (top, bottom) = (0, 1);
+    // Two rings of five at a latitude of atan(1/2), half a step apart
+    let ring_y = 1.0 / 5f32.sqrt();
+    let ring_radius = 2.0 * ring_y;
+    for i in 0..10 {
+        let mut direction = ring_point(ring_radius, i, 10);
+        direction.y = if i % 2 == 0 { ring_y } else { -ring_y };
+        directions.push(direction);
+    }
+    let upper = |i: usize| 2 + (i % 5) * 2;
+    let lower = |i: usize| 3 + (i % 5) * 2;
+    let mut triangles = Vec::with_capacity(20);
+    for i in 0..5 {
+        triangles.push([top, upper(i), upper(i + 1)]);
+        triangles.push([upper(i), lower(i), upper(i + 1)]);
+        triangles.push([lower(i), lower(i + 1), upper(i + 1)]);
+        triangles.push([bottom, lower(i + 1), lower(i)]);
+    }
+
+    for _ in 0..params.subdivisions.min(MAX_ICOSPHERE_SUBDIVISIONS) {
+        // Edge to the vertex in its middle, shared by the faces on it
+        let mut middles: HashMap<(usize, usize), usize> = HashMap::new();
+        let mut middle = |a: usize, b: usize| {
+            *middles.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                directions.push((directions[a] + directions[b]).normalized());
+                directions.len() - 1
+            })
+        };
+        triangles = triangles
+            .iter()
+            .flat_map(|&[a, b, c]| {
+                let (ab, bc, ca) = (middle(a, b), middle(b, c), middle(c, a));
+                vec![[a, ab, ca], [ab, b, bc], [ca, bc, c], [ab, bc, ca]]
+            })
+            .collect();
+    }
+
+    let mut mesh = PolyMesh::new();
+    for &direction in &directions {
+        mesh.add_vertex(direction * params.radius);
+    }
+    for triangle in &triangles {
+        let face = mesh.add_face(triangle).expect("icosphere triangles have distinct corners");
+        let uvs = sphere_uvs(triangle.map(|vertex| directions[vertex]));
+        for (l, uv) in mesh.face_loops(face).zip(uvs.iter()) {
+            mesh.loop_attributes_mut(l).uv = *uv;
+        }
+    }
+
+    normals::recalculate_normals(&mut mesh, NormalMode::Smooth);
+    mesh.recalculate_tangents();
+    mesh
+}
+
+/// UVs of a triangle on the unit sphere, wrapped as on `uv_sphere_mesh`.
+/// Corners left of the seam get a U past 1 when the rest of the triangle
+/// is right of it.
+fn sphere_uvs(corners: [Vec3; 3]) -> [Vec2; 3] {
+    const POLE_EPSILON: f32 = 1e-6;
+    let on_pole = |corner: &Vec3| corner.x * corner.x + corner.z * corner.z < POLE_EPSILON * POLE_EPSILON;
+    let mut us = corners.map(|corner| {
+        let u = corner.x.atan2(corner.z) / (2.0 * PI);
+        // Round-off just left of the seam still counts as on it
+        if u < -POLE_EPSILON {
+            u + 1.0
+        } else {
+            u.max(0.0)
+        }
+    });
+
+    let around: Vec<usize> = (0..3).filter(|&i| !on_pole(&corners[i])).collect();
+    let max = around.iter().map(|&i| us[i]).fold(0.0, f32::max);
+    if around.iter().any(|&i| max - us[i] > 0.5) {
+        for &i in &around {
+            if us[i] < 0.5 {
+                us[i] += 1.0;
+            }
+        }
+    }
+    let middle = around.iter().map(|&i| us[i]).sum::<f32>() / around.len() as f32;
+    for (i, corner) in corners.iter().enumerate() {
+        if on_pole(corner) {
+            us[i] = middle;
+        }
+    }
+
+    [0, 1, 2].map(|i| Vec2::new(us[i], corners[i].y.clamp(-1.0, 1.0).asin() / PI + 0.5))
+}
+
+/// A ring around Y made of quads, centered on the origin. It is closed
+/// with every vertex shared, and the texture wraps once around the ring
+/// and once around the tube, with the seam of the tube on its inside.
+pub fn torus_mesh(params: &TorusParams) -> PolyMesh {
+    let major = params.major_segments.clamp(3, MAX_SEGMENTS);
+    let minor = params.minor_segments.clamp(3, MAX_SEGMENTS);
+
+    let mut mesh = PolyMesh::new();
+    for j in 0..minor {
+        // Starting on the inside, then down and around
+        let angle = PI + 2.0 * PI * j as f32 / minor as f32;
+        let radius = params.major_radius + params.minor_radius * angle.cos();
+        let height = Vec3::new(0.0, params.minor_radius * angle.sin(), 0.0);
+        for i in 0..major {
+            mesh.add_vertex(height + ring_point(radius, i, major));
+        }
+    }
+    add_quad_grid(&mut mesh, major, minor, |_, u, v| (v % minor) * major + u % major);
+
+    normals::recalculate_normals(&mut mesh, NormalMode::Smooth);
+    mesh.recalculate_tangents();
+    mesh
+}
+
+/// A stair standing on the floor, its first step's front on the
+/// origin, made of quads. Steps are blocks between the lines of a grid
+/// in plan, each one rise taller than the last. Treads, risers and the
+/// rest get their own material slots, and UVs are projected onto each
+/// face at one texture per unit.
+pub fn stair_mesh(params: &StairParams) -> PolyMesh {
+    let plan = StairPlan::new(params);
+
+    let mut mesh = PolyMesh::new();
+    add_steps(&mut mesh, &plan, params);
+    if params.support == StairSupport::Stringers {
+        for stringer in &plan.stringers {
+            add_stringer(&mut mesh, &plan, stringer, params);
+        }
+    }
+
+    let settings = UvSettings {
+        projection: UvProjection::Planar,
+        ..UvSettings::default()
+    };
+    uv::project_uvs(&mut mesh, &settings, |position| position);
+    normals::recalculate_normals(&mut mesh, NormalMode::Flat);
+    mesh.recalculate_tangents();
+    mesh
+}
+
+/// A step of a stair: the cell of the plan grid its tread covers, the
+/// height of its top in rises, and the side of the cell its riser is on,
+/// as a lattice axis like in `BOX_SIDES`.
+struct Tread {
+    cell: [usize; 2],
+    level: usize,
+    back: SignedAxis,
+}
+
+/// A stringer, as the grid points along its side of the flight from the
+/// bottom up, and the height of its top at the first one in rises. It
+/// rises one step per point, and is as thick as the stair's stringer
+/// width towards the points on the other side.
+struct StringerPath {
+    side: Vec<[usize; 2]>,
+    other_side: Vec<[usize; 2]>,
+    first_level: usize,
+}
+
+/// Where the lines of a plan grid meet on the floor.
+enum PlanGrid {
+    /// Lines at these X and Z coordinates, both ascending.
+    Rectilinear { x: Vec<f32>, z: Vec<f32> },
+    /// Circles from `inner_radius` outwards `width` apart, and rays
+    /// `step_angle` apart from +X towards +Z.
+    Polar { inner_radius: f32, width: f32, step_angle: f32 },
+}
+
+/// The plan of a stair.
+struct StairPlan {
+    grid: PlanGrid,
+    treads: Vec<Tread>,
+    stringers: Vec<StringerPath>,
+}
+
+impl StairPlan {
+    fn new(params: &StairParams) -> StairPlan {
+        let steps = params.steps.clamp(1, MAX_SEGMENTS);
+        let (run, half_width) = (params.run, params.width * 0.5);
+        // A stringer on each side of the flight along `lines`, the grid
+        // points of one side and then the other
+        let flight = |lines: [Vec<[usize; 2]>; 2], first_level: usize| {
+            let [first, second] = lines;
+            vec![
+                StringerPath { side: first.clone(), other_side: second.clone(), first_level },
+                StringerPath { side: second, other_side: first, first_level },
+            ]
+        };
+
+        match params.layout {
+            StairLayout::Straight => {
+                let z = (0..=steps).map(|b| -((steps - b) as f32) * run).collect();
+                let side = |a: usize| (0..=steps).rev().map(|b| [a, b]).collect();
+                StairPlan {
+                    grid: PlanGrid::Rectilinear { x: vec![-half_width, half_width], z },
+                    treads: (0..steps)
+                        .map(|step| Tread { cell: [0, steps - 1 - step], level: step + 1, back: (2, true) })
+                        .collect(),
+                    stringers: flight([side(0), side(1)], 0),
+                }
+            }
+            StairLayout::LShaped => {
+                // Steps below the landing, and above it
+                let first = (steps - 1) / 2;
+                let second = steps - 1 - first;
+                let mut x: Vec<f32> = (0..=second).map(|a| -half_width - (second - a) as f32 * run).collect();
+                x.push(half_width);
+                let mut z = vec![-(first as f32 * run + params.width)];
+                z.extend((1..=first + 1).map(|b| -((first + 1 - b) as f32) * run));
+
+                let mut treads: Vec<Tread> = (0..first)
+                    .map(|step| Tread { cell: [second, first - step], level: step + 1, back: (2, true) })
+                    .collect();
+                treads.push(Tread { cell: [second, 0], level: first + 1, back: (2, true) });
+                treads.extend((0..second).map(|step| Tread {
+                    cell: [second - 1 - step, 0],
+                    level: first + 2 + step,
+                    back: (0, true),
+                }));
+
+                let first_side = |a: usize| (1..=first + 1).rev().map(|b| [a, b]).collect();
+                let second_side = |b: usize| (0..=second).rev().map(|a| [a, b]).collect();
+                let mut stringers = flight([first_side(second), first_side(second + 1)], 0);
+                stringers.extend(flight([second_side(0), second_side(1)], first + 1));
+                StairPlan {
+                    grid: PlanGrid::Rectilinear { x, z },
+                    treads,
+                    stringers,
+                }
+            }
+            StairLayout::Spiral => {
+                let middle = params.inner_radius + half_width;
+                let side = |a: usize| (0..=steps).map(|b| [a, b]).collect();
+                StairPlan {
+                    grid: PlanGrid::Polar {
+                        inner_radius: params.inner_radius,
+                        width: params.width,
+                        step_angle: if middle > 0.0 { run / middle } else { 0.0 },
+                    },
+                    treads: (0..steps)
+                        .map(|step| Tread { cell: [0, step], level: step + 1, back: (2, false) })
+                        .collect(),
+                    stringers: flight([side(0), side(1)], 0),
+                }
+            }
+        }
+    }
+
+    /// Position of grid point `point` on the floor.
+    fn position(&self, point: [usize; 2]) -> Vec3 {
+        match &self.grid {
+            PlanGrid::Rectilinear { x, z } => Vec3::new(x[point[0]], 0.0, z[point[1]]),
+            PlanGrid::Polar { inner_radius, width, step_angle } => {
+                let radius = inner_radius + width * point[0] as f32;
+                let angle = step_angle * point[1] as f32;
+                Vec3::new(radius * angle.cos(), 0.0, radius * angle.sin())
+            }
+        }
+    }
+
+    /// `point`, or the first grid point in the same place when the
+    /// middle of a spiral closes into one.
+    fn shared_point(&self, point: [usize; 2]) -> [usize; 2] {
+        match self.grid {
+            PlanGrid::Polar { inner_radius, .. } if point[0] == 0 && inner_radius <= 0.0 => [0, 0],
+            _ => point,
+        }
+    }
+
+    /// Height of the top of the step on `cell` in rises, zero outside the
+    /// stair.
+    fn level(&self, cell: [isize; 2]) -> usize {
+        self.treads
+            .iter()
+            .find(|tread| tread.cell[0] as isize == cell[0] && tread.cell[1] as isize == cell[1])
+            .map_or(0, |tread| tread.level)
+    }
+}
+
+/// Adds the steps of `plan`. Solid steps are stacks of blocks one rise
+/// tall down to the floor, sharing their vertices and leaving out the
+/// faces between them, so they close into one solid. Otherwise each
+/// step is only its top block, on vertices of its own.
+fn add_steps(mesh: &mut PolyMesh, plan: &StairPlan, params: &StairParams) {
+    let solid = params.support == StairSupport::Solid;
+    // Step, or 0 for all of them when solid, and lattice point to vertex
+    let mut lattice: HashMap<(usize, [usize; 3]), VertexId> = HashMap::new();
+
+    for (step, tread) in plan.treads.iter().enumerate() {
+        let rows = if solid { 0 } else { tread.level - 1 };
+        let owner = if solid { 0 } else { step + 1 };
+        for row in rows..tread.level {
+            let block = [tread.cell[0], row, tread.cell[1]];
+            for &((axis, positive), (u_axis, u_positive), (v_axis, v_positive)) in BOX_SIDES.iter() {
+                if solid {
+                    let mut neighbour = block.map(|i| i as isize);
+                    neighbour[axis] += if positive { 1 } else { -1 };
+                    let level = plan.level([neighbour[0], neighbour[2]]);
+                    if neighbour[1] >= 0 && (neighbour[1] as usize) < level {
+                        continue;
+                    }
+                }
+
+                let mut corners = Vec::with_capacity(4);
+                for &(u, v) in &[(0, 0), (1, 0), (1, 1), (0, 1)] {
+                    let mut point = block;
+                    point[axis] += positive as usize;
+                    point[u_axis] += if u_positive { u } else { 1 - u };
+                    point[v_axis] += if v_positive { v } else { 1 - v };
+                    let [a, b] = plan.shared_point([point[0], point[2]]);
+                    let vertex = *lattice.entry((owner, [a, point[1], b])).or_insert_with(|| {
+                        mesh.add_vertex(plan.position([a, b]) + Vec3::new(0.0, params.rise * point[1] as f32, 0.0))
+                    });
+                    corners.push((vertex, Vec2::zero()));
+                }
+
+                let material = if (axis, positive) == (1, true) {
+                    STAIR_TREAD_MATERIAL
+                } else if (axis, positive) == tread.back && row + 1 == tread.level {
+                    STAIR_RISER_MATERIAL
+                } else {
+                    STAIR_SIDE_MATERIAL
+                };
+                if let Some(face) = add_merged_face(mesh, corners) {
+                    mesh.set_face_material(face, material);
+                }
+            }
+        }
+    }
+}
+
+/// Adds `stringer` as a beam under the side of its flight. Its top runs
+/// through the front bottom corners of the steps, and its bottom is cut
+/// off at the floor, where it narrows into an edge at the foot of the
+/// flight.
+fn add_stringer(mesh: &mut PolyMesh, plan: &StairPlan, stringer: &StringerPath, params: &StairParams) {
+    let side: Vec<Vec3> = stringer.side.iter().map(|&point| plan.position(point)).collect();
+    // The middle of a spiral has no side to carry
+    let length: f32 = side.windows(2).map(|pair| pair[0].distance_to(pair[1])).sum();
+    if side.len() < 2 || length <= 0.0 {
+        return;
+    }
+
+    let up = Vec3::new(0.0, 1.0, 0.0);
+    let mut sections: Vec<[VertexId; 4]> = Vec::with_capacity(side.len());
+    for (i, (&floor, &other)) in side.iter().zip(stringer.other_side.iter()).enumerate() {
+        let inward = (plan.position(other) - floor).normalized() * params.stringer_width;
+        let top = params.rise * (stringer.first_level + i) as f32;
+        let bottom = (top - params.stringer_depth).max(0.0);
+        let outer_top = mesh.add_vertex(floor + up * top);
+        let inner_top = mesh.add_vertex(floor + inward + up * top);
+        let (inner_bottom, outer_bottom) = if bottom < top {
+            (mesh.add_vertex(floor + inward + up * bottom), mesh.add_vertex(floor + up * bottom))
+        } else {
+            (inner_top, outer_top)
+        };
+        sections.push([outer_top, inner_top, inner_bottom, outer_bottom]);
+    }
+    // Around the section so that the sides face out along the flight
+    let inward = plan.position(stringer.other_side[0]) - side[0];
+    if inward.cross(side[1] - side[0]).y < 0.0 {
+        for section in &mut sections {
+            section.reverse();
+        }
+    }
+
+    let mut faces = add_quad_grid(mesh, 4, sections.len() - 1, |_, u, v| sections[v][u % 4]);
+    // The caps run the other way round than the sides next to them
+    let end = sections[sections.len() - 1];
+    let mut start = sections[0];
+    start.reverse();
+    for cap in &[end, start] {
+        faces.extend(add_merged_face(mesh, cap.iter().map(|&vertex| (vertex, Vec2::zero())).collect()));
+    }
+    for face in faces {
+        mesh.set_face_material(face, STAIR_SIDE_MATERIAL);
+    }
+}
+
+/// Point `index` of `count` around a circle of `radius` in the XZ
+/// plane, going from +Z towards +X.
+fn ring_point(radius: f32, index: usize, count: usize) -> Vec3 {
+    let angle = 2.0 * PI * index as f32 / count as f32;
+    Vec3::new(radius * angle.sin(), 0.0, radius * angle.cos())
+}
+
+/// Closes `ring`, made by `ring_point`, as `caps` says, facing up or
+/// down. The cap is textured like the top or bottom of `box_mesh`. Rings
+/// that are a single point are left alone.
+fn add_cap(mesh: &mut PolyMesh, ring: &[VertexId], caps: Caps, up: bool) {
+    if ring.len() < 3 {
+        return;
+    }
+    // Counter-clockwise seen from above, so the top is wound as it is
+    let mut ring = ring.to_vec();
+    if !up {
+        ring.reverse();
+    }
+
+    let center = ring.iter().fold(Vec3::zero(), |sum, &vertex| sum + mesh.position(vertex)) * (1.0 / ring.len() as f32);
+    let radius = mesh.position(ring[0]).distance_to(center);
+    let faces = match caps {
+        Caps::NGon => vec![mesh.add_face(&ring).expect("ring vertices are distinct")],
+        Caps::TriangleFan => {
+            let middle = mesh.add_vertex(center);
+            (0..ring.len())
+                .map(|i| {
+                    let triangle = [middle, ring[i], ring[(i + 1) % ring.len()]];
+                    mesh.add_face(&triangle).expect("ring vertices are distinct")
+                })
+                .collect()
+        }
+        Caps::None => Vec::new(),
+    };
+
+    // Top V runs along -Z, bottom V along +Z
+    let v_sign = if up { -0.5 } else { 0.5 };
+    for face in faces {
+        for l in mesh.face_loops(face) {
+            let offset = (mesh.position(mesh.get_loop(l).vertex) - center) * (1.0 / radius);
+            mesh.loop_attributes_mut(l).uv = Vec2::new(0.5 + 0.5 * offset.x, 0.5 + v_sign * offset.z);
+        }
+    }
+}
+
+/// Adds `u_count` by `v_count` quads wound counter-clockwise when U
+/// points right and V up, on the vertices `vertex` returns for each grid
+/// point. Each quad gets its share of the whole texture. Quads are added
+/// with `add_merged_face`, so a row collapsed into a point gives
+/// triangles meeting there. Returns the faces row by row.
+fn add_quad_grid(
+    mesh: &mut PolyMesh,
+    u_count: usize,
+    v_count: usize,
+    mut vertex: impl FnMut(&mut PolyMesh, usize, usize) -> VertexId,
+) -> Vec<FaceId> {
+    let mut faces = Vec::with_capacity(u_count * v_count);
+    for v in 0..v_count {
+        for u in 0..u_count {
+            let corners = [(u, v), (u + 1, v), (u + 1, v + 1), (u, v + 1)]
+                .iter()
+                .map(|&(u, v)| {
+                    let uv = Vec2::new(u as f32 / u_count as f32, v as f32 / v_count as f32);
+                    (vertex(mesh, u, v), uv)
+                })
+                .collect();
+            faces.extend(add_merged_face(mesh, corners));
+        }
+    }
+    faces
+}
+
+/// Adds a face on the vertices of `corners` with their UVs. Neighbouring
+/// corners on the same vertex are merged halfway between their UVs, and
+/// nothing is added when fewer than three corners are left.
+fn add_merged_face(mesh: &mut PolyMesh, mut corners: Vec<(VertexId, Vec2)>) -> Option<FaceId> {
+    let mut i = 0;
+    while corners.len() > 1 && i < corners.len() {
+        let next = (i + 1) % corners.len();
+        if corners[i].0 != corners[next].0 {
+            i += 1;
+            continue;
+        }
+        let (_, uv) = corners.remove(next);
+        if next < i {
+            i -= 1;
+        }
+        corners[i].1 = (corners[i].1 + uv) * 0.5;
+    }
+    if corners.len() < 3 {
+        return None;
+    }
+
+    let vertices: Vec<VertexId> = corners.iter().map(|&(vertex, _)| vertex).collect();
+    let face = mesh.add_face(&vertices).expect("merged corners are distinct");
+    for (l, &(_, uv)) in mesh.face_loops(face).zip(corners.iter()) {
+        mesh.loop_attributes_mut(l).uv = uv;
+    }
+    Some(face)
+}
+
+/// Unit vector along `axis`, pointing towards its negative end unless
+/// `positive`.
+fn axis_vector(axis: usize, positive: bool) -> Vec3 {
+    let mut vector = [0.0; 3];
+    vector[axis] = if positive { 1.0 } else { -1.0 };
+    Vec3::new(vector[0], vector[1], vector[2])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poly_mesh::tests::cube;
+    use crate::stl::check_solid;
+
+    fn face_positions(mesh: &PolyMesh) -> Vec<Vec<Vec3>> {
+        (0..mesh.face_count())
+            .map(|face| mesh.face_vertices(face).map(|vertex| mesh.position(vertex)).collect())
+            .collect()
+    }
+
+    fn bounds(mesh: &PolyMesh) -> (Vec3, Vec3) {
+        let min = mesh.positions().fold(mesh.position(0), Vec3::min);
+        let max = mesh.positions().fold(mesh.position(0), Vec3::max);
+        (min, max)
+    }
+
+    #[test]
+    fn default_box_is_the_unit_cube() {
+        let mesh = box_mesh(&BoxParams::default());
+
+        assert_eq!(mesh.vertex_count(), 8);
+        assert_eq!(face_positions(&mesh), face_positions(&cube()));
+        let uvs: Vec<Vec2> = mesh.face_loops(0).map(|l| mesh.loop_attributes(l).uv).collect();
+        assert_eq!(
+            uvs,
+            vec![Vec2::new(0.0, 0.0), Vec2::new(1.0, 0.0), Vec2::new(1.0, 1.0), Vec2::new(0.0, 1.0)]
+        );
+    }
+
+    #[test]
+    fn segments_make_a_closed_grid() {
+        let params = BoxParams {
+            size: Vec3::new(2.0, 3.0, 4.0),
+            segments: [2, 3, 4],
+            pivot: Pivot::Corner,
+        };
+        let mesh = box_mesh(&params);
+
+        // Every point of the 3 by 4 by 5 lattice but the 1 by 2 by 3 inside
+        assert_eq!(mesh.vertex_count(), 60 - 6);
+        assert_eq!(mesh.face_count(), 2 * (2 * 3 + 3 * 4 + 2 * 4));
+        assert!(check_solid(&mesh).is_solid());
+        for face in 0..mesh.face_count() {
+            let center = mesh.face_center(face) - Vec3::new(1.0, 1.5, 2.0);
+            assert!(mesh.face_normal(face).dot(center) > 0.0);
+        }
+    }
+
+    #[test]
+    fn pivot_moves_the_origin() {
+        let size = Vec3::new(2.0, 4.0, 6.0);
+        let pivot_bounds = |pivot| bounds(&box_mesh(&BoxParams { size, segments: [1, 2, 1], pivot }));
+
+        assert_eq!(pivot_bounds(Pivot::Corner), (Vec3::zero(), size));
+        assert_eq!(pivot_bounds(Pivot::Center), (size * -0.5, size * 0.5));
+        assert_eq!(
+            pivot_bounds(Pivot::BottomCenter),
+            (Vec3::new(-1.0, 0.0, -3.0), Vec3::new(1.0, 4.0, 3.0))
+        );
+        assert_eq!(Pivot::from_index(2), Some(Pivot::BottomCenter));
+        assert_eq!(Pivot::from_index(3), None);
+    }
+
+    #[test]
+    fn plane_is_a_connected_grid() {
+        let params = PlaneParams {
+            width: 3.0,
+            depth: 2.0,
+            segments: [3, 2],
+            facing: Facing::Up,
+        };
+        let mesh = plane_mesh(&params);
+
+        assert_eq!(mesh.vertex_count(), 4 * 3);
+        assert_eq!(mesh.face_count(), 6);
+        assert_eq!(mesh.boundary_edges().len(), 2 * (3 + 2));
+        assert_eq!(mesh.face_islands().len(), 1);
+        assert_eq!(bounds(&mesh), (Vec3::new(-1.5, 0.0, -1.0), Vec3::new(1.5, 0.0, 1.0)));
+        // The last quad has the far corner of the texture
+        let last = mesh.face_loops(5).nth(2).unwrap();
+        assert_eq!(mesh.loop_attributes(last).uv, Vec2::new(1.0, 1.0));
+    }
+
+    #[test]
+    fn plane_faces_its_axis() {
+        let directions = [
+            Vec3::new(0.0, 1.0, 0.0),
+            Vec3::new(0.0, -1.0, 0.0),
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(-1.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 1.0),
+            Vec3::new(0.0, 0.0, -1.0),
+        ];
+        for (index, direction) in directions.iter().enumerate() {
+            let facing = Facing::from_index(index).unwrap();
+            let mesh = plane_mesh(&PlaneParams {
+                segments: [2, 2],
+                facing,
+                ..PlaneParams::default()
+            });
+            for face in 0..mesh.face_count() {
+                assert!(mesh.face_normal(face).dot(*direction) > 0.99, "{:?}", facing);
+            }
+        }
+        assert_eq!(Facing::from_index(6), None);
+    }
+
+    /// Whether every face of a solid centered on the origin faces away
+    /// from it.
+    fn faces_outward(mesh: &PolyMesh) -> bool {
+        (0..mesh.face_count()).all(|face| mesh.face_normal(face).dot(mesh.face_center(face)) > 0.0)
+    }
+
+    #[test]
+    fn cylinder_is_a_closed_tube_of_quads() {
+        let params = CylinderParams {
+            height_segments: 2,
+            ..CylinderParams::default()
+        };
+        let mesh = cylinder_mesh(&params);
+
+        assert_eq!(mesh.vertex_count(), 3 * 16);
+        assert_eq!(mesh.face_count(), 2 * 16 + 2);
+        assert!((0..32).all(|face| mesh.face_len(face) == 4));
+        assert_eq!(mesh.face_len(32), 16);
+        assert_eq!(mesh.face_len(33), 16);
+        assert!(check_solid(&mesh).is_solid());
+        assert!(faces_outward(&mesh));
+        assert_eq!(bounds(&mesh).0.y, -1.0);
+        assert_eq!(bounds(&mesh).1.y, 1.0);
+
+        // The last quad around wraps onto the first column of vertices,
+        // with the right edge of the texture
+        let last = mesh.face_loops(15).nth(1).unwrap();
+        assert_eq!(mesh.get_loop(last).vertex, 0);
+        assert_eq!(mesh.loop_attributes(last).uv, Vec2::new(1.0, 0.0));
+    }
+
+    #[test]
+    fn cone_narrows_into_an_apex() {
+        let mesh = cylinder_mesh(&CylinderParams::cone());
+
+        assert_eq!(mesh.vertex_count(), 16 + 1);
+        assert_eq!(mesh.face_count(), 16 + 1);
+        assert!((0..16).all(|face| mesh.face_len(face) == 3));
+        assert!(check_solid(&mesh).is_solid());
+        assert!(faces_outward(&mesh));
+        // The apex corner sits halfway along the top of its quad
+        let apex = mesh.face_loops(0).nth(2).unwrap();
+        assert_eq!(mesh.get_loop(apex).vertex, 16);
+        assert_eq!(mesh.loop_attributes(apex).uv, Vec2::new(0.5 / 16.0, 1.0));
+    }
+
+    #[test]
+    fn caps_can_be_fans_or_open() {
+        let fan = cylinder_mesh(&CylinderParams {
+            radial_segments: 6,
+            caps: Caps::TriangleFan,
+            ..CylinderParams::default()
+        });
+        assert_eq!(fan.vertex_count(), 2 * 6 + 2);
+        assert_eq!(fan.face_count(), 6 + 2 * 6);
+        assert!(check_solid(&fan).is_solid());
+        assert!(faces_outward(&fan));
+        let middle = fan.face_loops(6).next().unwrap();
+        assert_eq!(fan.loop_attributes(middle).uv, Vec2::new(0.5, 0.5));
+
+        let open = cylinder_mesh(&CylinderParams {
+            radial_segments: 6,
+            caps: Caps::None,
+            ..CylinderParams::default()
+        });
+        assert_eq!(open.face_count(), 6);
+        assert_eq!(open.boundary_edges().len(), 2 * 6);
+        assert_eq!(Caps::from_index(3), None);
+    }
+
+    #[test]
+    fn smoothing_keeps_the_caps_hard() {
+        let mesh = cylinder_mesh(&CylinderParams::default());
+        match CylinderParams::default().normal_mode() {
+            NormalMode::AutoSmooth { angle } => assert!(angle > 22.5f32.to_radians() && angle < PI * 0.5),
+            mode => panic!("{:?}", mode),
+        }
+        let side = mesh.face_loops(0).next().unwrap();
+        assert!((mesh.loop_attributes(side).normal - Vec3::new(0.0, 0.0, 1.0)).length() < 1e-5);
+        let top = mesh.face_loops(17).next().unwrap();
+        assert!((mesh.loop_attributes(top).normal - Vec3::new(0.0, 1.0, 0.0)).length() < 1e-5);
+
+        let flat = CylinderParams {
+            smooth: false,
+            ..CylinderParams::default()
+        };
+        assert_eq!(flat.normal_mode(), NormalMode::Flat);
+        let triangular = CylinderParams {
+            radial_segments: 3,
+            ..CylinderParams::default()
+        };
+        assert_eq!(triangular.normal_mode(), NormalMode::Flat);
+    }
+
+    #[test]
+    fn uv_sphere_closes_at_the_poles() {
+        let params = UvSphereParams {
+            radius: 2.0,
+            segments: 8,
+            rings: 4,
+        };
+        let mesh = uv_sphere_mesh(&params);
+
+        assert_eq!(mesh.vertex_count(), 3 * 8 + 2);
+        assert_eq!(mesh.face_count(), 4 * 8);
+        assert_eq!((0..32).filter(|&face| mesh.face_len(face) == 3).count(), 16);
+        assert!(check_solid(&mesh).is_solid());
+        assert!(faces_outward(&mesh));
+        assert!(mesh.positions().all(|position| (position.length() - 2.0).abs() < 1e-5));
+
+        // Pole corners sit halfway along their face, and point along Y
+        let pole = mesh.face_loops(0).next().unwrap();
+        assert_eq!(mesh.loop_attributes(pole).uv, Vec2::new(0.5 / 8.0, 0.0));
+        assert!((mesh.loop_attributes(pole).normal - Vec3::new(0.0, -1.0, 0.0)).length() < 1e-5);
+        // Corners on the seam share a normal, but not a U
+        let seam: Vec<usize> = (0..mesh.loop_count()).filter(|&l| mesh.get_loop(l).vertex == 1).collect();
+        assert_eq!(seam.len(), 4);
+        let normal = mesh.loop_attributes(seam[0]).normal;
+        assert!(seam.iter().all(|&l| mesh.loop_attributes(l).normal == normal));
+        let mut us: Vec<f32> = seam.iter().map(|&l| mesh.loop_attributes(l).uv.x).collect();
+        us.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(us, vec![0.0, 0.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn icosphere_splits_the_icosahedron() {
+        let icosahedron = icosphere_mesh(&IcosphereParams {
+            radius: 1.0,
+            subdivisions: 0,
+        });
+        assert_eq!(icosahedron.vertex_count(), 12);
+        assert_eq!(icosahedron.face_count(), 20);
+        assert!(check_solid(&icosahedron).is_solid());
+        assert!(faces_outward(&icosahedron));
+
+        let mesh = icosphere_mesh(&IcosphereParams {
+            radius: 3.0,
+            subdivisions: 2,
+        });
+        assert_eq!(mesh.vertex_count(), 162);
+        assert_eq!(mesh.face_count(), 320);
+        assert!(check_solid(&mesh).is_solid());
+        assert!(faces_outward(&mesh));
+        assert!(mesh.positions().all(|position| (position.length() - 3.0).abs() < 1e-5));
+        let top = mesh.face_loops(0).next().unwrap();
+        assert!((mesh.loop_attributes(top).normal - Vec3::new(0.0, 1.0, 0.0)).length() < 1e-5);
+    }
+
+    #[test]
+    fn counts_past_the_limits_are_clamped() {
+        let mesh = icosphere_mesh(&IcosphereParams {
+            radius: 1.0,
+            subdivisions: usize::MAX,
+        });
+        assert_eq!(mesh.face_count(), 20 * 4usize.pow(MAX_ICOSPHERE_SUBDIVISIONS as u32));
+
+        let mesh = uv_sphere_mesh(&UvSphereParams {
+            radius: 1.0,
+            segments: usize::MAX,
+            rings: 2,
+        });
+        assert_eq!(mesh.face_count(), MAX_SEGMENTS * 2);
+    }
+
+    #[test]
+    fn icosphere_uvs_wrap_across_the_seam() {
+        let mesh = icosphere_mesh(&IcosphereParams::default());
+        for face in 0..mesh.face_count() {
+            let uvs: Vec<Vec2> = mesh.face_loops(face).map(|l| mesh.loop_attributes(l).uv).collect();
+            let min = uvs.iter().map(|uv| uv.x).fold(f32::MAX, f32::min);
+            let max = uvs.iter().map(|uv| uv.x).fold(f32::MIN, f32::max);
+            assert!((0.0..1.0).contains(&min) && max - min < 0.25, "face {} has {:?}", face, uvs);
+            assert!(uvs.iter().all(|uv| (0.0..=1.0).contains(&uv.y)));
+        }
+    }
+
+    #[test]
+    fn torus_is_a_closed_ring_of_quads() {
+        let params = TorusParams {
+            major_radius: 2.0,
+            minor_radius: 0.5,
+            major_segments: 8,
+            minor_segments: 6,
+        };
+        let mesh = torus_mesh(&params);
+
+        assert_eq!(mesh.vertex_count(), 8 * 6);
+        assert_eq!(mesh.face_count(), 8 * 6);
+        assert!((0..mesh.face_count()).all(|face| mesh.face_len(face) == 4));
+        assert!(check_solid(&mesh).is_solid());
+        // One hole, so V - E + F is zero
+        assert_eq!(mesh.vertex_count() + mesh.face_count(), mesh.edge_count());
+        let (min, max) = bounds(&mesh);
+        assert!((max.z - 2.5).abs() < 1e-5 && (min.z + 2.5).abs() < 1e-5);
+        assert!(max.y <= 0.5 && min.y >= -0.5);
+        for face in 0..mesh.face_count() {
+            let center = mesh.face_center(face);
+            let tube = Vec3::new(center.x, 0.0, center.z).normalized() * 2.0;
+            assert!(mesh.face_normal(face).dot(center - tube) > 0.0);
+        }
+
+        // The last quad wraps onto the first vertex, at the far corner of
+        // the texture
+        let last = mesh.face_loops(mesh.face_count() - 1).nth(2).unwrap();
+        assert_eq!(mesh.get_loop(last).vertex, 0);
+        assert_eq!(mesh.loop_attributes(last).uv, Vec2::new(1.0, 1.0));
+    }
+
+    /// Volume enclosed by a mesh, negative when it's inside out.
+    fn volume(mesh: &PolyMesh) -> f32 {
+        let mut volume = 0.0;
+        for face in 0..mesh.face_count() {
+            for [a, b, c] in mesh.face_triangles(face) {
+                let [a, b, c] = [a, b, c].map(|vertex| mesh.position(vertex));
+                volume += a.dot(b.cross(c)) / 6.0;
+            }
+        }
+        volume
+    }
+
+    fn material_count(mesh: &PolyMesh, material: usize) -> usize {
+        (0..mesh.face_count()).filter(|&face| mesh.face_material(face) == material).count()
+    }
+
+    fn stair(layout: StairLayout, support: StairSupport) -> PolyMesh {
+        stair_mesh(&StairParams {
+            steps: 5,
+            rise: 0.5,
+            run: 1.0,
+            width: 2.0,
+            layout,
+            support,
+            ..StairParams::default()
+        })
+    }
+
+    #[test]
+    fn straight_stair_is_a_solid_of_quads() {
+        let mesh = stair(StairLayout::Straight, StairSupport::Solid);
+
+        assert!(check_solid(&mesh).is_solid());
+        assert!((0..mesh.face_count()).all(|face| mesh.face_len(face) == 4));
+        // Columns of 1 to 5 blocks, 2 wide, 1 deep and 0.5 high
+        assert!((volume(&mesh) - 15.0).abs() < 1e-4);
+        assert_eq!(bounds(&mesh), (Vec3::new(-1.0, 0.0, -5.0), Vec3::new(1.0, 2.5, 0.0)));
+        assert_eq!(material_count(&mesh, STAIR_TREAD_MATERIAL), 5);
+        assert_eq!(material_count(&mesh, STAIR_RISER_MATERIAL), 5);
+        for face in (0..mesh.face_count()).filter(|&face| mesh.face_material(face) == STAIR_RISER_MATERIAL) {
+            assert!(mesh.face_normal(face).dot(Vec3::new(0.0, 0.0, 1.0)) > 0.99);
+        }
+        // UVs are in world units
+        let tread = (0..mesh.face_count()).find(|&face| mesh.face_material(face) == STAIR_TREAD_MATERIAL).unwrap();
+        let uvs: Vec<Vec2> = mesh.face_loops(tread).map(|l| mesh.loop_attributes(l).uv).collect();
+        let span = |value: fn(&Vec2) -> f32| {
+            let values: Vec<f32> = uvs.iter().map(value).collect();
+            values.iter().cloned().fold(f32::MIN, f32::max) - values.iter().cloned().fold(f32::MAX, f32::min)
+        };
+        let mut spans = [span(|uv| uv.x), span(|uv| uv.y)];
+        spans.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert!((spans[0] - 1.0).abs() < 1e-4 && (spans[1] - 2.0).abs() < 1e-4, "{:?}", uvs);
+    }
+
+    #[test]
+    fn l_shaped_stair_turns_after_its_landing() {
+        let mesh = stair(StairLayout::LShaped, StairSupport::Solid);
+
+        assert!(check_solid(&mesh).is_solid());
+        assert!((0..mesh.face_count()).all(|face| mesh.face_len(face) == 4));
+        // Two steps, a 2 by 2 landing at 3 rises, then two more steps
+        let blocks = 2.0 * (1.0 + 2.0) + 4.0 * 3.0 + 2.0 * (4.0 + 5.0);
+        assert!((volume(&mesh) - blocks * 0.5).abs() < 1e-4);
+        assert_eq!(bounds(&mesh), (Vec3::new(-3.0, 0.0, -4.0), Vec3::new(1.0, 2.5, 0.0)));
+        assert_eq!(material_count(&mesh, STAIR_TREAD_MATERIAL), 5);
+        assert_eq!(material_count(&mesh, STAIR_RISER_MATERIAL), 5);
+        // The last riser faces back along the second flight
+        let risers: Vec<FaceId> =
+            (0..mesh.face_count()).filter(|&face| mesh.face_material(face) == STAIR_RISER_MATERIAL).collect();
+        let highest = risers.iter().max_by(|&&a, &&b| mesh.face_center(a).y.partial_cmp(&mesh.face_center(b).y).unwrap());
+        assert!(mesh.face_normal(*highest.unwrap()).dot(Vec3::new(1.0, 0.0, 0.0)) > 0.99);
+    }
+
+    #[test]
+    fn spiral_stair_winds_around_its_middle() {
+        let mesh = stair(StairLayout::Spiral, StairSupport::Solid);
+        assert!(check_solid(&mesh).is_solid());
+        assert!((0..mesh.face_count()).all(|face| mesh.face_len(face) == 4));
+        assert!(volume(&mesh) > 0.0);
+        assert_eq!(material_count(&mesh, STAIR_TREAD_MATERIAL), 5);
+        assert_eq!(material_count(&mesh, STAIR_RISER_MATERIAL), 5);
+
+        // Without a middle, the steps meet on the axis
+        let closed = stair_mesh(&StairParams {
+            layout: StairLayout::Spiral,
+            inner_radius: 0.0,
+            ..StairParams::default()
+        });
+        assert!(check_solid(&closed).is_solid());
+        assert!(volume(&closed) > 0.0);
+        assert!(closed.positions().all(|position| Vec3::new(position.x, 0.0, position.z).length() < 1.0 + 1e-5));
+    }
+
+    #[test]
+    fn stringers_carry_separate_steps() {
+        for (layout, stringers) in [(StairLayout::Straight, 2), (StairLayout::LShaped, 4), (StairLayout::Spiral, 2)] {
+            let mesh = stair(layout, StairSupport::Stringers);
+
+            assert_eq!(mesh.face_islands().len(), 5 + stringers, "{:?}", layout);
+            assert!(check_solid(&mesh).is_solid(), "{:?}: {}", layout, check_solid(&mesh));
+            assert!(volume(&mesh) > 0.0);
+            assert_eq!(material_count(&mesh, STAIR_TREAD_MATERIAL), 5);
+            assert_eq!(material_count(&mesh, STAIR_RISER_MATERIAL), 5);
+            assert!(mesh.positions().all(|position| position.y >= 0.0));
+        }
+        assert_eq!(StairLayout::from_index(3), None);
+        assert_eq!(StairSupport::from_index(1), Some(StairSupport::Stringers));
+    }
+}
@@ -0,0 +1,236 @@
+use std::fmt;
+
+use crate::json::{Json, JsonError};
+use crate::math::{Color, Vec2, Vec3};
+use crate::poly_mesh::{MeshArrays, PolyMesh, TopologyError};
+
+/// Value of the "format" key every `.prodot` file starts with.
+pub const FORMAT_NAME: &str = "prodot";
+/// Version written to new files. Files of a later version aren't read.
+pub const FORMAT_VERSION: usize = 1;
+
+/// Why a `.prodot` file couldn't be read.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ProdotFileError {
+    Json(JsonError),
+    /// Valid JSON that isn't laid out like a `.prodot` file.
+    Format(String),
+    Topology(TopologyError),
+}
+
+impl fmt::Display for ProdotFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProdotFileError::Json(err) => write!(f, "{}", err),
+            ProdotFileError::Format(message) => write!(f, "{}", message),
+            ProdotFileError::Topology(err) => write!(f, "invalid topology: {}", err),
+        }
+    }
+}
+
+/// Writes the topology as the JSON text of a `.prodot` file: the vertex
+/// positions one per line, then one line per face with its material slot,
+/// vertices and the normal, UV and color of each corner. Everything is in
+/// the mesh's own order, so an edit only changes the lines of what it
+/// touched. Tangents are left out, they're recalculated on load.
+pub fn write_prodot(mesh: &PolyMesh) -> String {
+    let vertices: Vec<Json> = mesh.positions().map(|position| vec3_json(&[position])).collect();
+
+    let faces: Vec<Json> = (0..mesh.face_count())
+        .map(|face| {
+            let loops = mesh.face_loops(face);
+            let attributes: Vec<_> = loops.clone().map(|l| *mesh.loop_attributes(l)).collect();
+            let normals: Vec<Vec3> = attributes.iter().map(|attributes| attributes.normal).collect();
+            let uvs: Vec<Json> = attributes
+                .iter()
+                .flat_map(|attributes| [attributes.uv.x, attributes.uv.y])
+                .map(Json::from)
+                .collect();
+            let colors: Vec<Json> = attributes
+                .iter()
+                .flat_map(|attributes| {
+                    let color = attributes.color;
+                    [color.r, color.g, color.b, color.a]
+                })
+                .map(Json::from)
+                .collect();
+
+            Json::object(vec![
+                ("material", Json::from(mesh.face_material(face))),
+                ("vertices", Json::from(mesh.face_vertices(face).collect::<Vec<_>>())),
+                ("normals", vec3_json(&normals)),
+                ("uvs", Json::Array(uvs)),
+                ("colors", Json::Array(colors)),
+            ])
+        })
+        .collect();
+
+    Json::object(vec![
+        ("format", Json::from(FORMAT_NAME)),
+        ("version", Json::from(FORMAT_VERSION)),
+        ("vertices", Json::Array(vertices)),
+        ("faces", Json::Array(faces)),
+    ])
+    .to_pretty_string()
+}
+
+/// Reads the topology of a `.prodot` file. Faces without normals, UVs or
+/// colors get the defaults for them.
+pub fn read_prodot(text: &str) -> Result<PolyMesh, ProdotFileError> {
+    let json = Json::parse(text).map_err(ProdotFileError::Json)?;
+    if json.get("format").and_then(Json::as_str) != Some(FORMAT_NAME) {
+        return Err(format_error("not a .prodot file".to_string()));
+    }
+    match json.get("version").and_then(Json::as_usize) {
+        Some(version) if version <= FORMAT_VERSION => (),
+        Some(version) => return Err(format_error(format!("version {} is newer than this reader", version))),
+        None => return Err(format_error("missing version".to_string())),
+    }
+
+    let mut arrays = MeshArrays::default();
+    for (index, vertex) in array(&json, "vertices")?.iter().enumerate() {
+        let position = floats(vertex, 3).ok_or_else(|| format_error(format!("vertex {} isn't 3 numbers", index)))?;
+        arrays.positions.push(Vec3::new(position[0], position[1], position[2]));
+    }
+
+    let faces = array(&json, "faces")?;
+    let mut has_normals = Vec::with_capacity(faces.len());
+    for (index, face) in faces.iter().enumerate() {
+        let vertices = face
+            .get("vertices")
+            .and_then(Json::as_array)
+            .and_then(|vertices| vertices.iter().map(Json::as_usize).collect::<Option<Vec<_>>>())
+            .ok_or_else(|| format_error(format!("face {} has no vertex list", index)))?;
+        let material = match face.get("material") {
+            Some(material) => material
+                .as_usize()
+                .ok_or_else(|| format_error(format!("face {} has an invalid material slot", index)))?,
+            None => 0,
+        };
+        let corners = vertices.len();
+        let attribute = |key: &str, size: usize| -> Result<Option<Vec<f32>>, ProdotFileError> {
+            match face.get(key) {
+                Some(values) => floats(values, corners * size)
+                    .map(Some)
+                    .ok_or_else(|| format_error(format!("face {} needs {} {} numbers", index, corners * size, key))),
+                None => Ok(None),
+            }
+        };
+
+        let normals = attribute("normals", 3)?;
+        has_normals.push(normals.is_some());
+        let normals = normals.unwrap_or_else(|| vec![0.0; corners * 3]);
+        let uvs = attribute("uvs", 2)?.unwrap_or_else(|| vec![0.0; corners * 2]);
+        let colors = attribute("colors", 4)?.unwrap_or_else(|| [1.0; 4].repeat(corners));
+
+        arrays.face_sizes.push(corners);
+        arrays.face_materials.push(material);
+        arrays.face_vertices.extend(vertices);
+        arrays.normals.extend(normals.chunks_exact(3).map(|n| Vec3::new(n[0], n[1], n[2])));
+        arrays.uvs.extend(uvs.chunks_exact(2).map(|uv| Vec2::new(uv[0], uv[1])));
+        arrays.colors.extend(colors.chunks_exact(4).map(|c| Color::rgba(c[0], c[1], c[2], c[3])));
+    }
+
+    let mut mesh = PolyMesh::from_arrays(&arrays).map_err(ProdotFileError::Topology)?;
+    // Faces without stored normals get their face normal
+    for (face, has_normals) in has_normals.into_iter().enumerate() {
+        if !has_normals {
+            let normal = mesh.face_normal(face);
+            for l in mesh.face_loops(face) {
+                mesh.loop_attributes_mut(l).normal = normal;
+            }
+        }
+    }
+    mesh.recalculate_tangents();
+    Ok(mesh)
+}
+
+fn format_error(message: String) -> ProdotFileError {
+    ProdotFileError::Format(message)
+}
+
+fn array<'a>(json: &'a Json, key: &str) -> Result<&'a [Json], ProdotFileError> {
+    json.get(key)
+        .and_then(Json::as_array)
+        .ok_or_else(|| format_error(format!("missing {} list", key)))
+}
+
+/// The `count` numbers of a JSON array.
+fn floats(json: &Json, count: usize) -> Option<Vec<f32>> {
+    let values = json.as_array()?;
+    if values.len() != count {
+        return None;
+    }
+    values.iter().map(|value| value.as_f64().map(|value| value as f32)).collect()
+}
+
+/// The components of `vectors` as one flat array.
+fn vec3_json(vectors: &[Vec3]) -> Json {
+    Json::Array(
+        vectors
+            .iter()
+            .flat_map(|vector| [vector.x, vector.y, vector.z])
+            .map(Json::from)
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poly_mesh::tests::cube;
+
+    #[test]
+    fn cube_reads_back_the_same() {
+        let mut mesh = cube();
+        mesh.set_face_material(3, 2);
+        let l = mesh.face_loops(1).start;
+        mesh.loop_attributes_mut(l).uv = Vec2::new(0.25, 0.1);
+        mesh.loop_attributes_mut(l).color = Color::rgba(1.0, 0.5, 0.0, 1.0);
+        mesh.recalculate_tangents();
+
+        let text = write_prodot(&mesh);
+        let read = read_prodot(&text).unwrap();
+
+        assert_eq!(read.to_arrays(), mesh.to_arrays());
+        assert_eq!(write_prodot(&read), text);
+    }
+
+    #[test]
+    fn an_edit_changes_one_line() {
+        let mut mesh = cube();
+        let before = write_prodot(&mesh);
+        mesh.set_position(5, Vec3::new(1.0, 0.0, -0.5));
+        let after = write_prodot(&mesh);
+
+        assert_eq!(before.lines().count(), after.lines().count());
+        let changed: Vec<(&str, &str)> = before.lines().zip(after.lines()).filter(|(a, b)| a != b).collect();
+        assert_eq!(changed, vec![("    [1, 0, 0],", "    [1, 0, -0.5],")]);
+    }
+
+    #[test]
+    fn minimal_faces_get_default_attributes() {
+        let text = r#"{
+            "format": "prodot", "version": 1,
+            "vertices": [[0, 0, 0], [1, 0, 0], [1, 0, -1]],
+            "faces": [{"vertices": [0, 1, 2]}]
+        }"#;
+        let mesh = read_prodot(text).unwrap();
+
+        assert_eq!(mesh.face_count(), 1);
+        assert_eq!(mesh.face_material(0), 0);
+        let attributes = mesh.loop_attributes(0);
+        assert_eq!(attributes.color, Color::white());
+        assert!((attributes.normal - Vec3::new(0.0, 1.0, 0.0)).length() < 1e-6);
+    }
+
+    #[test]
+    fn invalid_files_are_reported() {
+        assert!(matches!(read_prodot("{"), Err(ProdotFileError::Json(_))));
+        assert!(matches!(read_prodot(r#"{"format": "obj"}"#), Err(ProdotFileError::Format(_))));
+        let newer = r#"{"format": "prodot", "version": 99, "vertices": [], "faces": []}"#;
+        assert!(matches!(read_prodot(newer), Err(ProdotFileError::Format(_))));
+        let repeated = r#"{"format": "prodot", "version": 1, "vertices": [[0,0,0],[1,0,0]], "faces": [{"vertices": [0, 1, 0]}]}"#;
+        assert!(matches!(read_prodot(repeated), Err(ProdotFileError::Topology(_))));
+    }
+}
@@ -0,0 +1,249 @@
+use crate::math::{Color, Tangent, Vec2, Vec3};
+use crate::poly_mesh::{LoopId, PolyMesh};
+
+/// Flat vertex and index arrays for one Godot mesh surface, built from the
+/// face loops of a `PolyMesh`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SurfaceArrays {
+    /// Material slot shared by every face in the surface.
+    pub material: usize,
+    pub positions: Vec<Vec3>,
+    pub normals: Vec<Vec3>,
+    pub tangents: Vec<Tangent>,
+    pub colors: Vec<Color>,
+    pub uvs: Vec<Vec2>,
+    /// Triangle indices, wound clockwise since that is what Godot treats
+    /// as front facing.
+    pub indices: Vec<u32>,
+    /// First loop each render vertex was built from.
+    pub vertex_loops: Vec<LoopId>,
+}
+
+impl SurfaceArrays {
+    pub fn vertex_count(&self) -> usize {
+        self.positions.len()
+    }
+}
+
+/// Every surface of a mesh, plus where each face corner ended up.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MeshSurfaces {
+    /// One surface per material slot that has faces, in slot order.
+    pub surfaces: Vec<SurfaceArrays>,
+    /// Surface and render vertex of each loop. Several loops map to the
+    /// same render vertex when their attributes match.
+    pub loop_vertices: Vec<(usize, usize)>,
+}
+
+impl MeshSurfaces {
+    pub fn vertex_count(&self) -> usize {
+        self.surfaces.iter().map(|surface| surface.vertex_count()).sum()
+    }
+
+    /// Index of the surface built for `material`, if any face uses it.
+    pub fn material_surface(&self, material: usize) -> Option<usize> {
+        self.surfaces.iter().position(|surface| surface.material == material)
+    }
+
+    /// Copies the current attributes of `loops` into the render vertices
+    /// they map to, and returns those render vertices as (surface, vertex)
+    /// pairs. Returns `None` when a loop no longer matches the other loops
+    /// sharing its render vertex, e.g. because an edge turned hard, in
+    /// which case the surfaces have to be built again.
+    pub fn update_loops(&mut self, mesh: &PolyMesh, loops: &[LoopId]) -> Option<Vec<(usize, usize)>> {
+        for &l in loops {
+            let (surface, vertex) = self.loop_vertices[l];
+            let first = self.surfaces[surface].vertex_loops[vertex];
+            if mesh.loop_attributes(first) != mesh.loop_attributes(l) {
+                return None;
+            }
+        }
+
+        let mut updated = Vec::with_capacity(loops.len());
+        for &l in loops {
+            let (index, vertex) = self.loop_vertices[l];
+            let corner = mesh.get_loop(l);
+            let surface = &mut self.surfaces[index];
+            surface.positions[vertex] = mesh.position(corner.vertex);
+            surface.normals[vertex] = corner.attributes.normal;
+            surface.tangents[vertex] = corner.attributes.tangent;
+            surface.colors[vertex] = corner.attributes.color;
+            surface.uvs[vertex] = corner.attributes.uv;
+            updated.push((index, vertex));
+        }
+        updated.sort_unstable();
+        updated.dedup();
+        Some(updated)
+    }
+}
+
+/// Builds one surface per material slot. Within a surface, face corners on
+/// the same vertex share a render vertex when their normals, UVs, colors
+/// and tangents all match, and split where they differ, e.g. along hard
+/// edges and UV seams.
+pub fn build_surfaces(mesh: &PolyMesh) -> MeshSurfaces {
+    let mut materials: Vec<usize> = (0..mesh.face_count()).map(|face| mesh.face_material(face)).collect();
+    materials.sort_unstable();
+    materials.dedup();
+
+    let mut surfaces = MeshSurfaces {
+        surfaces: Vec::with_capacity(materials.len()),
+        loop_vertices: vec![(0, 0); mesh.loop_count()],
+    };
+
+    for (index, &material) in materials.iter().enumerate() {
+        let mut surface = SurfaceArrays {
+            material,
+            ..SurfaceArrays::default()
+        };
+        // Render vertices already built for each mesh vertex
+        let mut vertex_splits: Vec<Vec<usize>> = vec![Vec::new(); mesh.vertex_count()];
+
+        for face in (0..mesh.face_count()).filter(|&face| mesh.face_material(face) == material) {
+            for l in mesh.face_loops(face) {
+                let corner = mesh.get_loop(l);
+                let splits = &mut vertex_splits[corner.vertex];
+                let shared = splits
+                    .iter()
+                    .copied()
+                    .find(|&vertex| *mesh.loop_attributes(surface.vertex_loops[vertex]) == corner.attributes);
+                if let Some(vertex) = shared {
+                    surfaces.loop_vertices[l] = (index, vertex);
+                    continue;
+                }
+
+                splits.push(surface.positions.len());
+                surfaces.loop_vertices[l] = (index, surface.positions.len());
+                surface.positions.push(mesh.position(corner.vertex));
+                surface.normals.push(corner.attributes.normal);
+                surface.tangents.push(corner.attributes.tangent);
+                surface.colors.push(corner.attributes.color);
+                surface.uvs.push(corner.attributes.uv);
+                surface.vertex_loops.push(l);
+            }
+
+            for [a, b, c] in mesh.face_loop_triangles(face) {
+                surface.indices.push(surfaces.loop_vertices[a].1 as u32);
+                surface.indices.push(surfaces.loop_vertices[c].1 as u32);
+                surface.indices.push(surfaces.loop_vertices[b].1 as u32);
+            }
+        }
+
+        surfaces.surfaces.push(surface);
+    }
+
+    surfaces
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn two_quads() -> PolyMesh {
+        let mut mesh = PolyMesh::new();
+        let ids: Vec<_> = [
+            (0.0, 0.0, 0.0),
+            (1.0, 0.0, 0.0),
+            (1.0, 1.0, 0.0),
+            (0.0, 1.0, 0.0),
+            (1.0, 0.0, -1.0),
+            (1.0, 1.0, -1.0),
+        ]
+        .iter()
+        .map(|&(x, y, z)| mesh.add_vertex(Vec3::new(x, y, z)))
+        .collect();
+        mesh.add_face(&[ids[0], ids[1], ids[2], ids[3]]).unwrap();
+        mesh.add_face(&[ids[1], ids[4], ids[5], ids[2]]).unwrap();
+        mesh
+    }
+
+    #[test]
+    fn hard_corners_get_their_own_render_vertex() {
+        let mesh = two_quads();
+        let surfaces = build_surfaces(&mesh);
+        let surface = &surfaces.surfaces[0];
+
+        assert_eq!(surface.vertex_count(), 8);
+        assert_eq!(surface.indices.len(), 12);
+        for (vertex, &l) in surface.vertex_loops.iter().enumerate() {
+            assert_eq!(surfaces.loop_vertices[l], (0, vertex));
+            assert_eq!(surface.positions[vertex], mesh.position(mesh.get_loop(l).vertex));
+        }
+    }
+
+    #[test]
+    fn matching_corners_share_a_render_vertex() {
+        let mut mesh = two_quads();
+        for l in 0..mesh.loop_count() {
+            mesh.loop_attributes_mut(l).normal = Vec3::new(0.0, 0.0, 1.0);
+        }
+        let surfaces = build_surfaces(&mesh);
+
+        assert_eq!(surfaces.vertex_count(), 6);
+        assert_eq!(surfaces.loop_vertices[1], surfaces.loop_vertices[4]);
+        assert_eq!(surfaces.surfaces[0].indices.len(), 12);
+    }
+
+    #[test]
+    fn shared_corners_keep_per_face_normals() {
+        let surfaces = build_surfaces(&two_quads());
+        let surface = &surfaces.surfaces[0];
+
+        // Vertex 1 is the second corner of the first face and the first
+        // corner of the second one
+        assert_eq!(surface.positions[1], surface.positions[4]);
+        assert_eq!(surface.normals[1], Vec3::new(0.0, 0.0, 1.0));
+        assert_eq!(surface.normals[4], Vec3::new(1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn triangles_are_wound_clockwise() {
+        let surfaces = build_surfaces(&two_quads());
+        assert_eq!(surfaces.surfaces[0].indices[..6], [0, 2, 1, 0, 3, 2]);
+    }
+
+    #[test]
+    fn faces_are_grouped_by_material() {
+        let mut mesh = two_quads();
+        mesh.set_face_material(0, 3);
+        let surfaces = build_surfaces(&mesh);
+
+        assert_eq!(surfaces.surfaces.len(), 2);
+        assert_eq!(surfaces.surfaces[0].material, 0);
+        assert_eq!(surfaces.surfaces[1].material, 3);
+        assert_eq!(surfaces.material_surface(3), Some(1));
+        assert_eq!(surfaces.material_surface(1), None);
+
+        // The second quad is alone in surface 0, so its indices start over
+        assert_eq!(surfaces.surfaces[0].indices[..3], [0, 2, 1]);
+        assert_eq!(surfaces.loop_vertices[4], (0, 0));
+        assert_eq!(surfaces.loop_vertices[0], (1, 0));
+    }
+
+    #[test]
+    fn updating_loops_moves_their_render_vertices() {
+        let mut mesh = two_quads();
+        let mut surfaces = build_surfaces(&mesh);
+
+        mesh.set_position(2, Vec3::new(1.0, 2.0, 0.0));
+        let loops = mesh.vertex_loops(2);
+        let updated = surfaces.update_loops(&mesh, &loops).unwrap();
+
+        assert_eq!(updated, vec![(0, 2), (0, 7)]);
+        assert_eq!(surfaces.surfaces[0].positions[2], Vec3::new(1.0, 2.0, 0.0));
+        assert_eq!(surfaces.surfaces[0].positions[7], Vec3::new(1.0, 2.0, 0.0));
+    }
+
+    #[test]
+    fn updating_split_loops_needs_a_rebuild() {
+        let mut mesh = two_quads();
+        for l in 0..mesh.loop_count() {
+            mesh.loop_attributes_mut(l).normal = Vec3::new(0.0, 0.0, 1.0);
+        }
+        let mut surfaces = build_surfaces(&mesh);
+
+        // Loops 1 and 4 share a render vertex until their normals differ
+        mesh.loop_attributes_mut(4).normal = Vec3::new(1.0, 0.0, 0.0);
+        assert_eq!(surfaces.update_loops(&mesh, &[1, 4]), None);
+    }
+}
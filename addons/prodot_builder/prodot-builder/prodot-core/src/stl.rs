@@ -0,0 +1,323 @@
+use std::fmt::{self, Write};
+
+use crate::math::{Transform, Vec3};
+use crate::obj::obj_name;
+use crate::poly_mesh::{EdgeId, FaceId, PolyMesh};
+
+/// Faces with less area than this are reported as degenerate. Slicers
+/// can't tell which way they face.
+pub const MIN_FACE_AREA: f32 = 1e-8;
+
+const HEADER: &[u8] = b"Exported by Prodot Builder";
+const HEADER_LEN: usize = 80;
+const TRIANGLE_LEN: usize = 50;
+
+/// One mesh to write into an STL file.
+#[derive(Clone, Debug)]
+pub struct StlObject<'a> {
+    pub mesh: &'a PolyMesh,
+    /// Applied to positions, e.g. the node's global transform.
+    pub transform: Transform,
+}
+
+/// What stops a mesh from being a closed solid, found by `check_solid`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MeshReport {
+    /// Edges shared by more than two faces.
+    pub non_manifold_edges: Vec<EdgeId>,
+    /// Edges with only one face, on the rim of a hole.
+    pub boundary_edges: Vec<EdgeId>,
+    /// Edges whose two faces run along them in the same direction, so
+    /// one of the faces is flipped.
+    pub flipped_edges: Vec<EdgeId>,
+    /// Faces with no area, see `MIN_FACE_AREA`.
+    pub degenerate_faces: Vec<FaceId>,
+}
+
+impl MeshReport {
+    /// Whether the mesh is a closed, consistently wound solid.
+    pub fn is_solid(&self) -> bool {
+        self.non_manifold_edges.is_empty()
+            && self.boundary_edges.is_empty()
+            && self.flipped_edges.is_empty()
+            && self.degenerate_faces.is_empty()
+    }
+}
+
+impl fmt::Display for MeshReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_solid() {
+            return write!(f, "closed solid");
+        }
+        let problems: Vec<String> = [
+            (self.non_manifold_edges.len(), "non-manifold edge(s)"),
+            (self.boundary_edges.len(), "open boundary edge(s)"),
+            (self.flipped_edges.len(), "edge(s) between flipped faces"),
+            (self.degenerate_faces.len(), "degenerate face(s)"),
+        ]
+        .iter()
+        .filter(|(count, _)| *count > 0)
+        .map(|(count, problem)| format!("{} {}", count, problem))
+        .collect();
+        write!(f, "{}", problems.join(", "))
+    }
+}
+
+/// Checks that `mesh` encloses a volume: every edge has exactly two
+/// faces wound against each other, and no face is degenerate.
+pub fn check_solid(mesh: &PolyMesh) -> MeshReport {
+    let mut report = MeshReport::default();
+    for edge in 0..mesh.edge_count() {
+        let loops = mesh.edge_loops(edge);
+        match loops.len() {
+            0 => (),
+            1 => report.boundary_edges.push(edge),
+            2 => {
+                // Neighbouring faces start the shared edge at opposite ends
+                if mesh.get_loop(loops[0]).vertex == mesh.get_loop(loops[1]).vertex {
+                    report.flipped_edges.push(edge);
+                }
+            }
+            _ => report.non_manifold_edges.push(edge),
+        }
+    }
+    for face in 0..mesh.face_count() {
+        let positions: Vec<Vec3> = mesh.face_vertices(face).map(|vertex| mesh.position(vertex)).collect();
+        if polygon_area(&positions) < MIN_FACE_AREA {
+            report.degenerate_faces.push(face);
+        }
+    }
+    report
+}
+
+/// Writes the objects as one ASCII STL solid named `name`.
+pub fn write_stl_ascii(name: &str, objects: &[StlObject]) -> String {
+    let name = obj_name(name);
+    let mut out = String::new();
+    writeln!(out, "solid {}", name).unwrap();
+    for triangle in outward_triangles(objects) {
+        let normal = triangle_normal(&triangle);
+        writeln!(out, "  facet normal {} {} {}", normal.x, normal.y, normal.z).unwrap();
+        writeln!(out, "    outer loop").unwrap();
+        for corner in &triangle {
+            writeln!(out, "      vertex {} {} {}", corner.x, corner.y, corner.z).unwrap();
+        }
+        writeln!(out, "    endloop").unwrap();
+        writeln!(out, "  endfacet").unwrap();
+    }
+    writeln!(out, "endsolid {}", name).unwrap();
+    out
+}
+
+/// Writes the objects as a binary STL file, little-endian as the format
+/// requires.
+pub fn write_stl_binary(objects: &[StlObject]) -> Vec<u8> {
+    let triangles = outward_triangles(objects);
+    let mut out = Vec::with_capacity(HEADER_LEN + 4 + triangles.len() * TRIANGLE_LEN);
+    out.extend_from_slice(HEADER);
+    out.resize(HEADER_LEN, 0);
+    out.extend_from_slice(&(triangles.len() as u32).to_le_bytes());
+    for triangle in &triangles {
+        let normal = triangle_normal(triangle);
+        for vector in std::iter::once(&normal).chain(triangle.iter()) {
+            for component in &[vector.x, vector.y, vector.z] {
+                out.extend_from_slice(&component.to_le_bytes());
+            }
+        }
+        // Attribute byte count, unused
+        out.extend_from_slice(&0u16.to_le_bytes());
+    }
+    out
+}
+
+/// Triangulates the objects in world space, wound counter-clockwise seen
+/// from outside. Each connected part whose faces point inwards, so that
+/// it encloses a negative volume, is turned inside out. Triangles
+/// without area are left out.
+fn outward_triangles(objects: &[StlObject]) -> Vec<[Vec3; 3]> {
+    let mut triangles = Vec::new();
+    for object in objects {
+        let mesh = object.mesh;
+        let mirrored = object.transform.is_mirrored();
+        for part in mesh.face_islands() {
+            let start = triangles.len();
+            let mut volume = 0.0;
+            for &face in &part {
+                for corners in mesh.face_triangles(face) {
+                    let [a, b, c] = corners.map(|vertex| object.transform.xform(mesh.position(vertex)));
+                    let triangle = if mirrored { [a, c, b] } else { [a, b, c] };
+                    if polygon_area(&triangle) < MIN_FACE_AREA {
+                        continue;
+                    }
+                    volume += triangle[0].dot(triangle[1].cross(triangle[2]));
+                    triangles.push(triangle);
+                }
+            }
+            if volume < 0.0 {
+                for triangle in &mut triangles[start..] {
+                    triangle.swap(1, 2);
+                }
+            }
+        }
+    }
+    triangles
+}
+
+fn triangle_normal(triangle: &[Vec3; 3]) -> Vec3 {
+    (triangle[1] - triangle[0]).cross(triangle[2] - triangle[0]).normalized()
+}
+
+/// Area of a polygon from its Newell normal, robust to concave and
+/// non-planar ones.
+fn polygon_area(positions: &[Vec3]) -> f32 {
+    let mut normal = Vec3::zero();
+    for (i, current) in positions.iter().enumerate() {
+        let next = positions[(i + 1) % positions.len()];
+        normal.x += (current.y - next.y) * (current.z + next.z);
+        normal.y += (current.z - next.z) * (current.x + next.x);
+        normal.z += (current.x - next.x) * (current.y + next.y);
+    }
+    normal.length() * 0.5
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poly_mesh::tests::cube;
+
+    /// The triangles of a binary STL file.
+    fn read_binary(bytes: &[u8]) -> Vec<[Vec3; 3]> {
+        let float = |offset: usize| f32::from_le_bytes([bytes[offset], bytes[offset + 1], bytes[offset + 2], bytes[offset + 3]]);
+        let count = u32::from_le_bytes([bytes[80], bytes[81], bytes[82], bytes[83]]) as usize;
+        assert_eq!(bytes.len(), HEADER_LEN + 4 + count * TRIANGLE_LEN);
+        (0..count)
+            .map(|triangle| {
+                let start = HEADER_LEN + 4 + triangle * TRIANGLE_LEN + 12;
+                let corner = |i: usize| Vec3::new(float(start + i * 12), float(start + i * 12 + 4), float(start + i * 12 + 8));
+                [corner(0), corner(1), corner(2)]
+            })
+            .collect()
+    }
+
+    fn volume(triangles: &[[Vec3; 3]]) -> f32 {
+        triangles.iter().map(|t| t[0].dot(t[1].cross(t[2]))).sum::<f32>() / 6.0
+    }
+
+    /// The cube with the faces in `flip` wound clockwise.
+    fn flipped_cube(flip: &[FaceId]) -> PolyMesh {
+        let cube = cube();
+        let mut mesh = PolyMesh::new();
+        for position in cube.positions() {
+            mesh.add_vertex(position);
+        }
+        for face in 0..cube.face_count() {
+            let mut vertices: Vec<usize> = cube.face_vertices(face).collect();
+            if flip.contains(&face) {
+                vertices.reverse();
+            }
+            mesh.add_face(&vertices).unwrap();
+        }
+        mesh
+    }
+
+    #[test]
+    fn cube_is_a_solid() {
+        let report = check_solid(&cube());
+        assert!(report.is_solid());
+        assert_eq!(report.to_string(), "closed solid");
+    }
+
+    #[test]
+    fn problems_are_reported() {
+        let mut mesh = PolyMesh::new();
+        for &(x, y, z) in &[(0.0, 0.0, 0.0), (1.0, 0.0, 0.0), (0.0, 1.0, 0.0), (0.0, 0.0, 1.0), (2.0, 0.0, 0.0)] {
+            mesh.add_vertex(Vec3::new(x, y, z));
+        }
+        mesh.add_face(&[0, 1, 2]).unwrap();
+        mesh.add_face(&[1, 0, 3]).unwrap();
+        // Flat along the shared edge, and a third face on it
+        mesh.add_face(&[0, 1, 4]).unwrap();
+
+        let report = check_solid(&mesh);
+        assert!(!report.is_solid());
+        assert_eq!(report.non_manifold_edges.len(), 1);
+        assert_eq!(report.degenerate_faces, vec![2]);
+        assert_eq!(report.boundary_edges.len(), 6);
+        assert_eq!(
+            report.to_string(),
+            "1 non-manifold edge(s), 6 open boundary edge(s), 1 degenerate face(s)"
+        );
+
+        assert_eq!(check_solid(&flipped_cube(&[0])).flipped_edges.len(), 4);
+    }
+
+    #[test]
+    fn binary_cube_has_twelve_outward_triangles() {
+        let mesh = cube();
+        let bytes = write_stl_binary(&[StlObject { mesh: &mesh, transform: Transform::identity() }]);
+        assert!(bytes.starts_with(HEADER));
+        let triangles = read_binary(&bytes);
+
+        assert_eq!(triangles.len(), 12);
+        assert!((volume(&triangles) - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn inside_out_and_mirrored_parts_are_wound_outward() {
+        let inverted = flipped_cube(&[0, 1, 2, 3, 4, 5]);
+        let mesh = cube();
+        let mut mirror = Transform::identity();
+        mirror.x = Vec3::new(-2.0, 0.0, 0.0);
+        mirror.origin = Vec3::new(5.0, 0.0, 0.0);
+        let bytes = write_stl_binary(&[
+            StlObject { mesh: &inverted, transform: Transform::identity() },
+            StlObject { mesh: &mesh, transform: mirror },
+        ]);
+        let triangles = read_binary(&bytes);
+
+        assert_eq!(triangles.len(), 24);
+        assert!((volume(&triangles[..12]) - 1.0).abs() < 1e-5);
+        assert!((volume(&triangles[12..]) - 2.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn concave_faces_keep_their_winding() {
+        // An L-shaped prism, whose caps a fan from the first corner would fold
+        let mut mesh = PolyMesh::new();
+        let corners = [(2.0, 0.0), (2.0, 1.0), (1.0, 1.0), (1.0, 2.0), (0.0, 2.0), (0.0, 0.0)];
+        let bottom: Vec<_> = corners.iter().map(|&(x, y)| mesh.add_vertex(Vec3::new(x, y, 0.0))).collect();
+        let top: Vec<_> = corners.iter().map(|&(x, y)| mesh.add_vertex(Vec3::new(x, y, 1.0))).collect();
+        mesh.add_face(&top).unwrap();
+        mesh.add_face(&bottom.iter().rev().copied().collect::<Vec<_>>()).unwrap();
+        for i in 0..6 {
+            let j = (i + 1) % 6;
+            mesh.add_face(&[bottom[i], bottom[j], top[j], top[i]]).unwrap();
+        }
+        assert!(check_solid(&mesh).is_solid());
+
+        let triangles = read_binary(&write_stl_binary(&[StlObject { mesh: &mesh, transform: Transform::identity() }]));
+        assert_eq!(triangles.len(), 2 * 4 + 6 * 2);
+        assert!((volume(&triangles) - 3.0).abs() < 1e-5);
+        for triangle in &triangles {
+            let normal = triangle_normal(triangle);
+            if triangle.iter().all(|corner| corner.z == 1.0) {
+                assert!(normal.is_equal_approx(Vec3::new(0.0, 0.0, 1.0), 1e-6));
+            } else if triangle.iter().all(|corner| corner.z == 0.0) {
+                assert!(normal.is_equal_approx(Vec3::new(0.0, 0.0, -1.0), 1e-6));
+            }
+        }
+    }
+
+    #[test]
+    fn ascii_lists_every_facet() {
+        let mesh = cube();
+        let text = write_stl_ascii("My Cube", &[StlObject { mesh: &mesh, transform: Transform::identity() }]);
+
+        assert!(text.starts_with("solid My_Cube\n"));
+        assert!(text.ends_with("endsolid My_Cube\n"));
+        assert_eq!(text.matches("facet normal").count(), 12);
+        assert_eq!(text.matches("vertex ").count(), 36);
+        // The bottom face points down
+        assert!(text.contains("facet normal 0 -1 0"));
+    }
+}
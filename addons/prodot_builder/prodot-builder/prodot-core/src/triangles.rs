@@ -0,0 +1,330 @@
+use std::collections::HashMap;
+
+use crate::math::{Color, Vec2, Vec3};
+use crate::poly_mesh::{PolyMesh, VertexId};
+
+/// Positions closer than this on every axis are welded into one vertex.
+pub const WELD_DISTANCE: f32 = 0.0001;
+/// Two triangles are merged into a quad when their normals are at least
+/// this aligned.
+pub const COPLANAR_DOT: f32 = 0.9999;
+
+/// One surface of a triangle mesh, laid out like Godot's surface arrays.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TriangleSurface {
+    pub positions: Vec<Vec3>,
+    /// One per position, or empty for default UVs.
+    pub uvs: Vec<Vec2>,
+    /// One per position, or empty for white.
+    pub colors: Vec<Color>,
+    /// Triangle indices wound clockwise, as Godot draws them. Empty when
+    /// the positions are the triangles themselves.
+    pub indices: Vec<u32>,
+}
+
+/// A triangle after welding, wound counter-clockwise.
+struct Triangle {
+    vertices: [VertexId; 3],
+    uvs: [Vec2; 3],
+    colors: [Color; 3],
+    material: usize,
+    normal: Vec3,
+}
+
+impl Triangle {
+    /// Index of the corner at `vertex`.
+    fn corner(&self, vertex: VertexId) -> usize {
+        self.vertices.iter().position(|&v| v == vertex).unwrap()
+    }
+}
+
+/// Builds editable topology from triangle surfaces, each of which becomes
+/// a material slot. Coincident positions are welded into shared vertices,
+/// and pairs of coplanar triangles that form a convex quad are merged
+/// back into one, as long as the UVs and colors along their shared edge
+/// agree. Triangles without an area or with a position that isn't finite
+/// are dropped.
+///
+/// Normals aren't read, the ProdotMesh recalculates them with its normal
+/// mode.
+pub fn poly_mesh_from_triangles(surfaces: &[TriangleSurface]) -> PolyMesh {
+    let mut mesh = PolyMesh::new();
+    let mut welded: HashMap<[i64; 3], VertexId> = HashMap::new();
+    let mut triangles: Vec<Triangle> = Vec::new();
+
+    for (material, surface) in surfaces.iter().enumerate() {
+        // NaN would weld with the origin, so those positions get no vertex
+        let vertices: Vec<Option<VertexId>> = surface
+            .positions
+            .iter()
+            .map(|&position| {
+                if !position.is_finite() {
+                    return None;
+                }
+                let key = [
+                    (position.x / WELD_DISTANCE).round() as i64,
+                    (position.y / WELD_DISTANCE).round() as i64,
+                    (position.z / WELD_DISTANCE).round() as i64,
+                ];
+                Some(*welded.entry(key).or_insert_with(|| mesh.add_vertex(position)))
+            })
+            .collect();
+
+        let indices: Vec<usize> = if surface.indices.is_empty() {
+            (0..surface.positions.len()).collect()
+        } else {
+            surface.indices.iter().map(|&index| index as usize).collect()
+        };
+        for triangle in indices.chunks_exact(3) {
+            if triangle.iter().any(|&index| index >= vertices.len()) {
+                continue;
+            }
+            // Godot's clockwise triangles, turned counter-clockwise
+            let corners = [triangle[0], triangle[2], triangle[1]];
+            let ids = match (vertices[corners[0]], vertices[corners[1]], vertices[corners[2]]) {
+                (Some(a), Some(b), Some(c)) => [a, b, c],
+                _ => continue,
+            };
+            if ids[0] == ids[1] || ids[1] == ids[2] || ids[2] == ids[0] {
+                continue;
+            }
+            let [a, b, c] = [mesh.position(ids[0]), mesh.position(ids[1]), mesh.position(ids[2])];
+            let normal = (b - a).cross(c - a);
+            // Huge positions can overflow the normal to infinity or NaN
+            if !normal.is_finite() || normal.length() <= f32::EPSILON {
+                continue;
+            }
+
+            triangles.push(Triangle {
+                vertices: ids,
+                uvs: corners.map(|corner| surface.uvs.get(corner).copied().unwrap_or_default()),
+                colors: corners.map(|corner| surface.colors.get(corner).copied().unwrap_or_default()),
+                material,
+                normal: normal.normalized(),
+            });
+        }
+    }
+
+    // Directed edge to the triangles that run along it
+    let mut edge_triangles: HashMap<(VertexId, VertexId), Vec<usize>> = HashMap::new();
+    for (t, triangle) in triangles.iter().enumerate() {
+        for k in 0..3 {
+            let edge = (triangle.vertices[k], triangle.vertices[(k + 1) % 3]);
+            edge_triangles.entry(edge).or_default().push(t);
+        }
+    }
+
+    let mut merged: Vec<bool> = vec![false; triangles.len()];
+    for t in 0..triangles.len() {
+        if merged[t] {
+            continue;
+        }
+        let triangle = &triangles[t];
+
+        // The diagonal of a triangulated quad is usually its longest edge
+        let mut edges = [0, 1, 2];
+        edges.sort_by(|&i, &j| {
+            let length = |k: usize| {
+                let from = mesh.position(triangle.vertices[k]);
+                from.distance_to(mesh.position(triangle.vertices[(k + 1) % 3]))
+            };
+            length(j).total_cmp(&length(i))
+        });
+
+        let quad = edges.iter().find_map(|&k| {
+            let (a, b) = (triangle.vertices[k], triangle.vertices[(k + 1) % 3]);
+            let candidates = edge_triangles.get(&(b, a))?;
+            candidates
+                .iter()
+                .copied()
+                .filter(|&other| other != t && !merged[other])
+                .find_map(|other| quad_corners(&mesh, &triangles, t, k, other).map(|corners| (other, corners)))
+        });
+
+        let (vertices, uvs, colors) = match quad {
+            Some((other, corners)) => {
+                merged[other] = true;
+                let mut vertices = Vec::with_capacity(4);
+                let mut uvs = Vec::with_capacity(4);
+                let mut colors = Vec::with_capacity(4);
+                for (source, vertex) in corners.iter() {
+                    let source = &triangles[*source];
+                    let corner = source.corner(*vertex);
+                    vertices.push(*vertex);
+                    uvs.push(source.uvs[corner]);
+                    colors.push(source.colors[corner]);
+                }
+                (vertices, uvs, colors)
+            }
+            None => (triangle.vertices.to_vec(), triangle.uvs.to_vec(), triangle.colors.to_vec()),
+        };
+        merged[t] = true;
+
+        let face = mesh
+            .add_face(&vertices)
+            .expect("welded faces have distinct vertices");
+        mesh.set_face_material(face, triangle.material);
+        for (l, (uv, color)) in mesh.face_loops(face).zip(uvs.into_iter().zip(colors)) {
+            let attributes = mesh.loop_attributes_mut(l);
+            attributes.uv = uv;
+            attributes.color = color;
+        }
+    }
+
+    mesh.recalculate_tangents();
+    mesh
+}
+
+/// Corners of the quad made of triangle `t` and `other`, which share the
+/// edge starting at corner `k` of `t`, as (triangle, vertex) pairs in
+/// counter-clockwise order. `None` if they shouldn't be merged.
+fn quad_corners(
+    mesh: &PolyMesh,
+    triangles: &[Triangle],
+    t: usize,
+    k: usize,
+    other: usize,
+) -> Option<[(usize, VertexId); 4]> {
+    let (first, second) = (&triangles[t], &triangles[other]);
+    if first.material != second.material || first.normal.dot(second.normal) < COPLANAR_DOT {
+        return None;
+    }
+
+    let a = first.vertices[k];
+    let b = first.vertices[(k + 1) % 3];
+    let c = first.vertices[(k + 2) % 3];
+    let d = second.vertices[(second.corner(a) + 1) % 3];
+    if d == c {
+        return None;
+    }
+
+    // Merging would lose a UV or color seam along the shared edge
+    for &vertex in [a, b].iter() {
+        let (i, j) = (first.corner(vertex), second.corner(vertex));
+        if first.uvs[i] != second.uvs[j] || first.colors[i] != second.colors[j] {
+            return None;
+        }
+    }
+
+    // t runs a -> b -> c and other b -> a -> d, so around the quad it's
+    // a -> d -> b -> c
+    let corners = [(t, a), (other, d), (t, b), (t, c)];
+    let positions: Vec<Vec3> = corners.iter().map(|&(_, vertex)| mesh.position(vertex)).collect();
+    let convex = (0..4).all(|i| {
+        let previous = positions[(i + 3) % 4];
+        let next = positions[(i + 1) % 4];
+        (positions[i] - previous).cross(next - positions[i]).dot(first.normal) > f32::EPSILON
+    });
+
+    if convex {
+        Some(corners)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poly_mesh::tests::cube;
+    use crate::render;
+
+    /// The surfaces Godot would draw for `mesh`, one per material slot.
+    fn rendered(mesh: &PolyMesh) -> Vec<TriangleSurface> {
+        render::build_surfaces(mesh)
+            .surfaces
+            .into_iter()
+            .map(|surface| TriangleSurface {
+                positions: surface.positions,
+                uvs: surface.uvs,
+                colors: surface.colors,
+                indices: surface.indices,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn rendered_cube_becomes_quads_again() {
+        let mesh = poly_mesh_from_triangles(&rendered(&cube()));
+
+        assert_eq!(mesh.vertex_count(), 8);
+        assert_eq!(mesh.face_count(), 6);
+        for face in 0..6 {
+            assert_eq!(mesh.face_len(face), 4);
+            let outward = mesh.face_center(face) - Vec3::new(0.5, 0.5, 0.5);
+            assert!(mesh.face_normal(face).dot(outward) > 0.0);
+        }
+        assert!((0..mesh.edge_count()).all(|edge| mesh.is_manifold_edge(edge)));
+    }
+
+    #[test]
+    fn surfaces_become_material_slots() {
+        let mut source = cube();
+        source.set_face_material(2, 1);
+        let mesh = poly_mesh_from_triangles(&rendered(&source));
+
+        assert_eq!(mesh.vertex_count(), 8);
+        assert_eq!(mesh.material_count(), 2);
+        assert_eq!((0..6).filter(|&face| mesh.face_material(face) == 1).count(), 1);
+    }
+
+    #[test]
+    fn seams_bends_and_slivers_stay_triangles() {
+        // Unindexed clockwise triangles: a square split along its diagonal,
+        // a triangle folded up from it, and one without an area
+        let positions = vec![
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(1.0, 1.0, 0.0),
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            Vec3::new(1.0, 1.0, 0.0),
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(1.0, 1.0, 0.0),
+            Vec3::new(2.0, 0.0, 1.0),
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(0.5, 0.0, 0.0),
+            Vec3::new(1.0, 0.0, 0.0),
+        ];
+        let quad = TriangleSurface {
+            positions: positions.clone(),
+            ..TriangleSurface::default()
+        };
+        let mesh = poly_mesh_from_triangles(&[quad]);
+        assert_eq!(mesh.face_count(), 2);
+        assert_eq!(mesh.face_len(0), 4);
+        assert_eq!(mesh.face_len(1), 3);
+
+        // A UV seam along the diagonal keeps the square split
+        let mut uvs = vec![Vec2::new(0.0, 0.0); positions.len()];
+        uvs[1] = Vec2::new(0.5, 0.5);
+        let seam = TriangleSurface {
+            positions,
+            uvs,
+            ..TriangleSurface::default()
+        };
+        assert_eq!(poly_mesh_from_triangles(&[seam]).face_count(), 3);
+    }
+
+    #[test]
+    fn positions_that_arent_finite_are_dropped() {
+        let surface = TriangleSurface {
+            positions: vec![
+                Vec3::new(0.0, 0.0, 0.0),
+                Vec3::new(f32::NAN, 1.0, 0.0),
+                Vec3::new(1.0, 0.0, 0.0),
+                Vec3::new(0.0, 0.0, 0.0),
+                Vec3::new(0.0, 1.0, 0.0),
+                Vec3::new(1.0, 1.0, 0.0),
+                Vec3::new(f32::MAX, 0.0, 0.0),
+                Vec3::new(0.0, f32::MAX, 0.0),
+                Vec3::new(-f32::MAX, -f32::MAX, 0.0),
+            ],
+            ..TriangleSurface::default()
+        };
+        let mesh = poly_mesh_from_triangles(&[surface]);
+
+        assert_eq!(mesh.face_count(), 1);
+        assert!(mesh.positions().all(|position| position.is_finite()));
+    }
+}
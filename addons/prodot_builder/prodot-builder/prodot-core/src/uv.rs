@@ -0,0 +1,231 @@
+use crate::math::{Vec2, Vec3};
+use crate::poly_mesh::{FaceId, PolyMesh};
+
+/// How face corner UVs are generated from the geometry.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum UvProjection {
+    /// Each face is projected along the axis its normal is closest to,
+    /// in the mesh's local space.
+    Box,
+    /// Each face is projected onto its own plane, so slopes keep square
+    /// texels too.
+    Planar,
+    /// Like `Box`, but in world space, so textures line up across
+    /// separate meshes.
+    World,
+}
+
+/// Projection and texture placement for `project_uvs`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct UvSettings {
+    pub projection: UvProjection,
+    /// Texture pixels per world unit.
+    pub texel_density: f32,
+    /// Size of the texture in pixels. With the default density of 64, a
+    /// 64 pixel texture repeats once per unit.
+    pub texture_size: f32,
+    pub offset: Vec2,
+    /// Rotation in radians, counter-clockwise.
+    pub rotation: f32,
+    pub scale: Vec2,
+}
+
+impl Default for UvSettings {
+    fn default() -> Self {
+        UvSettings {
+            projection: UvProjection::Box,
+            texel_density: 64.0,
+            texture_size: 64.0,
+            offset: Vec2::zero(),
+            rotation: 0.0,
+            scale: Vec2::new(1.0, 1.0),
+        }
+    }
+}
+
+impl UvSettings {
+    /// Turns projected coordinates (in world units, +V pointing down the
+    /// texture like Godot's UVs) into the final UV.
+    fn place(&self, projected: Vec2) -> Vec2 {
+        let repeats = if self.texture_size > 0.0 {
+            self.texel_density / self.texture_size
+        } else {
+            0.0
+        };
+        let uv = (projected * repeats).rotated(self.rotation);
+        Vec2::new(uv.x * self.scale.x, uv.y * self.scale.y) + self.offset
+    }
+}
+
+/// Recomputes the UVs of every face corner. `to_world` maps local
+/// positions into world space and is only used by `UvProjection::World`.
+pub fn project_uvs<F>(mesh: &mut PolyMesh, settings: &UvSettings, to_world: F)
+where
+    F: Fn(Vec3) -> Vec3,
+{
+    let faces: Vec<FaceId> = (0..mesh.face_count()).collect();
+    project_face_uvs(mesh, settings, &faces, to_world);
+}
+
+/// Recomputes the corner UVs of `faces` only. A face's UVs depend on its
+/// own corners alone, so after moving vertices only the faces around them
+/// need to be projected again.
+pub fn project_face_uvs<F>(mesh: &mut PolyMesh, settings: &UvSettings, faces: &[FaceId], to_world: F)
+where
+    F: Fn(Vec3) -> Vec3,
+{
+    for &face in faces {
+        let positions: Vec<Vec3> = match settings.projection {
+            UvProjection::World => mesh.face_vertices(face).map(|vertex| to_world(mesh.position(vertex))).collect(),
+            _ => mesh.face_vertices(face).map(|vertex| mesh.position(vertex)).collect(),
+        };
+        let normal = newell_normal(&positions);
+
+        let uvs: Vec<Vec2> = positions
+            .iter()
+            .map(|&position| {
+                let projected = match settings.projection {
+                    UvProjection::Box | UvProjection::World => box_project(position, normal),
+                    UvProjection::Planar => planar_project(position, normal),
+                };
+                settings.place(projected)
+            })
+            .collect();
+
+        set_face_uvs(mesh, face, &uvs);
+    }
+}
+
+fn set_face_uvs(mesh: &mut PolyMesh, face: FaceId, uvs: &[Vec2]) {
+    for (l, &uv) in mesh.face_loops(face).zip(uvs.iter()) {
+        mesh.loop_attributes_mut(l).uv = uv;
+    }
+}
+
+/// Same as `PolyMesh::face_normal`, for positions that were moved into
+/// another space first.
+fn newell_normal(positions: &[Vec3]) -> Vec3 {
+    let mut normal = Vec3::zero();
+    for (i, &current) in positions.iter().enumerate() {
+        let next = positions[(i + 1) % positions.len()];
+        normal.x += (current.y - next.y) * (current.z + next.z);
+        normal.y += (current.z - next.z) * (current.x + next.x);
+        normal.z += (current.x - next.x) * (current.y + next.y);
+    }
+    normal.normalized()
+}
+
+/// Projects onto the axis plane the normal is closest to. U runs to the
+/// right and V down when looking at the front of the face, so textures
+/// are neither mirrored nor upside down.
+fn box_project(position: Vec3, normal: Vec3) -> Vec2 {
+    let abs = normal.abs();
+    if abs.x >= abs.y && abs.x >= abs.z {
+        Vec2::new(-position.z * normal.x.signum(), -position.y)
+    } else if abs.y >= abs.z {
+        Vec2::new(position.x, position.z * normal.y.signum())
+    } else {
+        Vec2::new(position.x * normal.z.signum(), -position.y)
+    }
+}
+
+/// Projects onto the face's own plane, with U kept horizontal. Faces that
+/// are (nearly) horizontal fall back to the box projection.
+fn planar_project(position: Vec3, normal: Vec3) -> Vec2 {
+    let right = Vec3::new(0.0, 1.0, 0.0).cross(normal);
+    if right.length() < 0.01 {
+        return box_project(position, normal);
+    }
+    let right = right.normalized();
+    let down = right.cross(normal);
+    Vec2::new(position.dot(right), position.dot(down))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poly_mesh::tests::cube;
+
+    fn face_uvs(mesh: &PolyMesh, face: FaceId) -> Vec<Vec2> {
+        mesh.face_loops(face).map(|l| mesh.loop_attributes(l).uv).collect()
+    }
+
+    #[test]
+    fn box_projection_maps_each_side_to_a_full_tile() {
+        let mut mesh = cube();
+        project_uvs(&mut mesh, &UvSettings::default(), |position| position);
+
+        // Front face corners, counter-clockwise from the bottom left
+        assert_eq!(
+            face_uvs(&mesh, 0),
+            vec![
+                Vec2::new(0.0, 0.0),
+                Vec2::new(1.0, 0.0),
+                Vec2::new(1.0, -1.0),
+                Vec2::new(0.0, -1.0),
+            ]
+        );
+        // Right face runs from z = 1 to z = 0, U still grows to the right
+        let right = face_uvs(&mesh, 1);
+        assert_eq!(right[0], Vec2::new(-1.0, 0.0));
+        assert_eq!(right[1], Vec2::new(0.0, 0.0));
+    }
+
+    #[test]
+    fn texel_density_scales_the_tiles() {
+        let mut mesh = cube();
+        let settings = UvSettings {
+            texel_density: 128.0,
+            ..UvSettings::default()
+        };
+        project_uvs(&mut mesh, &settings, |position| position);
+        assert_eq!(face_uvs(&mesh, 0)[2], Vec2::new(2.0, -2.0));
+    }
+
+    #[test]
+    fn offset_rotation_and_scale_are_applied_in_order() {
+        let settings = UvSettings {
+            offset: Vec2::new(0.5, 0.0),
+            rotation: std::f32::consts::FRAC_PI_2,
+            scale: Vec2::new(2.0, 1.0),
+            ..UvSettings::default()
+        };
+        let uv = settings.place(Vec2::new(1.0, 0.0));
+        assert!((uv.x - 0.5).abs() < 1e-6);
+        assert!((uv.y - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn planar_projection_keeps_slopes_square() {
+        let mut mesh = PolyMesh::new();
+        let a = mesh.add_vertex(Vec3::new(0.0, 0.0, 0.0));
+        let b = mesh.add_vertex(Vec3::new(1.0, 0.0, 0.0));
+        let c = mesh.add_vertex(Vec3::new(1.0, 1.0, -1.0));
+        let d = mesh.add_vertex(Vec3::new(0.0, 1.0, -1.0));
+        mesh.add_face(&[a, b, c, d]).unwrap();
+
+        let settings = UvSettings {
+            projection: UvProjection::Planar,
+            ..UvSettings::default()
+        };
+        project_uvs(&mut mesh, &settings, |position| position);
+
+        let uvs = face_uvs(&mesh, 0);
+        assert!(((uvs[3] - uvs[0]).length() - 2f32.sqrt()).abs() < 1e-5);
+        assert!(((uvs[1] - uvs[0]).length() - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn world_projection_follows_the_transform() {
+        let mut local = cube();
+        let mut world = cube();
+        let settings = UvSettings {
+            projection: UvProjection::World,
+            ..UvSettings::default()
+        };
+        project_uvs(&mut local, &settings, |position| position);
+        project_uvs(&mut world, &settings, |position| position + Vec3::new(0.5, 0.0, 0.0));
+
+        assert_eq!(face_uvs(&world, 0)[0], face_uvs(&local, 0)[0] + Vec2::new(0.5, 0.0));
+    }
+}
@@ -0,0 +1,206 @@
+use std::ops::Range;
+
+use crate::render::SurfaceArrays;
+
+/// Bytes per render vertex in a surface committed uncompressed with
+/// vertex, normal, tangent, color and UV arrays. Godot interleaves them
+/// in that order: 3, 3, 4, 4 and 2 floats.
+pub const VERTEX_STRIDE: usize = 64;
+
+/// Ranges of render vertices that changed since they were last uploaded,
+/// kept sorted and merged.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DirtyRanges {
+    ranges: Vec<Range<usize>>,
+}
+
+impl DirtyRanges {
+    /// Ranges closer than this many vertices are merged, since one larger
+    /// upload is cheaper than several small ones.
+    pub const MERGE_GAP: usize = 8;
+
+    pub fn new() -> Self {
+        DirtyRanges::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    pub fn mark(&mut self, vertex: usize) {
+        self.mark_range(vertex..vertex + 1);
+    }
+
+    pub fn mark_range(&mut self, range: Range<usize>) {
+        if range.start >= range.end {
+            return;
+        }
+
+        // First range that ends close enough to the new one to merge
+        let first = self
+            .ranges
+            .iter()
+            .position(|existing| existing.end + Self::MERGE_GAP >= range.start)
+            .unwrap_or(self.ranges.len());
+        let mut merged = range;
+        let mut last = first;
+        while last < self.ranges.len() && self.ranges[last].start <= merged.end + Self::MERGE_GAP {
+            merged.start = merged.start.min(self.ranges[last].start);
+            merged.end = merged.end.max(self.ranges[last].end);
+            last += 1;
+        }
+
+        self.ranges.splice(first..last, std::iter::once(merged));
+    }
+
+    /// Returns the dirty ranges and clears them.
+    pub fn take(&mut self) -> Vec<Range<usize>> {
+        std::mem::take(&mut self.ranges)
+    }
+}
+
+/// CPU copy of a surface's vertex buffer, laid out the way Godot stores
+/// it, so edited vertices can be uploaded with `surface_update_region`
+/// instead of rebuilding the whole surface.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct VertexBuffer {
+    bytes: Vec<u8>,
+    dirty: DirtyRanges,
+}
+
+impl VertexBuffer {
+    pub fn new(surface: &SurfaceArrays) -> Self {
+        let mut buffer = VertexBuffer {
+            bytes: vec![0; surface.vertex_count() * VERTEX_STRIDE],
+            dirty: DirtyRanges::new(),
+        };
+        for vertex in 0..surface.vertex_count() {
+            buffer.write_vertex(surface, vertex);
+        }
+        buffer
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.bytes.len() / VERTEX_STRIDE
+    }
+
+    /// Rewrites `vertex` from the surface arrays and marks it dirty.
+    pub fn update_vertex(&mut self, surface: &SurfaceArrays, vertex: usize) {
+        self.write_vertex(surface, vertex);
+        self.dirty.mark(vertex);
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        !self.dirty.is_empty()
+    }
+
+    /// Returns the byte offset and contents of every dirty range, and
+    /// clears them.
+    pub fn take_dirty(&mut self) -> Vec<(usize, &[u8])> {
+        let ranges = self.dirty.take();
+        let bytes = &self.bytes;
+        ranges
+            .into_iter()
+            .map(|range| {
+                let region = range.start * VERTEX_STRIDE..range.end * VERTEX_STRIDE;
+                (region.start, &bytes[region])
+            })
+            .collect()
+    }
+
+    fn write_vertex(&mut self, surface: &SurfaceArrays, vertex: usize) {
+        let position = surface.positions[vertex];
+        let normal = surface.normals[vertex];
+        let tangent = surface.tangents[vertex];
+        let color = surface.colors[vertex];
+        let uv = surface.uvs[vertex];
+
+        let floats = [
+            position.x,
+            position.y,
+            position.z,
+            normal.x,
+            normal.y,
+            normal.z,
+            tangent.direction.x,
+            tangent.direction.y,
+            tangent.direction.z,
+            tangent.sign,
+            color.r,
+            color.g,
+            color.b,
+            color.a,
+            uv.x,
+            uv.y,
+        ];
+
+        let start = vertex * VERTEX_STRIDE;
+        for (i, value) in floats.iter().enumerate() {
+            let offset = start + i * 4;
+            self.bytes[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::Vec3;
+    use crate::poly_mesh::tests::cube;
+    use crate::render::build_surfaces;
+
+    #[test]
+    fn dirty_ranges_merge_when_close() {
+        let mut dirty = DirtyRanges::new();
+        dirty.mark(40);
+        dirty.mark(0);
+        dirty.mark(5);
+        dirty.mark_range(20..22);
+        assert_eq!(dirty.clone().take(), vec![0..6, 20..22, 40..41]);
+
+        // Bridges the gap between the first two ranges
+        dirty.mark(13);
+        assert_eq!(dirty.take(), vec![0..22, 40..41]);
+        assert!(dirty.is_empty());
+    }
+
+    #[test]
+    fn vertices_are_interleaved_with_a_fixed_stride() {
+        let surfaces = build_surfaces(&cube());
+        let surface = &surfaces.surfaces[0];
+        let buffer = VertexBuffer::new(surface);
+
+        assert_eq!(buffer.vertex_count(), surface.vertex_count());
+        let float = |vertex: usize, index: usize| {
+            let offset = vertex * VERTEX_STRIDE + index * 4;
+            f32::from_le_bytes([
+                buffer.bytes[offset],
+                buffer.bytes[offset + 1],
+                buffer.bytes[offset + 2],
+                buffer.bytes[offset + 3],
+            ])
+        };
+        // Position of the third render vertex, then its normal
+        assert_eq!(Vec3::new(float(2, 0), float(2, 1), float(2, 2)), surface.positions[2]);
+        assert_eq!(Vec3::new(float(2, 3), float(2, 4), float(2, 5)), surface.normals[2]);
+        // Color alpha
+        assert_eq!(float(2, 13), 1.0);
+    }
+
+    #[test]
+    fn only_updated_vertices_are_uploaded() {
+        let mut surfaces = build_surfaces(&cube());
+        let mut buffer = VertexBuffer::new(&surfaces.surfaces[0]);
+        assert!(!buffer.is_dirty());
+
+        surfaces.surfaces[0].positions[3] = Vec3::new(9.0, 9.0, 9.0);
+        buffer.update_vertex(&surfaces.surfaces[0], 3);
+
+        let dirty = buffer.take_dirty();
+        assert_eq!(dirty.len(), 1);
+        assert_eq!(dirty[0].0, 3 * VERTEX_STRIDE);
+        assert_eq!(dirty[0].1.len(), VERTEX_STRIDE);
+        assert_eq!(dirty[0].1[..4], 9f32.to_le_bytes());
+        assert!(!buffer.is_dirty());
+    }
+}
@@ -0,0 +1,36 @@
+use gdnative::api::Button;
+use gdnative::prelude::*;
+
+#[derive(NativeClass)]
+#[inherit(Button)]
+#[register_with(Self::register_signals)]
+pub struct BakeButton;
+
+#[methods]
+impl BakeButton {
+    fn new(_owner: TRef<Button>) -> Self {
+        BakeButton
+    }
+
+    fn register_signals(builder: &ClassBuilder<Self>) {
+        builder.add_signal(Signal {
+            name: "bake",
+            args: &[],
+        });
+    }
+
+    #[export]
+    fn _enter_tree(&self, owner: TRef<Button>) {
+        owner
+            .connect("pressed", owner, "on_click", VariantArray::new_shared(), 0)
+            .expect("[BakeButton]: Error when attempting to connect pressed signal!");
+    }
+
+    #[export]
+    fn on_click(&self, owner: TRef<Button>) {
+        owner.emit_signal("bake", &[]);
+    }
+
+    #[export]
+    fn _exit_tree(&self, _owner: TRef<Button>) {}
+}
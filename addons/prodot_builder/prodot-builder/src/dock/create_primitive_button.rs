@@ -0,0 +1,61 @@
+use gdnative::api::Button;
+use gdnative::prelude::*;
+use crate::prodot_primitives::*;
+
+/// Button under a primitive's parameter controls. Emits `signal_name`
+/// with the parameters when pressed, which the plugin connects to its
+/// method of the same name.
+#[derive(NativeClass)]
+#[inherit(Button)]
+pub struct CreatePrimitiveButton {
+    #[property(after_set = "Self::add_signal")]
+    signal_name: GodotString,
+}
+
+#[methods]
+impl CreatePrimitiveButton {
+    fn new(_owner: TRef<Button>) -> Self {
+        CreatePrimitiveButton {
+            signal_name: GodotString::new(),
+        }
+    }
+
+    pub fn signal_name(&self) -> GodotString {
+        self.signal_name.clone()
+    }
+
+    /// Signals are per class, so each button adds its own once the dock
+    /// scene sets the name.
+    fn add_signal(&mut self, owner: TRef<Button>) {
+        if self.signal_name.is_empty() || owner.has_user_signal(self.signal_name.clone()) {
+            return;
+        }
+
+        let params = Dictionary::new();
+        params.insert("name", "params");
+        params.insert("type", VariantType::Dictionary as i64);
+        let args = VariantArray::new();
+        args.push(params.into_shared());
+        owner.add_user_signal(self.signal_name.clone(), args.into_shared());
+    }
+
+    #[export]
+    fn _enter_tree(&self, owner: TRef<Button>) {
+        owner
+            .connect("pressed", owner, "on_click", VariantArray::new_shared(), 0)
+            .expect("[CreatePrimitiveButton]: Error when attempting to connect pressed signal!");
+    }
+
+    #[export]
+    fn on_click(&self, owner: TRef<Button>) {
+        // The parameter controls are next to the button in its tab
+        let params = owner
+            .get_node("../Params")
+            .map(|params| read_dock_params(unsafe { params.assume_safe() }))
+            .expect("[CreatePrimitiveButton]: Failed to find the primitive parameters!");
+        owner.emit_signal(self.signal_name.clone(), &[ params.owned_to_variant() ] );
+    }
+
+    #[export]
+    fn _exit_tree(&self, _owner: TRef<Button>) {}
+}
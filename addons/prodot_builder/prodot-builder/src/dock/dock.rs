@@ -1,7 +1,17 @@
 //pub mod dock;
 
-pub mod create_cube_button;
+pub mod create_primitive_button;
+pub mod make_unique_button;
+pub mod make_editable_button;
 pub mod object_mode_button;
 pub mod vertex_mode_button;
 pub mod face_mode_button;
 pub mod edge_mode_button;
+pub mod export_obj_button;
+pub mod export_gltf_button;
+pub mod export_prodot_button;
+pub mod export_stl_button;
+pub mod export_ply_button;
+pub mod import_obj_button;
+pub mod import_ply_button;
+pub mod bake_button;
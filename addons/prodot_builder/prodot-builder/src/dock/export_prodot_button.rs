@@ -0,0 +1,42 @@
+use gdnative::api::Button;
+use gdnative::prelude::*;
+use crate::prodot_export::*;
+
+#[derive(NativeClass)]
+#[inherit(Button)]
+#[register_with(Self::register_signals)]
+pub struct ExportProdotButton;
+
+#[methods]
+impl ExportProdotButton {
+    fn new(_owner: TRef<Button>) -> Self {
+        ExportProdotButton
+    }
+
+    fn register_signals(builder: &ClassBuilder<Self>) {
+        builder.add_signal(Signal {
+            name: "export_prodot",
+            args: &[SignalArgument {
+                name: "format",
+                default: Variant::from_i64(ExportFormat::Prodot.value()),
+                export_info: ExportInfo::new(VariantType::I64),
+                usage: PropertyUsage::DEFAULT,
+            }],
+        });
+    }
+
+    #[export]
+    fn _enter_tree(&self, owner: TRef<Button>) {
+        owner
+            .connect("pressed", owner, "on_click", VariantArray::new_shared(), 0)
+            .expect("[ExportProdotButton]: Error when attempting to connect pressed signal!");
+    }
+
+    #[export]
+    fn on_click(&self, owner: TRef<Button>) {
+        owner.emit_signal("export_prodot", &[ Variant::from_i64(ExportFormat::Prodot.value()) ] );
+    }
+
+    #[export]
+    fn _exit_tree(&self, _owner: TRef<Button>) {}
+}
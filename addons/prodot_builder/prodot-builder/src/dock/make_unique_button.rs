@@ -4,17 +4,17 @@ use gdnative::prelude::*;
 #[derive(NativeClass)]
 #[inherit(Button)]
 #[register_with(Self::register_signals)]
-pub struct CreateCubeButton;
+pub struct MakeUniqueButton;
 
 #[methods]
-impl CreateCubeButton {
+impl MakeUniqueButton {
     fn new(_owner: TRef<Button>) -> Self {
-        CreateCubeButton
+        MakeUniqueButton
     }
 
     fn register_signals(builder: &ClassBuilder<Self>) {
         builder.add_signal(Signal {
-            name: "create_cube",
+            name: "make_unique",
             args: &[],
         });
     }
@@ -23,12 +23,12 @@ impl CreateCubeButton {
     fn _enter_tree(&self, owner: TRef<Button>) {
         owner
             .connect("pressed", owner, "on_click", VariantArray::new_shared(), 0)
-            .expect("[CreateCubeButton]: Error when attempting to connect pressed signal!");
+            .expect("[MakeUniqueButton]: Error when attempting to connect pressed signal!");
     }
 
     #[export]
     fn on_click(&self, owner: TRef<Button>) {
-        owner.emit_signal("create_cube", &[]);
+        owner.emit_signal("make_unique", &[]);
     }
 
     #[export]
@@ -33,7 +33,11 @@ impl ObjectModeButton {
     }
 
     #[export]
-    fn on_toggled(&self, owner: TRef<Button>, _button_pressed: bool) {
-        owner.emit_signal("object_mode", &[ Variant::from_i64(BuildMode::Object.value()) ] );
+    fn on_toggled(&self, owner: TRef<Button>, button_pressed: bool) {
+        // The button group untoggles the previous mode's button, which
+        // isn't a mode change of its own
+        if button_pressed {
+            owner.emit_signal("object_mode", &[ Variant::from_i64(BuildMode::Object.value()) ] );
+        }
     }
 }
@@ -2,20 +2,40 @@ use gdnative::prelude::*;
 
 #[path = "dock/dock.rs"]
 mod dock;
+mod prodot_bake;
 mod prodot_builder;
+mod prodot_export;
+mod prodot_export_plugin;
+mod prodot_import;
+mod prodot_import_plugin;
 mod prodot_mesh;
+mod prodot_mesh_data;
+mod prodot_primitives;
 mod prodot_utils;
 mod prodot_gizmo;
 
 fn init(handle: InitHandle) {
     handle.add_tool_class::<prodot_builder::ProdotBuilderPlugin>();
     handle.add_tool_class::<prodot_mesh::ProdotMesh>();
+    handle.add_tool_class::<prodot_mesh_data::ProdotMeshData>();
     handle.add_tool_class::<prodot_gizmo::ProdotGizmo>();
-    handle.add_tool_class::<dock::create_cube_button::CreateCubeButton>();
+    handle.add_tool_class::<prodot_export_plugin::ProdotExportPlugin>();
+    handle.add_tool_class::<prodot_import_plugin::ProdotImportPlugin>();
+    handle.add_tool_class::<dock::create_primitive_button::CreatePrimitiveButton>();
+    handle.add_tool_class::<dock::make_unique_button::MakeUniqueButton>();
+    handle.add_tool_class::<dock::make_editable_button::MakeEditableButton>();
     handle.add_tool_class::<dock::object_mode_button::ObjectModeButton>();
     handle.add_tool_class::<dock::vertex_mode_button::VertexModeButton>();
     handle.add_tool_class::<dock::face_mode_button::FaceModeButton>();
     handle.add_tool_class::<dock::edge_mode_button::EdgeModeButton>();
+    handle.add_tool_class::<dock::export_obj_button::ExportObjButton>();
+    handle.add_tool_class::<dock::export_gltf_button::ExportGltfButton>();
+    handle.add_tool_class::<dock::export_prodot_button::ExportProdotButton>();
+    handle.add_tool_class::<dock::export_stl_button::ExportStlButton>();
+    handle.add_tool_class::<dock::export_ply_button::ExportPlyButton>();
+    handle.add_tool_class::<dock::import_obj_button::ImportObjButton>();
+    handle.add_tool_class::<dock::import_ply_button::ImportPlyButton>();
+    handle.add_tool_class::<dock::bake_button::BakeButton>();
 }
 
 godot_gdnative_init!();
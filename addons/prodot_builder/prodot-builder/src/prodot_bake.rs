@@ -0,0 +1,449 @@
+use gdnative::api::{
+    CollisionShape, ConcavePolygonShape, GlobalConstants, Mesh, MeshInstance, ProjectSettings, Resource, ResourceSaver,
+    Shape, StaticBody, UndoRedo,
+};
+use gdnative::prelude::*;
+
+use prodot_core::collision;
+use prodot_core::lod;
+use prodot_core::render;
+use crate::prodot_export::with_extension;
+use crate::prodot_mesh::*;
+use crate::prodot_utils::*;
+
+pub const COLLISION_SETTING: &str = "prodot_builder/bake/collision";
+pub const LOD_COUNT_SETTING: &str = "prodot_builder/bake/lod_count";
+pub const LOD_DISTANCE_SETTING: &str = "prodot_builder/bake/lod_distance";
+
+/// Name of the StaticBody baked meshes get their collision on.
+const BAKED_COLLISION_NAME: &str = "Collision";
+
+/// Properties a baked node takes over from its ProdotMesh. Baked levels of
+/// detail get them too, except the transform.
+const COPIED_PROPERTIES: [&str; 5] = ["transform", "visible", "material_override", "cast_shadow", "layers"];
+
+/// How ProdotMesh nodes are baked, set in the project settings under
+/// Prodot Builder > Bake.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BakeOptions {
+    /// Give meshes without a generated collision a trimesh one.
+    pub collision: bool,
+    /// Most simplified levels of detail to add under each mesh.
+    pub lod_count: usize,
+    /// Distance each level of detail covers.
+    pub lod_distance: f32,
+}
+
+impl Default for BakeOptions {
+    fn default() -> Self {
+        BakeOptions {
+            collision: false,
+            lod_count: 0,
+            lod_distance: 20.0,
+        }
+    }
+}
+
+impl BakeOptions {
+    pub fn from_project_settings() -> BakeOptions {
+        let settings = ProjectSettings::godot_singleton();
+        let defaults = BakeOptions::default();
+        let setting = |name: &str| {
+            if settings.has_setting(name) {
+                settings.get_setting(name)
+            } else {
+                Variant::new()
+            }
+        };
+
+        BakeOptions {
+            collision: setting(COLLISION_SETTING).try_to_bool().unwrap_or(defaults.collision),
+            lod_count: setting(LOD_COUNT_SETTING)
+                .try_to_i64()
+                .map_or(defaults.lod_count, |count| count.max(0) as usize),
+            lod_distance: setting(LOD_DISTANCE_SETTING)
+                .try_to_f64()
+                .map_or(defaults.lod_distance, |distance| distance as f32),
+        }
+    }
+}
+
+/// Adds the bake settings to the project settings with their defaults.
+pub fn register_project_settings() {
+    let settings = ProjectSettings::godot_singleton();
+    let defaults = BakeOptions::default();
+    let entries = [
+        (COLLISION_SETTING, defaults.collision.to_variant(), GlobalConstants::TYPE_BOOL),
+        (LOD_COUNT_SETTING, (defaults.lod_count as i64).to_variant(), GlobalConstants::TYPE_INT),
+        (LOD_DISTANCE_SETTING, (defaults.lod_distance as f64).to_variant(), GlobalConstants::TYPE_REAL),
+    ];
+
+    for (name, default, variant_type) in entries.iter() {
+        if !settings.has_setting(*name) {
+            settings.set_setting(*name, default.clone());
+        }
+        settings.set_initial_value(*name, default.clone());
+
+        let info = Dictionary::new();
+        info.insert("name", *name);
+        info.insert("type", *variant_type);
+        settings.add_property_info(info.into_shared());
+    }
+}
+
+/// A plain MeshInstance standing in for a ProdotMesh, not in the tree yet.
+pub struct BakedMesh {
+    pub node: Ref<MeshInstance, Shared>,
+    /// Its levels of detail and collision, and their descendants.
+    pub generated: Vec<Ref<Node, Shared>>,
+    /// The mesh of the node followed by those of its levels of detail.
+    pub meshes: Vec<Ref<Mesh, Shared>>,
+}
+
+/// Builds the plain MeshInstance that replaces `node`, with its mesh,
+/// materials and rendering properties. The collision the ProdotMesh
+/// generated is copied, and meshes without one get a trimesh collision
+/// when `options` asks for it. `None` if `node` isn't a ProdotMesh.
+pub fn bake_mesh(node: TRef<MeshInstance>, options: &BakeOptions) -> Option<BakedMesh> {
+    let (poly_mesh, materials) = node
+        .cast_instance::<ProdotMesh>()?
+        .map(|mesh, owner: TRef<MeshInstance>| (mesh.poly_mesh().clone(), mesh.slot_materials(owner)))
+        .ok()?;
+
+    let baked = MeshInstance::new();
+    baked.set_name(node.name());
+    for property in COPIED_PROPERTIES.iter() {
+        baked.set(*property, node.get(*property));
+    }
+    let mesh = surfaces_mesh(&render::build_surfaces(&poly_mesh).surfaces, &materials)
+        .upcast::<Mesh>()
+        .into_shared();
+    baked.set_mesh(mesh.clone());
+    let baked = baked.into_shared();
+    let baked_node = unsafe { baked.assume_safe() };
+
+    let mut generated = Vec::new();
+    let mut meshes = vec![mesh];
+
+    // Each level takes over where the previous one stops
+    let levels = lod::lod_meshes(&poly_mesh, options.lod_count);
+    if !levels.is_empty() {
+        baked_node.set_lod_max_distance(options.lod_distance as f64);
+    }
+    for (index, level) in levels.iter().enumerate() {
+        let lod_node = MeshInstance::new();
+        lod_node.set_name(format!("LOD{}", index + 1));
+        for property in COPIED_PROPERTIES.iter().skip(1) {
+            lod_node.set(*property, node.get(*property));
+        }
+        let lod_mesh = surfaces_mesh(&render::build_surfaces(level).surfaces, &materials)
+            .upcast::<Mesh>()
+            .into_shared();
+        lod_node.set_mesh(lod_mesh.clone());
+        lod_node.set_lod_min_distance(options.lod_distance as f64 * (index + 1) as f64);
+        if index + 1 < levels.len() {
+            lod_node.set_lod_max_distance(options.lod_distance as f64 * (index + 2) as f64);
+        }
+
+        let lod_node = lod_node.into_shared();
+        baked_node.add_child(lod_node, false);
+        generated.push(lod_node.upcast::<Node>());
+        meshes.push(lod_mesh);
+    }
+
+    if let Some(body) = baked_collision(node, &poly_mesh, options) {
+        baked_node.add_child(body, false);
+        generated.extend(descendants(unsafe { body.assume_safe() }));
+        generated.push(body);
+    }
+
+    Some(BakedMesh {
+        node: baked,
+        generated,
+        meshes,
+    })
+}
+
+/// A copy of the collision body of `node` with its own shapes, or a new
+/// trimesh one when there is none and `options` asks for it.
+fn baked_collision(
+    node: TRef<MeshInstance>,
+    poly_mesh: &prodot_core::poly_mesh::PolyMesh,
+    options: &BakeOptions,
+) -> Option<Ref<Node, Shared>> {
+    if node.has_node(COLLISION_BODY_NAME) {
+        let body = unsafe { node.get_node(COLLISION_BODY_NAME)?.assume_safe() };
+        let copy = unsafe { body.duplicate(Node::DUPLICATE_SIGNALS | Node::DUPLICATE_GROUPS)?.assume_safe() };
+        copy.set_name(BAKED_COLLISION_NAME);
+        // Shapes are shared with the copy otherwise, and the ProdotMesh
+        // would still reshape them if the bake is undone
+        for child in copy.get_children().iter() {
+            let shape_node = match child.try_to_object::<CollisionShape>() {
+                Some(shape_node) => unsafe { shape_node.assume_safe() },
+                None => continue,
+            };
+            let shape = shape_node
+                .shape()
+                .and_then(|shape| unsafe { shape.assume_safe() }.duplicate(false))
+                .and_then(|shape| unsafe { shape.assume_safe() }.cast::<Shape>().map(|shape| shape.claim()));
+            if let Some(shape) = shape {
+                shape_node.set_shape(shape);
+            }
+        }
+        return Some(copy.claim());
+    }
+    if !options.collision {
+        return None;
+    }
+
+    let shape = ConcavePolygonShape::new();
+    let faces: TypedArray<Vector3> = collision::trimesh_faces(poly_mesh).into_iter().map(to_vector3).collect();
+    shape.set_faces(faces);
+    let shape_node = CollisionShape::new();
+    shape_node.set_shape(shape);
+    let shape_node = shape_node.into_shared();
+
+    let body = StaticBody::new();
+    body.set_name(BAKED_COLLISION_NAME);
+    body.add_child(shape_node, false);
+    Some(body.upcast::<Node>().into_shared())
+}
+
+/// One ProdotMesh to replace by its baked MeshInstance.
+pub struct BakeStep {
+    pub node: Ref<MeshInstance, Shared>,
+    pub baked: BakedMesh,
+    pub parent: Ref<Node, Shared>,
+    pub index: i64,
+    /// Children the node keeps once baked. Those baked themselves are
+    /// their replacements.
+    pub children: Vec<Ref<Node, Shared>>,
+}
+
+/// Bakes every node of `nodes` that is a ProdotMesh with a parent. Nested
+/// meshes come before the meshes around them, so each step only moves
+/// nodes the steps before it left in place.
+pub fn plan_bake(nodes: &[Ref<MeshInstance, Shared>], options: &BakeOptions) -> Vec<BakeStep> {
+    let mut ordered: Vec<TRef<MeshInstance>> = nodes.iter().map(|node| unsafe { node.assume_safe() }).collect();
+    ordered.sort_by_key(|node| std::cmp::Reverse(depth(node.upcast::<Node>())));
+
+    let mut steps: Vec<BakeStep> = Vec::new();
+    for node in ordered {
+        let parent = match node.get_parent() {
+            Some(parent) => parent,
+            None => continue,
+        };
+        let baked = match bake_mesh(node, options) {
+            Some(baked) => baked,
+            None => continue,
+        };
+
+        let children = user_children(node)
+            .into_iter()
+            .map(|child| {
+                let id = unsafe { child.assume_safe() }.get_instance_id();
+                steps
+                    .iter()
+                    .find(|step| unsafe { step.node.assume_safe() }.get_instance_id() == id)
+                    .map_or(child, |step| step.baked.node.upcast::<Node>())
+            })
+            .collect();
+
+        steps.push(BakeStep {
+            node: node.claim(),
+            baked,
+            parent,
+            index: node.get_index(),
+            children,
+        });
+    }
+    steps
+}
+
+/// Replaces the nodes of `steps` by their baked MeshInstance right away,
+/// for scenes that aren't being edited. The ProdotMesh nodes are freed.
+pub fn apply_bake(steps: &[BakeStep]) {
+    let owners: Vec<(Ref<Node, Shared>, Ref<Node, Shared>)> = steps.iter().flat_map(|step| baked_owners(step, steps)).collect();
+
+    for step in steps.iter() {
+        let node = unsafe { step.node.assume_safe() };
+        let baked = unsafe { step.baked.node.assume_safe() };
+        let parent = unsafe { step.parent.assume_safe() };
+        for child in step.children.iter() {
+            node.remove_child(*child);
+            baked.add_child(*child, false);
+        }
+        parent.remove_child(node);
+        parent.add_child(baked, false);
+        parent.move_child(baked, step.index);
+    }
+
+    for (node, owner) in owners.iter() {
+        unsafe { node.assume_safe() }.set_owner(*owner);
+    }
+    for step in steps.iter() {
+        unsafe { step.node.free() };
+    }
+}
+
+/// Bakes `nodes` in the scene under `root` right away, see `apply_bake`.
+/// A ProdotMesh root has no parent to take its baked node, so it's baked
+/// under a stand-in one and its baked node becomes the root instead.
+/// Returns the scene's root after baking and how many meshes were baked.
+pub fn bake_scene(
+    root: TRef<Node>, nodes: &[Ref<MeshInstance, Shared>], options: &BakeOptions,
+) -> (Ref<Node, Shared>, usize) {
+    let holder = Node::new();
+    holder.add_child(root, false);
+    let steps = plan_bake(nodes, options);
+    apply_bake(&steps);
+
+    let new_root = holder.get_child(0).expect("[Prodot Builder]: The baked scene lost its root");
+    holder.remove_child(new_root);
+    holder.free();
+    let new_root_ref = unsafe { new_root.assume_safe() };
+    if new_root_ref.get_instance_id() != root.get_instance_id() {
+        // Freeing the old root cleared the owner of everything it owned
+        for descendant in descendants(new_root_ref) {
+            let descendant = unsafe { descendant.assume_safe() };
+            if descendant.owner().is_none() {
+                descendant.set_owner(new_root);
+            }
+        }
+    }
+    (new_root, steps.len())
+}
+
+/// Adds the replacements of `steps` to the current action of
+/// `undo_redo`. Undo puts the ProdotMesh nodes back with their children.
+pub fn add_bake_ops(undo_redo: TRef<UndoRedo>, steps: &[BakeStep]) {
+    for step in steps.iter() {
+        let node = unsafe { step.node.assume_safe() };
+        let baked = unsafe { step.baked.node.assume_safe() };
+        let parent = unsafe { step.parent.assume_safe() };
+        for child in step.children.iter() {
+            // Nested meshes' replacements are under the node by then
+            undo_redo.add_do_method(node, "remove_child", &[child.to_variant()]);
+            undo_redo.add_do_method(baked, "add_child", &[child.to_variant(), false.to_variant()]);
+        }
+        undo_redo.add_do_method(parent, "remove_child", &[node.to_variant()]);
+        undo_redo.add_do_method(parent, "add_child", &[baked.to_variant(), false.to_variant()]);
+        undo_redo.add_do_method(parent, "move_child", &[baked.to_variant(), step.index.to_variant()]);
+        undo_redo.add_do_reference(baked);
+        undo_redo.add_undo_reference(node);
+    }
+    for (node, owner) in steps.iter().flat_map(|step| baked_owners(step, steps)) {
+        undo_redo.add_do_method(node, "set_owner", &[owner.to_variant()]);
+    }
+
+    // Undo runs in the order it's added, so the outer meshes come back
+    // before the nested ones are taken out of them
+    for step in steps.iter().rev() {
+        let node = unsafe { step.node.assume_safe() };
+        let baked = unsafe { step.baked.node.assume_safe() };
+        let parent = unsafe { step.parent.assume_safe() };
+        undo_redo.add_undo_method(parent, "remove_child", &[baked.to_variant()]);
+        undo_redo.add_undo_method(parent, "add_child", &[node.to_variant(), false.to_variant()]);
+        undo_redo.add_undo_method(parent, "move_child", &[node.to_variant(), step.index.to_variant()]);
+        for child in step.children.iter() {
+            undo_redo.add_undo_method(baked, "remove_child", &[child.to_variant()]);
+            undo_redo.add_undo_method(node, "add_child", &[child.to_variant(), false.to_variant()]);
+        }
+    }
+    for step in steps.iter() {
+        let node = unsafe { step.node.assume_safe() };
+        let nodes = std::iter::once(node.upcast::<Node>().claim()).chain(descendants(node.upcast::<Node>()));
+        for descendant in nodes {
+            if let Some(owner) = unsafe { descendant.assume_safe() }.owner() {
+                undo_redo.add_undo_method(descendant, "set_owner", &[owner.to_variant()]);
+            }
+        }
+    }
+}
+
+/// Owner every node the step moves should have once baked: the
+/// ProdotMesh's for the baked node and what was generated for it, and
+/// their own for the children it keeps and their descendants.
+fn baked_owners(step: &BakeStep, steps: &[BakeStep]) -> Vec<(Ref<Node, Shared>, Ref<Node, Shared>)> {
+    let node = unsafe { step.node.assume_safe() };
+    let mut owners = Vec::new();
+    if let Some(owner) = node.owner() {
+        owners.push((step.baked.node.upcast::<Node>(), owner));
+        for generated in step.baked.generated.iter() {
+            owners.push((*generated, owner));
+        }
+    }
+
+    for child in step.children.iter() {
+        let child = unsafe { child.assume_safe() };
+        let is_baked = steps
+            .iter()
+            .any(|other| unsafe { other.baked.node.assume_safe() }.get_instance_id() == child.get_instance_id());
+        if is_baked {
+            continue;
+        }
+        let nodes = std::iter::once(child.claim()).chain(descendants(child));
+        for descendant in nodes {
+            if let Some(owner) = unsafe { descendant.assume_safe() }.owner() {
+                owners.push((descendant, owner));
+            }
+        }
+    }
+    owners
+}
+
+/// Saves the meshes of a baked node as `.mesh` resources next to the
+/// scene at `scene_path`, named after the scene and the node's path in
+/// it, so the scene references them instead of embedding them.
+pub fn save_baked_meshes(baked: &BakedMesh, scene_path: &str, node_path: &str) -> Result<(), String> {
+    let stem = with_extension(scene_path, "");
+    let stem = stem.trim_end_matches('.');
+    let node_path: String = node_path
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' { c } else { '_' })
+        .collect();
+
+    for (level, mesh) in baked.meshes.iter().enumerate() {
+        let path = if level == 0 {
+            format!("{}_{}.mesh", stem, node_path)
+        } else {
+            format!("{}_{}_lod{}.mesh", stem, node_path, level)
+        };
+        ResourceSaver::godot_singleton()
+            .save(path.as_str(), mesh.clone().upcast::<Resource>(), ResourceSaver::FLAG_CHANGE_PATH)
+            .map_err(|err| format!("Failed to save {}: {:?}", path, err))?;
+    }
+    Ok(())
+}
+
+/// Children of a ProdotMesh the user added, without the overlay and the
+/// generated collision.
+fn user_children(node: TRef<MeshInstance>) -> Vec<Ref<Node, Shared>> {
+    node.get_children()
+        .iter()
+        .filter_map(|child| child.try_to_object::<Node>())
+        .filter(|child| {
+            let name = unsafe { child.assume_safe() }.name().to_string();
+            name != IMMEDIATE_GEOMETRY_NAME && name != COLLISION_BODY_NAME
+        })
+        .collect()
+}
+
+/// Every node below `node`, parents before their children.
+fn descendants(node: TRef<Node>) -> Vec<Ref<Node, Shared>> {
+    let mut nodes = Vec::new();
+    for child in node.get_children().iter() {
+        if let Some(child) = child.try_to_object::<Node>() {
+            nodes.push(child);
+            nodes.extend(descendants(unsafe { child.assume_safe() }));
+        }
+    }
+    nodes
+}
+
+fn depth(node: TRef<Node>) -> usize {
+    match node.get_parent() {
+        Some(parent) => 1 + depth(unsafe { parent.assume_safe() }),
+        None => 0,
+    }
+}
@@ -1,28 +1,47 @@
 use gdnative::api::{
     Button,
     Camera,
+    CheckBox,
     Control,
+    EditorExportPlugin,
+    EditorFileDialog,
+    EditorImportPlugin,
     EditorPlugin,
     InputEvent,
     InputEventMouseButton,
     InputEventMouseMotion,
     InputEventWithModifiers,
-    Geometry,
     GlobalConstants,
-    Mesh,
-    ArrayMesh,
+    //Mesh,
+    //ArrayMesh,
+    Material,
     MeshInstance,
     //MeshDataTool,
+    NativeScript,
     Object,
     //Texture,
-    //Script,
+    Script,
     //Spatial,
     PackedScene,
+    UndoRedo,
     //Viewport,
     //World,
 };
 use gdnative::prelude::*;
+use prodot_core::normals::NormalMode;
+use prodot_core::picking;
+use prodot_core::poly_mesh::*;
+use prodot_core::primitives::{
+    self, BoxParams, CylinderParams, IcosphereParams, PlaneParams, StairParams, TorusParams, UvSphereParams,
+};
+use prodot_core::prodot_file;
+use crate::dock::create_primitive_button::CreatePrimitiveButton;
+use crate::prodot_bake::{self, BakeOptions};
+use crate::prodot_export::{self, ExportFormat, ExportMesh, ExportNode};
+use crate::prodot_import::{self, ImportFormat};
 use crate::prodot_mesh::*;
+use crate::prodot_mesh_data::*;
+use crate::prodot_primitives;
 use crate::prodot_utils::*;
 
 use std::collections::HashMap;
@@ -67,7 +86,12 @@ pub struct ProdotBuilderPlugin {
     dock: Option<Ref<Control, Shared>>,
     mesh_scene: Option<Ref<PackedScene, Shared>>,
     selected_node: Option<Ref<MeshInstance, Shared>>,
+    // Any selected MeshInstance, ProdotMesh or not, for Make Editable
+    selected_mesh_instance: Option<Ref<MeshInstance, Shared>>,
     selected_indices: Vec::<i32>,
+    // Vertex and face count of the selected mesh when selected_indices was
+    // last valid for it
+    selected_counts: (usize, usize),
     vertices_drag_state: HashMap::<i32, Vector3>,
     hover_index: i32,
     hovering_gizmo_axis: Vector3,
@@ -76,6 +100,18 @@ pub struct ProdotBuilderPlugin {
     vertex_mode_button: Option<Ref<Button, Shared>>,
     face_mode_button: Option<Ref<Button, Shared>>,
     edge_mode_button: Option<Ref<Button, Shared>>,
+    whole_scene_check: Option<Ref<CheckBox, Shared>>,
+    apply_transform_check: Option<Ref<CheckBox, Shared>>,
+    ascii_check: Option<Ref<CheckBox, Shared>>,
+    export_dialog: Option<Ref<EditorFileDialog, Shared>>,
+    // Format the export dialog was opened for
+    export_format: ExportFormat,
+    import_dialog: Option<Ref<EditorFileDialog, Shared>>,
+    import_format: ImportFormat,
+    // Bakes the ProdotMesh nodes of exported scenes
+    export_plugin: Option<Ref<EditorExportPlugin, Shared>>,
+    // Imports .prodot files as mesh data
+    import_plugin: Option<Ref<EditorImportPlugin, Shared>>,
 
     // flags
     is_dragging: bool,
@@ -88,7 +124,9 @@ impl ProdotBuilderPlugin {
             dock: None,
             mesh_scene: None,
             selected_node: None,
+            selected_mesh_instance: None,
             selected_indices: Vec::<i32>::new(),
+            selected_counts: (0, 0),
             vertices_drag_state: HashMap::<i32, Vector3>::new(),
             hover_index: -1,
             hovering_gizmo_axis: Vector3::zero(),
@@ -97,6 +135,15 @@ impl ProdotBuilderPlugin {
             vertex_mode_button: None,
             face_mode_button: None,
             edge_mode_button: None,
+            whole_scene_check: None,
+            apply_transform_check: None,
+            ascii_check: None,
+            export_dialog: None,
+            export_format: ExportFormat::Obj,
+            import_dialog: None,
+            import_format: ImportFormat::Obj,
+            export_plugin: None,
+            import_plugin: None,
 
             is_dragging: false,
         }
@@ -143,11 +190,22 @@ impl ProdotBuilderPlugin {
         */
         //owner.add_custom_type("ProdotMesh", "MeshInstance", script, texture);
 
-        let create_cube_button = unsafe {
+        let make_unique_button = unsafe {
+            self.dock
+                .unwrap()
+                .assume_safe()
+                .get_node("./DockVC/MakeUnique")
+                .unwrap()
+                .assume_safe()
+                .cast::<Button>()
+                .unwrap()
+        };
+
+        let make_editable_button = unsafe {
             self.dock
                 .unwrap()
                 .assume_safe()
-                .get_node("./DockVC/CreateCube")
+                .get_node("./DockVC/MakeEditable")
                 .unwrap()
                 .assume_safe()
                 .cast::<Button>()
@@ -206,15 +264,197 @@ impl ProdotBuilderPlugin {
 
         edge_mode_button.set_pressed(false);
 
+        let whole_scene_check = unsafe {
+            self.dock
+                .unwrap()
+                .assume_safe()
+                .get_node("./DockVC/ExportVC/Options/WholeScene")
+                .unwrap()
+                .assume_safe()
+                .cast::<CheckBox>()
+                .unwrap()
+        };
+
+        let apply_transform_check = unsafe {
+            self.dock
+                .unwrap()
+                .assume_safe()
+                .get_node("./DockVC/ExportVC/Options/ApplyTransform")
+                .unwrap()
+                .assume_safe()
+                .cast::<CheckBox>()
+                .unwrap()
+        };
+
+        let ascii_check = unsafe {
+            self.dock
+                .unwrap()
+                .assume_safe()
+                .get_node("./DockVC/ExportVC/Options/Ascii")
+                .unwrap()
+                .assume_safe()
+                .cast::<CheckBox>()
+                .unwrap()
+        };
+
+        let export_obj_button = unsafe {
+            self.dock
+                .unwrap()
+                .assume_safe()
+                .get_node("./DockVC/ExportVC/Formats/ExportObj")
+                .unwrap()
+                .assume_safe()
+                .cast::<Button>()
+                .unwrap()
+        };
+
+        let import_obj_button = unsafe {
+            self.dock
+                .unwrap()
+                .assume_safe()
+                .get_node("./DockVC/ImportVC/Formats/ImportObj")
+                .unwrap()
+                .assume_safe()
+                .cast::<Button>()
+                .unwrap()
+        };
+
+        let import_ply_button = unsafe {
+            self.dock
+                .unwrap()
+                .assume_safe()
+                .get_node("./DockVC/ImportVC/Formats/ImportPly")
+                .unwrap()
+                .assume_safe()
+                .cast::<Button>()
+                .unwrap()
+        };
+
+        let export_prodot_button = unsafe {
+            self.dock
+                .unwrap()
+                .assume_safe()
+                .get_node("./DockVC/ExportVC/Formats/ExportProdot")
+                .unwrap()
+                .assume_safe()
+                .cast::<Button>()
+                .unwrap()
+        };
+
+        let export_stl_button = unsafe {
+            self.dock
+                .unwrap()
+                .assume_safe()
+                .get_node("./DockVC/ExportVC/Formats/ExportStl")
+                .unwrap()
+                .assume_safe()
+                .cast::<Button>()
+                .unwrap()
+        };
+
+        let export_ply_button = unsafe {
+            self.dock
+                .unwrap()
+                .assume_safe()
+                .get_node("./DockVC/ExportVC/Formats/ExportPly")
+                .unwrap()
+                .assume_safe()
+                .cast::<Button>()
+                .unwrap()
+        };
+
+        let bake_button = unsafe {
+            self.dock
+                .unwrap()
+                .assume_safe()
+                .get_node("./DockVC/Bake")
+                .unwrap()
+                .assume_safe()
+                .cast::<Button>()
+                .unwrap()
+        };
+
+        let export_gltf_button = unsafe {
+            self.dock
+                .unwrap()
+                .assume_safe()
+                .get_node("./DockVC/ExportVC/Formats/ExportGltf")
+                .unwrap()
+                .assume_safe()
+                .cast::<Button>()
+                .unwrap()
+        };
+
+        // The export dialog lives in the editor's base control, so it can
+        // be centered on the whole editor
+        let export_dialog = EditorFileDialog::new();
+        export_dialog.set_mode(EditorFileDialog::MODE_SAVE_FILE);
+        export_dialog.set_access(EditorFileDialog::ACCESS_FILESYSTEM);
+        let export_dialog = export_dialog.into_shared();
+        unsafe {
+            EditorPlugin::get_editor_interface(&owner)
+                .unwrap()
+                .assume_safe()
+                .get_base_control()
+                .unwrap()
+                .assume_safe()
+                .add_child(export_dialog, false);
+        }
+
+        let import_dialog = EditorFileDialog::new();
+        import_dialog.set_mode(EditorFileDialog::MODE_OPEN_FILE);
+        import_dialog.set_access(EditorFileDialog::ACCESS_FILESYSTEM);
+        let import_dialog = import_dialog.into_shared();
+        unsafe {
+            EditorPlugin::get_editor_interface(&owner)
+                .unwrap()
+                .assume_safe()
+                .get_base_control()
+                .unwrap()
+                .assume_safe()
+                .add_child(import_dialog, false);
+        }
+
+        prodot_bake::register_project_settings();
+        let export_plugin = unsafe {
+            load_resource::<NativeScript>("res://addons/prodot_builder/prodot_export_plugin.gdns", "NativeScript")
+                .expect("[Prodot Builder]: Failed to load the export plugin script!")
+                .assume_safe()
+                .new(&[])
+                .try_to_object::<EditorExportPlugin>()
+                .expect("[Prodot Builder]: Failed to create the export plugin!")
+        };
+        owner.add_export_plugin(export_plugin.clone());
+
+        let import_plugin = unsafe {
+            load_resource::<NativeScript>("res://addons/prodot_builder/prodot_import_plugin.gdns", "NativeScript")
+                .expect("[Prodot Builder]: Failed to load the import plugin script!")
+                .assume_safe()
+                .new(&[])
+                .try_to_object::<EditorImportPlugin>()
+                .expect("[Prodot Builder]: Failed to create the import plugin!")
+        };
+        owner.add_import_plugin(import_plugin.clone());
+
 
         // Signals
-        create_cube_button.connect(
-            "create_cube",
+        self.connect_primitive_buttons(owner);
+
+        make_unique_button.connect(
+            "make_unique",
+            owner,
+            "make_unique",
+            VariantArray::new_shared(),
+            0,
+        ).expect("[Prodot Builder]: Error when connecting the make unique button!");
+
+        make_editable_button.connect(
+            "make_editable",
             owner,
-            "create_cube",
+            "make_editable",
             VariantArray::new_shared(),
             0,
-        ).expect("[Prodot Builder]: Error when connecting the create cube button!");
+        ).expect("[Prodot Builder]: Error when connecting the make editable button!");
 
         object_mode_button.connect(
             "object_mode",
@@ -248,12 +488,99 @@ impl ProdotBuilderPlugin {
             0,
         ).expect("[Prodot Builder]: Error when connecting the edge mode button!");
 
+        export_obj_button.connect(
+            "export_obj",
+            owner,
+            "open_export_dialog",
+            VariantArray::new_shared(),
+            0,
+        ).expect("[Prodot Builder]: Error when connecting the export OBJ button!");
+
+        export_gltf_button.connect(
+            "export_gltf",
+            owner,
+            "open_export_dialog",
+            VariantArray::new_shared(),
+            0,
+        ).expect("[Prodot Builder]: Error when connecting the export glTF button!");
+
+        export_prodot_button.connect(
+            "export_prodot",
+            owner,
+            "open_export_dialog",
+            VariantArray::new_shared(),
+            0,
+        ).expect("[Prodot Builder]: Error when connecting the export Prodot button!");
+
+        export_stl_button.connect(
+            "export_stl",
+            owner,
+            "open_export_dialog",
+            VariantArray::new_shared(),
+            0,
+        ).expect("[Prodot Builder]: Error when connecting the export STL button!");
+
+        export_ply_button.connect(
+            "export_ply",
+            owner,
+            "open_export_dialog",
+            VariantArray::new_shared(),
+            0,
+        ).expect("[Prodot Builder]: Error when connecting the export PLY button!");
+
+        unsafe { export_dialog.assume_safe() }.connect(
+            "file_selected",
+            owner,
+            "_on_export_file_selected",
+            VariantArray::new_shared(),
+            0,
+        ).expect("[Prodot Builder]: Error when connecting the export dialog!");
+
+        import_obj_button.connect(
+            "import_obj",
+            owner,
+            "open_import_dialog",
+            VariantArray::new_shared(),
+            0,
+        ).expect("[Prodot Builder]: Error when connecting the import OBJ button!");
+
+        import_ply_button.connect(
+            "import_ply",
+            owner,
+            "open_import_dialog",
+            VariantArray::new_shared(),
+            0,
+        ).expect("[Prodot Builder]: Error when connecting the import PLY button!");
+
+        unsafe { import_dialog.assume_safe() }.connect(
+            "file_selected",
+            owner,
+            "_on_import_file_selected",
+            VariantArray::new_shared(),
+            0,
+        ).expect("[Prodot Builder]: Error when connecting the import dialog!");
+
+        bake_button.connect(
+            "bake",
+            owner,
+            "_on_bake_pressed",
+            VariantArray::new_shared(),
+            0,
+        ).expect("[Prodot Builder]: Error when connecting the bake button!");
+
 
         // Cache
         self.object_mode_button = Some ( object_mode_button.claim() );
         self.vertex_mode_button = Some ( vertex_mode_button.claim() );
         self.face_mode_button = Some ( face_mode_button.claim() );
         self.edge_mode_button = Some ( edge_mode_button.claim() );
+        self.whole_scene_check = Some ( whole_scene_check.claim() );
+        self.apply_transform_check = Some ( apply_transform_check.claim() );
+        self.ascii_check = Some ( ascii_check.claim() );
+        self.export_dialog = Some ( export_dialog );
+        self.import_dialog = Some ( import_dialog );
+        self.export_plugin = Some ( export_plugin );
+        self.import_plugin = Some ( import_plugin );
         
     }
 
@@ -266,8 +593,17 @@ impl ProdotBuilderPlugin {
         // Remove the dock
         owner.remove_control_from_docks(self.dock.unwrap());
         
+        if let Some(export_plugin) = self.export_plugin.take() {
+            owner.remove_export_plugin(export_plugin);
+        }
+        if let Some(import_plugin) = self.import_plugin.take() {
+            owner.remove_import_plugin(import_plugin);
+        }
+
         // Free the stored instanciated nodes
         unsafe { self.dock.unwrap().assume_safe().queue_free() };
+        unsafe { self.export_dialog.unwrap().assume_safe().queue_free() };
+        unsafe { self.import_dialog.unwrap().assume_safe().queue_free() };
         //unsafe { self.object_mode_button.unwrap().assume_safe().queue_free() };
         //unsafe { self.vertex_mode_button.unwrap().assume_safe().queue_free() };
         //unsafe { self.face_mode_button.unwrap().assume_safe().queue_free() };
@@ -275,16 +611,44 @@ impl ProdotBuilderPlugin {
 
         // Clean up
         self.selected_node = None;
+        self.selected_mesh_instance = None;
         self.mesh_scene = None;
         self.object_mode_button = None;
         self.vertex_mode_button = None;
         self.face_mode_button = None;
         self.edge_mode_button = None;
+        self.whole_scene_check = None;
+        self.apply_transform_check = None;
+        self.ascii_check = None;
+        self.export_dialog = None;
+        self.import_dialog = None;
 
     }
 
     #[export]
     fn _process(&mut self, owner: TRef<EditorPlugin>, _delta: f64) {
+        // Undoing Make Editable takes the script off the selected node
+        if let Some(mesh_ref) = self.selected_node {
+            if unsafe { mesh_ref.assume_safe() }.cast_instance::<ProdotMesh>().is_none() {
+                self.selected_indices.clear();
+                self.selected_node = None;
+            }
+        }
+
+        // Indices picked on a mesh that has since been swapped or rebuilt
+        // may point past its end
+        if let Some(mesh_ref) = self.selected_node {
+            let counts = unsafe { mesh_ref.assume_safe() }
+                .cast_instance::<ProdotMesh>()
+                .and_then(|mesh| mesh.map(|mesh, _| (mesh.poly_mesh().vertex_count(), mesh.poly_mesh().face_count())).ok());
+            if let Some(counts) = counts {
+                if counts != self.selected_counts {
+                    self.selected_counts = counts;
+                    self.reset(owner);
+                }
+            }
+        }
+
         match self.selected_node {
             Some(mesh_ref) => {
                 let editor_instance = unsafe { EditorPlugin::get_editor_interface(&owner).unwrap().assume_safe() };
@@ -326,13 +690,23 @@ impl ProdotBuilderPlugin {
     /// Requests the editor to edit the given object
     /// Sends the plugin the object that is being edited.
     ///
+    /// Only ProdotMesh nodes can be edited, other MeshInstances are kept
+    /// for Make Editable.
+    ///
     #[export]
     fn edit(&mut self, _owner: TRef<EditorPlugin>, object: Ref<Object>) {
         match unsafe { object.assume_safe().cast::<MeshInstance>() } {
             Some(node) => {
-                self.selected_node = Some(node.claim());
+                self.selected_mesh_instance = Some(node.claim());
+                self.selected_node = match node.cast_instance::<ProdotMesh>() {
+                    Some(_) => Some(node.claim()),
+                    None => None,
+                };
+            }
+            None => {
+                self.selected_mesh_instance = None;
+                self.selected_node = None;
             }
-            None => self.selected_node = None,
         }
     }
 
@@ -344,7 +718,8 @@ impl ProdotBuilderPlugin {
             }
             None =>{
                 self.reset(owner);
-                self.selected_node = None
+                self.selected_node = None;
+                self.selected_mesh_instance = None;
             },
         }
 
@@ -381,7 +756,11 @@ impl ProdotBuilderPlugin {
 
             let mesh = unsafe { node.assume_safe() };
             let mesh_pos = mesh.global_transform().origin;
-            let mesh_script = mesh.cast_instance::<ProdotMesh>().unwrap();
+            // Other MeshInstances are only selected for Make Editable
+            let mesh_script = match mesh.cast_instance::<ProdotMesh>() {
+                Some(mesh_script) => mesh_script,
+                None => return false,
+            };
 
     // --------------- Input Event With Modifers ------------------------ //
     //
@@ -409,14 +788,6 @@ impl ProdotBuilderPlugin {
 
                     },
                     BuildMode::Vertex => {
-                        let vertices = 
-                            mesh_script
-                                .map_mut(|mesh, owner: TRef<MeshInstance>| {
-                                    mesh.get_vertices(owner)
-                                })
-                                .ok()
-                                .unwrap();
-
                         let box_size: f32 = 0.1;
                         
                         // Check to see if the user is still dragging, and if so update the position
@@ -426,7 +797,18 @@ impl ProdotBuilderPlugin {
                             let mut new_positions: HashMap<i32, Vector3> = HashMap::<i32, Vector3>::new();
                             
                             for index in self.selected_indices.iter() {
-                                let vertex_pos: Vector3 = vertices.get(*index);
+                                // Only the dragged vertices are read, so large meshes stay responsive
+                                let vertex_pos = 
+                                    mesh_script
+                                        .map_mut(|mesh, owner: TRef<MeshInstance>| {
+                                            mesh.get_vertex(owner, *index)
+                                        })
+                                        .ok()
+                                        .unwrap();
+                                let vertex_pos: Vector3 = match vertex_pos {
+                                    Some(vertex_pos) => vertex_pos,
+                                    None => continue,
+                                };
                                 let mut g_pos: Vector3; 
                                 if self.hovering_gizmo_axis == Vector3::new(1.0, 0.0, 0.0) {
                                     plane.d = vertex_pos.z;
@@ -473,23 +855,17 @@ impl ProdotBuilderPlugin {
                             }
                                                 
                         } else {
-                            for i in 0..vertices.len() {
-                                let base_pos: Vector3 = vertices.get(i) + mesh_pos;
+                            let picked_vertex = 
+                                mesh_script
+                                    .map(|mesh, _owner: TRef<MeshInstance>| {
+                                        picking::pick_vertex(mesh.poly_mesh(), to_vec3(mesh_pos), to_vec3(origin), to_vec3(normal), box_size)
+                                    })
+                                    .ok()
+                                    .unwrap();
 
-                                let vertex_pos = base_pos;
-                                plane.d = vertex_pos.z;
-                                
-                                if let Some(proj_pos) = plane.intersects_ray(origin, normal) {
-                                    if proj_pos.x > vertex_pos.x - box_size &&
-                                        proj_pos.x < vertex_pos.x + box_size &&
-                                        proj_pos.y > vertex_pos.y - box_size &&
-                                        proj_pos.y < vertex_pos.y + box_size &&
-                                        proj_pos.z > vertex_pos.z - box_size &&
-                                        proj_pos.z < vertex_pos.z + box_size { 
-                                        self.hover_index = i as i32;
-                                        hover_index_found = true;
-                                    }
-                                }
+                            if let Some(vertex) = picked_vertex {
+                                self.hover_index = vertex as i32;
+                                hover_index_found = true;
                             }
                         }
                         
@@ -506,25 +882,6 @@ impl ProdotBuilderPlugin {
                         }
                     },
                     BuildMode::Face => {
-                        let geom = Geometry::godot_singleton();
-                        let vertices = 
-                            mesh_script
-                                .map_mut(|mesh, owner: TRef<MeshInstance>| {
-                                    mesh.get_vertices(owner)
-                                })
-                                .ok()
-                                .unwrap();
-                        // Check faces
-                        let faces: Vec::<Face> = 
-                            mesh_script
-                                .map_mut(|mesh, owner: TRef<MeshInstance>| {
-                                    mesh.get_faces(owner)
-                                })
-                                .ok()
-                                .unwrap();
-                        
-                        let mut closest_index = -1;
-                        let mut closest_dist = 10000000.0;
 
                         // Check to see if the user is still dragging, and if so update the position
                         /*if self.is_dragging && !self.selected_indices.is_empty() && self.hovering_gizmo_axis != Vector3::zero() {
@@ -575,59 +932,18 @@ impl ProdotBuilderPlugin {
                                     .unwrap();
                             }
                         } else {*/
-                            for i in 0..faces.len() {
-                                let face = faces[i];
-                                let vertex_one = mesh_pos + vertices.get(face.tris_one.x as i32);
-                                let vertex_two = mesh_pos + vertices.get(face.tris_one.y as i32);
-                                let vertex_three = mesh_pos + vertices.get(face.tris_one.z as i32);
-                                
-
-                                let vertex_four = mesh_pos + vertices.get(face.tris_two.x as i32);
-                                let vertex_five = mesh_pos + vertices.get(face.tris_two.y as i32);
-                                let vertex_six =  mesh_pos + vertices.get(face.tris_two.z as i32);
-
-                                let result_one = geom.ray_intersects_triangle(
-                                    origin,
-                                    normal,
-                                    vertex_one,
-                                    vertex_two,
-                                    vertex_three
-                                );
-
-                                let result_two = geom.ray_intersects_triangle(
-                                    origin,
-                                    normal,
-                                    vertex_four,
-                                    vertex_five,
-                                    vertex_six
-                                );
-
-                                let mut dist_one = 1000000.0; 
-                                let mut intersects = false; 
-                                if !result_one.is_nil() {
-                                    intersects = true;
-                                    dist_one = origin.distance_to(result_one.to_vector3()).abs();
-                                }
-                                
-                                let mut dist_two = 1000000.0;
-                                if !result_two.is_nil() {
-                                    intersects = true;
-                                    dist_two = origin.distance_to(result_two.to_vector3()).abs();
-                                }
-
-                                if intersects && (dist_one < closest_dist || dist_two < closest_dist) {
-                                    closest_index = i as i32;
-                                    if dist_one <= dist_two {
-                                        closest_dist = dist_one;
-                                    } else {
-                                        closest_dist = dist_two;
-                                    }
-                                }
-                            }
+                            // Check faces
+                            let picked_face = 
+                                mesh_script
+                                    .map(|mesh, _owner: TRef<MeshInstance>| {
+                                        picking::pick_face(mesh.poly_mesh(), to_vec3(mesh_pos), to_vec3(origin), to_vec3(normal))
+                                    })
+                                    .ok()
+                                    .unwrap();
 
-                            if closest_index != -1 {
+                            if let Some(face) = picked_face {
                                 hover_index_found = true;
-                                self.hover_index = closest_index as i32;
+                                self.hover_index = face as i32;
                             }
 
                             // Check to see if the mouse if hovering over a gizmo on the selected vertex
@@ -676,15 +992,19 @@ impl ProdotBuilderPlugin {
                                     } 
                                     self.selected_indices.push(self.hover_index);
                                     
-                                    // Get the starting vertex position
-                                    let vertex_pos = 
-                                        mesh_script
-                                            .map_mut(|mesh, owner: TRef<MeshInstance>| {
-                                                mesh.get_vertex(owner, self.hover_index)
-                                            })
-                                            .ok()
-                                            .unwrap();
-                                    self.vertices_drag_state.insert(self.hover_index, vertex_pos);
+                                    // Get the starting vertex position, only vertices are dragged
+                                    if matches!(self.build_mode, BuildMode::Vertex) {
+                                        let vertex_pos = 
+                                            mesh_script
+                                                .map_mut(|mesh, owner: TRef<MeshInstance>| {
+                                                    mesh.get_vertex(owner, self.hover_index)
+                                                })
+                                                .ok()
+                                                .unwrap();
+                                        if let Some(vertex_pos) = vertex_pos {
+                                            self.vertices_drag_state.insert(self.hover_index, vertex_pos);
+                                        }
+                                    }
                                 } else if control_down{
                                     let mut index: i32 = -1;
                                     for i in 0..self.selected_indices.len() {
@@ -698,41 +1018,21 @@ impl ProdotBuilderPlugin {
                                         self.selected_indices.remove(index as usize);
                                     }
                                 }
-                            
+
+                                // Undo may have moved the vertices since the last drag
+                                self.capture_drag_state(mesh);
                                 self.is_dragging = true;
                                 consume_input = true;
                                 owner.update_overlays();
                             } else {
                                 self.is_dragging = false;
-                                for (index, _position) in self.vertices_drag_state.clone() {
-                                // Get the starting vertex position
-                                let vertex_pos = 
-                                    mesh_script
-                                        .map_mut(|mesh, owner: TRef<MeshInstance>| {
-                                            mesh.get_vertex(owner, index)
-                                        })
-                                        .ok()
-                                        .unwrap();
-                                self.vertices_drag_state.insert(index, vertex_pos);
-                            }
+                                self.commit_drag(owner, mesh);
                                 //self.vertices_drag_state.clear();
                             }
                         } else if self.is_dragging && !button.is_pressed() {
                             self.is_dragging = false;
                             //self.vertices_drag_state.clear();
-                            
-                            for (index, _position) in self.vertices_drag_state.clone() {
-                                godot_print!("Update them");
-                                // Get the starting vertex position
-                                let vertex_pos = 
-                                    mesh_script
-                                        .map_mut(|mesh, owner: TRef<MeshInstance>| {
-                                            mesh.get_vertex(owner, index)
-                                        })
-                                        .ok()
-                                        .unwrap();
-                                self.vertices_drag_state.insert(index, vertex_pos);
-                            }
+                            self.commit_drag(owner, mesh);
                             owner.update_overlays();
                         }
                     },
@@ -753,168 +1053,127 @@ impl ProdotBuilderPlugin {
         consume_input
     }
 
-    /// Creates a default 1x1x1 cube and attaches it to the current
-    /// scenes root node
+    /// Adds a box to the edited scene. `params` can hold `width`, `height`,
+    /// `depth`, the quad counts `segments_x`, `segments_y` and `segments_z`
+    /// and the `pivot` (0 corner, 1 center, 2 bottom center). Missing ones
+    /// make a 1x1x1 cube with a corner at the origin.
     ///
     #[export]
-    pub fn create_cube(&mut self, owner: TRef<EditorPlugin>) {
-        godot_print!("Created cube!");
+    pub fn create_cube(&mut self, owner: TRef<EditorPlugin>, #[opt] params: Option<Dictionary>) {
+        let params = params.as_ref().map_or_else(BoxParams::default, prodot_primitives::box_params);
+        let poly_mesh = primitives::box_mesh(&params);
+        self.add_prodot_mesh(owner, "Create Prodot Box", None, poly_mesh, Some(NormalMode::Flat), Vec::new());
+    }
 
-        let editor = EditorPlugin::get_editor_interface(owner.as_ref()).unwrap();
-        let root_node = unsafe { editor.assume_safe().get_edited_scene_root().unwrap().assume_safe() };
+    /// Adds a flat grid of quads to the edited scene, centered on the
+    /// origin. `params` can hold `width`, `depth`, the quad counts
+    /// `segments_x` and `segments_z` and the `facing` direction (0 up,
+    /// 1 down, 2 +X, 3 -X, 4 +Z, 5 -Z). Missing ones make a 2x2 quad
+    /// facing up.
+    ///
+    #[export]
+    pub fn create_plane(&mut self, owner: TRef<EditorPlugin>, #[opt] params: Option<Dictionary>) {
+        let params = params.as_ref().map_or_else(PlaneParams::default, prodot_primitives::plane_params);
+        let poly_mesh = primitives::plane_mesh(&params);
+        self.add_prodot_mesh(owner, "Create Prodot Plane", None, poly_mesh, Some(NormalMode::Flat), Vec::new());
+    }
 
-        
-        let arrays = VariantArray::new();
-        let mut normal_array = TypedArray::<Vector3>::new();
-        let mut uv_array = TypedArray::<Vector2>::new();
-        let mut vertex_array = TypedArray::<Vector3>::new();
-        let mut index_array = TypedArray::<i32>::new();
-        let mut face_array = Vec::<Face>::new();
-
-        arrays.resize(Mesh::ARRAY_MAX as i32);
-        
-        // Front face
-        // 
-
-        //bl
-        normal_array.push(Vector3::new(0.0, 0.0, 1.0));
-        uv_array.push(Vector2::new(0.0, 0.0));
-        vertex_array.push(Vector3::new(0.0, 0.0, 1.0));
-
-        // tl
-        normal_array.push(Vector3::new(0.0, 0.0, 1.0));
-        uv_array.push(Vector2::new(0.0, 1.0));
-        vertex_array.push(Vector3::new(0.0, 1.0, 1.0));
-
-        // tr 
-        normal_array.push(Vector3::new(0.0, 0.0, 1.0));
-        uv_array.push(Vector2::new(1.0, 1.0));
-        vertex_array.push(Vector3::new(1.0, 1.0, 1.0));
-        
-        // br 
-        normal_array.push(Vector3::new(0.0, 0.0, 1.0));
-        uv_array.push(Vector2::new(1.0, 0.0));
-        vertex_array.push(Vector3::new(1.0, 0.0, 1.0));
-        
-        index_array.push(0);
-        index_array.push(1);
-        index_array.push(2);
+    /// Adds a cylinder along Y to the edited scene, centered on the
+    /// origin. `params` can hold `radius_top`, `radius_bottom`, `height`,
+    /// the quad counts `radial_segments` and `height_segments`, the `caps`
+    /// (0 n-gon, 1 triangle fan, 2 none) and whether to `smooth` the
+    /// side. A top radius of 0 makes a cone.
+    ///
+    #[export]
+    pub fn create_cylinder(&mut self, owner: TRef<EditorPlugin>, #[opt] params: Option<Dictionary>) {
+        let defaults = CylinderParams::default();
+        let params = params.as_ref().map_or(defaults, |params| prodot_primitives::cylinder_params(params, defaults));
+        let poly_mesh = primitives::cylinder_mesh(&params);
+        self.add_prodot_mesh(owner, "Create Prodot Cylinder", None, poly_mesh, Some(params.normal_mode()), Vec::new());
+    }
 
-        index_array.push(2);
-        index_array.push(3);
-        index_array.push(0);
-        
+    /// Adds a cone to the edited scene. Takes the same `params` as
+    /// `create_cylinder`, but `radius_top` defaults to 0.
+    ///
+    #[export]
+    pub fn create_cone(&mut self, owner: TRef<EditorPlugin>, #[opt] params: Option<Dictionary>) {
+        let defaults = CylinderParams::cone();
+        let params = params.as_ref().map_or(defaults, |params| prodot_primitives::cylinder_params(params, defaults));
+        let poly_mesh = primitives::cylinder_mesh(&params);
+        self.add_prodot_mesh(owner, "Create Prodot Cone", None, poly_mesh, Some(params.normal_mode()), Vec::new());
+    }
+
+    /// Adds a sphere of quads between rings of latitude to the edited
+    /// scene, centered on the origin. `params` can hold the `radius`, the
+    /// `segments` around it and the `rings` from pole to pole.
+    ///
+    #[export]
+    pub fn create_uv_sphere(&mut self, owner: TRef<EditorPlugin>, #[opt] params: Option<Dictionary>) {
+        let params = params.as_ref().map_or_else(UvSphereParams::default, prodot_primitives::uv_sphere_params);
+        let poly_mesh = primitives::uv_sphere_mesh(&params);
+        self.add_prodot_mesh(owner, "Create Prodot UV Sphere", None, poly_mesh, Some(NormalMode::Smooth), Vec::new());
+    }
+
+    /// Adds a sphere of even triangles to the edited scene, centered on
+    /// the origin. `params` can hold the `radius` and how many times to
+    /// split the icosahedron it starts from, `subdivisions`.
+    ///
+    #[export]
+    pub fn create_icosphere(&mut self, owner: TRef<EditorPlugin>, #[opt] params: Option<Dictionary>) {
+        let params = params.as_ref().map_or_else(IcosphereParams::default, prodot_primitives::icosphere_params);
+        let poly_mesh = primitives::icosphere_mesh(&params);
+        self.add_prodot_mesh(owner, "Create Prodot Icosphere", None, poly_mesh, Some(NormalMode::Smooth), Vec::new());
+    }
+
+    /// Adds a torus lying around Y to the edited scene, centered on the
+    /// origin. `params` can hold the `major_radius` of the ring, the
+    /// `minor_radius` of its tube and the quad counts `major_segments`
+    /// around the ring and `minor_segments` around the tube.
+    ///
+    #[export]
+    pub fn create_torus(&mut self, owner: TRef<EditorPlugin>, #[opt] params: Option<Dictionary>) {
+        let params = params.as_ref().map_or_else(TorusParams::default, prodot_primitives::torus_params);
+        let poly_mesh = primitives::torus_mesh(&params);
+        self.add_prodot_mesh(owner, "Create Prodot Torus", None, poly_mesh, Some(NormalMode::Smooth), Vec::new());
+    }
+
+    /// Adds a stair to the edited scene, climbing from the origin towards
+    /// -Z. `params` can hold the number of `steps`, their `rise`, `run`
+    /// and `width`, the `layout` (0 straight, 1 L-shaped, 2 spiral), the
+    /// `support` (0 solid, 1 stringers), the `stringer_width` and
+    /// `stringer_depth` and the `inner_radius` of spirals. Treads, risers
+    /// and the rest get material slots 0, 1 and 2.
+    ///
+    #[export]
+    pub fn create_stairs(&mut self, owner: TRef<EditorPlugin>, #[opt] params: Option<Dictionary>) {
+        let params = params.as_ref().map_or_else(StairParams::default, prodot_primitives::stair_params);
+        let poly_mesh = primitives::stair_mesh(&params);
+        self.add_prodot_mesh(owner, "Create Prodot Stairs", None, poly_mesh, Some(NormalMode::Flat), vec![None; 3]);
+    }
+
+    /// Adds a ProdotMesh node with `poly_mesh` to the edited scene as the
+    /// undoable action `action`. The node is shaded with `normal_mode`,
+    /// or keeps the corner normals of `poly_mesh` when there is none.
+    /// Slots get `materials`, and it is named `name` when there is one.
+    /// Returns the new node.
+    ///
+    fn add_prodot_mesh(
+        &self,
+        owner: TRef<EditorPlugin>,
+        action: &str,
+        name: Option<&str>,
+        poly_mesh: PolyMesh,
+        normal_mode: Option<NormalMode>,
+        materials: Vec<Option<Ref<Material, Shared>>>,
+    ) -> Option<Ref<MeshInstance, Shared>> {
+        let root_node = match self.scene_root(owner) {
+            Some(root_node) => unsafe { root_node.assume_safe() },
+            None => {
+                godot_print!("[Prodot Builder]: Open a scene to add a ProdotMesh to!");
+                return None;
+            }
+        };
 
-        face_array.push(Face {
-            tris_one: Vector3::new(0.0, 1.0, 2.0),
-            tris_two: Vector3::new(2.0, 3.0, 0.0),
-        });
-
-        // Right face
-        //
-        // tr 
-        normal_array.push(Vector3::new(1.0, 0.0, 0.0));
-        uv_array.push(Vector2::new(1.0, 1.0));
-        vertex_array.push(Vector3::new(1.0, 1.0, 0.0));
-        
-        // br 
-        normal_array.push(Vector3::new(1.0, 0.0, 0.0));
-        uv_array.push(Vector2::new(1.0, 0.0));
-        vertex_array.push(Vector3::new(1.0, 0.0, 0.0));
-        
-        index_array.push(3);
-        index_array.push(2);
-        index_array.push(4);
-
-        index_array.push(4);
-        index_array.push(5);
-        index_array.push(3);
-
-        face_array.push(Face {
-            tris_one: Vector3::new(3.0, 2.0, 4.0),
-            tris_two: Vector3::new(4.0, 5.0, 3.0),
-        });
-
-        // Left face
-        //bl
-        normal_array.push(Vector3::new(-1.0, 0.0, 0.0));
-        uv_array.push(Vector2::new(0.0, 0.0));
-        vertex_array.push(Vector3::new(0.0, 0.0, 0.0));
-
-        // tl
-        normal_array.push(Vector3::new(-1.0, 0.0, 0.0));
-        uv_array.push(Vector2::new(0.0, 1.0));
-        vertex_array.push(Vector3::new(0.0, 1.0, 0.0));
-        
-        index_array.push(6);
-        index_array.push(7);
-        index_array.push(1);
-
-        index_array.push(1);
-        index_array.push(0);
-        index_array.push(6);
-
-        face_array.push(Face {
-            tris_one: Vector3::new(6.0, 7.0, 1.0),
-            tris_two: Vector3::new(1.0, 0.0, 6.0),
-        });
-
-        // Back Face
-        //
-        index_array.push(5);
-        index_array.push(4);
-        index_array.push(7);
-
-        index_array.push(7);
-        index_array.push(6);
-        index_array.push(5);
-        
-        face_array.push(Face {
-            tris_one: Vector3::new(5.0, 4.0, 7.0),
-            tris_two: Vector3::new(7.0, 6.0, 5.0),
-        });
-
-        // Top Face
-        // 
-        index_array.push(1);
-        index_array.push(7);
-        index_array.push(4);
-
-        index_array.push(4);
-        index_array.push(2);
-        index_array.push(1);
-
-
-        face_array.push(Face {
-            tris_one: Vector3::new(1.0, 7.0, 4.0),
-            tris_two: Vector3::new(4.0, 2.0, 1.0),
-        });
-
-        // Bottom Face
-        //
-        index_array.push(6);
-        index_array.push(0);
-        index_array.push(3);
-
-        index_array.push(3);
-        index_array.push(5);
-        index_array.push(6);
-        
-        face_array.push(Face {
-            tris_one: Vector3::new(6.0, 0.0, 3.0),
-            tris_two: Vector3::new(3.0, 5.0, 6.0),
-        });
-
-        arrays.set(Mesh::ARRAY_VERTEX as i32, vertex_array.clone());
-        arrays.set(Mesh::ARRAY_NORMAL as i32, normal_array);
-        arrays.set(Mesh::ARRAY_TEX_UV as i32, uv_array);
-        arrays.set(Mesh::ARRAY_INDEX as i32, index_array);
-
-        let blend_shapes = VariantArray::new_shared();
-        //blend_shapes.resize(Mesh::ARRAY_MAX as i32);
-        let end_mesh = ArrayMesh::new();
-        end_mesh.add_surface_from_arrays(Mesh::PRIMITIVE_TRIANGLES, arrays.into_shared(), blend_shapes, 97280);
-        
         let mesh_instance = unsafe {
             self
                 .mesh_scene
@@ -927,10 +1186,27 @@ impl ProdotBuilderPlugin {
                 .cast::<MeshInstance>()
                 .unwrap()
         };
-        
-        root_node.add_child(mesh_instance, false);
-        mesh_instance.set_owner(root_node);
-        mesh_instance.set_mesh(end_mesh);
+        if let Some(name) = name {
+            mesh_instance.set_name(name);
+        }
+
+        // Undo removes the node again, and the reference keeps it alive
+        // until redo adds it back
+        let undo_redo = unsafe { self.undo_redo(owner).assume_safe() };
+        undo_redo.create_action(action, UndoRedo::MERGE_DISABLE);
+        undo_redo.add_do_method(root_node, "add_child", &[mesh_instance.to_variant(), name.is_some().to_variant()]);
+        undo_redo.add_do_method(mesh_instance, "set_owner", &[root_node.to_variant()]);
+        undo_redo.add_do_reference(mesh_instance);
+        undo_redo.add_undo_method(root_node, "remove_child", &[mesh_instance.to_variant()]);
+        undo_redo.commit_action();
+
+        if !materials.is_empty() {
+            let slots = VariantArray::new();
+            for material in materials.iter() {
+                slots.push(material.to_variant());
+            }
+            mesh_instance.set("materials", slots.into_shared());
+        }
 
         let mesh_script = 
             mesh_instance
@@ -938,14 +1214,535 @@ impl ProdotBuilderPlugin {
                 .unwrap();
         mesh_script
             .map_mut(|mesh, owner: TRef<MeshInstance>| {
-                mesh.set_vertices(owner, vertex_array);
-                mesh.set_faces(owner, face_array);
+                mesh.set_mesh_data(owner, Some(create_mesh_data()));
+                match normal_mode {
+                    Some(normal_mode) => {
+                        mesh.set_normal_mode(owner, normal_mode);
+                        mesh.set_poly_mesh(owner, poly_mesh);
+                    },
+                    None => mesh.set_poly_mesh_keep_normals(owner, poly_mesh),
+                }
             })
             .ok()
             .unwrap();
 
+        Some(mesh_instance.claim())
+    }
+
+    /// Gives the selected ProdotMesh its own copy of its mesh data
+    ///
+    #[export]
+    pub fn make_unique(&mut self, owner: TRef<EditorPlugin>) {
+        if let Some(mesh_ref) = self.selected_node {
+            let mesh = unsafe { mesh_ref.assume_safe() };
+            if let Some(mesh_script) = mesh.cast_instance::<ProdotMesh>() {
+                let shared_data = 
+                    mesh_script
+                        .map(|mesh, owner: TRef<MeshInstance>| {
+                            mesh.get_mesh_data(owner)
+                        })
+                        .ok()
+                        .unwrap();
+
+                let undo_redo = unsafe { self.undo_redo(owner).assume_safe() };
+                undo_redo.create_action("Make Prodot Mesh Unique", UndoRedo::MERGE_DISABLE);
+                undo_redo.add_do_method(mesh, "make_unique", &[]);
+                undo_redo.add_undo_method(mesh, "set_mesh_data", &[shared_data.to_variant()]);
+                undo_redo.commit_action();
+            }
+        }
     }
-    /// Sets the build mode of the plugin
+
+    /// Turns the selected MeshInstance into a ProdotMesh in place, as an
+    /// undoable action. Its mesh, an ArrayMesh or a primitive mesh, is
+    /// welded into shared vertices and coplanar triangle pairs become
+    /// quads again. Each surface becomes a material slot.
+    ///
+    #[export]
+    pub fn make_editable(&mut self, owner: TRef<EditorPlugin>) {
+        let node = match self.selected_mesh_instance {
+            Some(node) => unsafe { node.assume_safe() },
+            None => {
+                godot_print!("[Prodot Builder]: Select a MeshInstance to make editable!");
+                return;
+            }
+        };
+        if node.cast_instance::<ProdotMesh>().is_some() {
+            godot_print!("[Prodot Builder]: {} is already a ProdotMesh!", node.name());
+            return;
+        }
+
+        let topology = match prodot_import::mesh_instance_topology(node) {
+            Ok(topology) => topology,
+            Err(err) => {
+                godot_print!("[Prodot Builder]: {}", err);
+                return;
+            }
+        };
+
+        let mesh_data = create_mesh_data();
+        map_mesh_data(&mesh_data, |data, data_owner| {
+            data.set_arrays(data_owner, topology.mesh.to_arrays());
+        })
+        .expect("[Prodot Builder]: Failed to fill the new mesh data!");
+        let script = unsafe {
+            load_resource::<Script>("res://addons/prodot_builder/prodot_mesh.gdns", "Script")
+                .expect("[Prodot Builder]: Failed to load the ProdotMesh script!")
+        };
+        let slots = VariantArray::new();
+        for material in topology.materials.iter() {
+            slots.push(material.to_variant());
+        }
+        let slots = slots.into_shared();
+
+        // The ProdotMesh builds its own ArrayMesh from the mesh data, undo
+        // puts the old mesh and script back
+        let undo_redo = unsafe { self.undo_redo(owner).assume_safe() };
+        undo_redo.create_action("Make Prodot Mesh Editable", UndoRedo::MERGE_DISABLE);
+        undo_redo.add_do_method(node, "set_script", &[script.to_variant()]);
+        undo_redo.add_do_method(node, "set_mesh", &[Variant::new()]);
+        undo_redo.add_do_property(node, "materials", slots);
+        undo_redo.add_do_method(node, "set_mesh_data", &[mesh_data.to_variant()]);
+        // Processing is only turned on for scripts the node was ready with
+        undo_redo.add_do_method(node, "set_process", &[true.to_variant()]);
+        // The overlay child is only added once the mesh is drawn, and isn't
+        // the node's own unless it already had one
+        if node.has_node(IMMEDIATE_GEOMETRY_NAME) {
+            undo_redo.add_undo_method(node, "clear", &[]);
+        } else {
+            undo_redo.add_undo_method(node, "remove_overlay", &[]);
+        }
+        undo_redo.add_undo_method(node, "set_process", &[node.is_processing().to_variant()]);
+        undo_redo.add_undo_method(node, "set_script", &[node.get_script().to_variant()]);
+        undo_redo.add_undo_method(node, "set_mesh", &[node.mesh().to_variant()]);
+        undo_redo.commit_action();
+
+        self.selected_node = Some(node.claim());
+        godot_print!("[Prodot Builder]: Made {} editable with {} face(s)", node.name(), topology.mesh.face_count());
+    }
+
+    /// Puts the selected faces of the selected ProdotMesh in material
+    /// slot `slot`. Only works in face mode.
+    ///
+    #[export]
+    pub fn set_selected_faces_material(&mut self, owner: TRef<EditorPlugin>, slot: i64) {
+        if !matches!(self.build_mode, BuildMode::Face) || self.selected_indices.is_empty() {
+            godot_print!("[Prodot Builder]: Select faces in face mode to set their material!");
+            return;
+        }
+
+        if let Some(mesh_ref) = self.selected_node {
+            let mesh = unsafe { mesh_ref.assume_safe() };
+            if let Some(mesh_script) = mesh.cast_instance::<ProdotMesh>() {
+                let faces = self.selected_indices.clone();
+                let old_slots: Option<Vec<i64>> = 
+                    mesh_script
+                        .map_mut(|mesh, owner: TRef<MeshInstance>| {
+                            faces.iter().map(|face| mesh.get_face_material(owner, *face)).collect()
+                        })
+                        .ok()
+                        .unwrap();
+                let old_slots = match old_slots {
+                    Some(old_slots) => old_slots,
+                    None => return,
+                };
+
+                let undo_redo = unsafe { self.undo_redo(owner).assume_safe() };
+                undo_redo.create_action("Set Face Material", UndoRedo::MERGE_DISABLE);
+                undo_redo.add_do_method(mesh, "set_face_material", &[faces.to_variant(), slot.to_variant()]);
+                undo_redo.add_undo_method(mesh, "set_face_materials", &[faces.to_variant(), old_slots.to_variant()]);
+                undo_redo.commit_action();
+            }
+        }
+    }
+
+    /// Opens the export dialog for `format`, called by the dock's export
+    /// buttons
+    ///
+    #[export]
+    pub fn open_export_dialog(&mut self, owner: TRef<EditorPlugin>, format: i64) {
+        self.export_format = match ExportFormat::from_value(format) {
+            Some(format) => format,
+            None => {
+                godot_print!("[Prodot Builder]: Cannot export to an unknown format!");
+                return;
+            }
+        };
+
+        let file_name = if self.is_checked(self.whole_scene_check) {
+            self.scene_root(owner).map(|root| unsafe { root.assume_safe() }.name().to_string())
+        } else {
+            self.selected_node.map(|node| unsafe { node.assume_safe() }.name().to_string())
+        };
+
+        let dialog = unsafe { self.export_dialog.unwrap().assume_safe() };
+        dialog.clear_filters();
+        dialog.add_filter(self.export_format.filter());
+        dialog.set_current_file(format!(
+            "{}.{}",
+            file_name.unwrap_or_else(|| "mesh".to_string()),
+            self.export_format.extension()
+        ));
+        dialog.popup_centered_ratio(0.6);
+    }
+
+    #[export]
+    fn _on_export_file_selected(&mut self, owner: TRef<EditorPlugin>, path: String) {
+        let whole_scene = self.is_checked(self.whole_scene_check);
+        let apply_transform = self.is_checked(self.apply_transform_check);
+        match self.export_format {
+            ExportFormat::Obj => {
+                self.export_obj(owner, path, whole_scene, apply_transform);
+            }
+            ExportFormat::Gltf => {
+                self.export_gltf(owner, path, whole_scene, apply_transform);
+            }
+            ExportFormat::Prodot => {
+                self.export_prodot(owner, path);
+            }
+            ExportFormat::Stl => {
+                let ascii = self.is_checked(self.ascii_check);
+                self.export_stl(owner, path, whole_scene, apply_transform, ascii);
+            }
+            ExportFormat::Ply => {
+                let ascii = self.is_checked(self.ascii_check);
+                self.export_ply(owner, path, whole_scene, apply_transform, ascii);
+            }
+        }
+    }
+
+    /// Writes the selected ProdotMesh, or every ProdotMesh in the scene, to
+    /// an OBJ file at `path` and its materials to an MTL file next to it.
+    /// Faces keep their corner count, and vertices are moved into world
+    /// space with `apply_transform`. Returns whether the files were written.
+    ///
+    #[export]
+    pub fn export_obj(&mut self, owner: TRef<EditorPlugin>, path: String, whole_scene: bool, apply_transform: bool) -> bool {
+        let meshes = self.export_meshes(owner, whole_scene, apply_transform);
+        if meshes.is_empty() {
+            godot_print!("[Prodot Builder]: There is no ProdotMesh to export!");
+            return false;
+        }
+
+        match prodot_export::export_obj(&path, &meshes) {
+            Ok(()) => {
+                godot_print!("[Prodot Builder]: Exported {} mesh(es) to {}", meshes.len(), path);
+                true
+            }
+            Err(err) => {
+                godot_print!("[Prodot Builder]: {}", err);
+                false
+            }
+        }
+    }
+
+    /// Writes the selected ProdotMesh, or every ProdotMesh in the scene, to
+    /// a glTF 2.0 file at `path`, binary if it ends in `.glb`. The whole
+    /// scene keeps its node hierarchy and local transforms, a selected
+    /// mesh is placed at its global transform with `apply_transform`.
+    /// Returns whether the file was written.
+    ///
+    #[export]
+    pub fn export_gltf(&mut self, owner: TRef<EditorPlugin>, path: String, whole_scene: bool, apply_transform: bool) -> bool {
+        let nodes = if whole_scene {
+            match self.scene_root(owner) {
+                Some(root) => prodot_export::scene_export_nodes(unsafe { root.assume_safe() }),
+                None => Vec::new(),
+            }
+        } else {
+            self.export_meshes(owner, false, apply_transform)
+                .into_iter()
+                .map(ExportNode::from_mesh)
+                .collect()
+        };
+        if nodes.is_empty() {
+            godot_print!("[Prodot Builder]: There is no ProdotMesh to export!");
+            return false;
+        }
+
+        match prodot_export::export_gltf(&path, &nodes) {
+            Ok(()) => {
+                let count = nodes.iter().filter(|node| node.mesh.is_some()).count();
+                godot_print!("[Prodot Builder]: Exported {} mesh(es) to {}", count, path);
+                true
+            }
+            Err(err) => {
+                godot_print!("[Prodot Builder]: {}", err);
+                false
+            }
+        }
+    }
+
+    /// Writes the topology of the selected ProdotMesh to a `.prodot` text
+    /// file at `path`. Inside the project it's imported as mesh data any
+    /// ProdotMesh can use. Returns whether the file was written.
+    ///
+    #[export]
+    pub fn export_prodot(&mut self, owner: TRef<EditorPlugin>, path: String) -> bool {
+        let meshes = self.export_meshes(owner, false, false);
+        let mesh = match meshes.first() {
+            Some(mesh) => mesh,
+            None => {
+                godot_print!("[Prodot Builder]: Select a ProdotMesh to export!");
+                return false;
+            }
+        };
+
+        match prodot_export::export_prodot(&path, &mesh.mesh) {
+            Ok(()) => {
+                godot_print!("[Prodot Builder]: Exported {} to {}", mesh.name, path);
+                true
+            }
+            Err(err) => {
+                godot_print!("[Prodot Builder]: {}", err);
+                false
+            }
+        }
+    }
+
+    /// Writes the selected ProdotMesh, or every ProdotMesh in the scene, to
+    /// an STL file at `path`, as text with `ascii` and binary otherwise.
+    /// Faces are triangulated and wound to face outwards. Meshes with open
+    /// boundaries, non-manifold edges, flipped or degenerate faces are
+    /// reported and nothing is written. Returns whether the file was
+    /// written.
+    ///
+    #[export]
+    pub fn export_stl(&mut self, owner: TRef<EditorPlugin>, path: String, whole_scene: bool, apply_transform: bool, ascii: bool) -> bool {
+        let meshes = self.export_meshes(owner, whole_scene, apply_transform);
+        if meshes.is_empty() {
+            godot_print!("[Prodot Builder]: There is no ProdotMesh to export!");
+            return false;
+        }
+
+        match prodot_export::export_stl(&path, &meshes, ascii) {
+            Ok(()) => {
+                godot_print!("[Prodot Builder]: Exported {} mesh(es) to {}", meshes.len(), path);
+                true
+            }
+            Err(err) => {
+                godot_print!("[Prodot Builder]: {}", err);
+                false
+            }
+        }
+    }
+
+    /// Writes the selected ProdotMesh, or every ProdotMesh in the scene, to
+    /// a PLY file at `path`, as text with `ascii` and binary little-endian
+    /// otherwise. Faces keep their corner count, and corners keep their
+    /// normals, UVs and colors. Returns whether the file was written.
+    ///
+    #[export]
+    pub fn export_ply(&mut self, owner: TRef<EditorPlugin>, path: String, whole_scene: bool, apply_transform: bool, ascii: bool) -> bool {
+        let meshes = self.export_meshes(owner, whole_scene, apply_transform);
+        if meshes.is_empty() {
+            godot_print!("[Prodot Builder]: There is no ProdotMesh to export!");
+            return false;
+        }
+
+        match prodot_export::export_ply(&path, &meshes, ascii) {
+            Ok(()) => {
+                godot_print!("[Prodot Builder]: Exported {} mesh(es) to {}", meshes.len(), path);
+                true
+            }
+            Err(err) => {
+                godot_print!("[Prodot Builder]: {}", err);
+                false
+            }
+        }
+    }
+
+    /// Writes the mesh data of the edited scene's ProdotMesh nodes that
+    /// comes from `.prodot` files back to them, called when the editor
+    /// saves. Files whose text didn't change are left alone.
+    ///
+    #[export]
+    fn save_external_data(&mut self, owner: TRef<EditorPlugin>) {
+        let root = match self.scene_root(owner) {
+            Some(root) => unsafe { root.assume_safe() },
+            None => return,
+        };
+
+        let mut saved: Vec<String> = Vec::new();
+        for node in prodot_export::scene_prodot_meshes(root).iter() {
+            let node = unsafe { node.assume_safe() };
+            let file = node
+                .cast_instance::<ProdotMesh>()
+                .and_then(|mesh_script| {
+                    mesh_script
+                        .map(|mesh, owner: TRef<MeshInstance>| {
+                            let data = mesh.get_mesh_data(owner)?;
+                            let path = unsafe { data.assume_safe() }.path().to_string();
+                            Some((path, mesh.poly_mesh().clone()))
+                        })
+                        .ok()
+                })
+                .flatten();
+            let (path, mesh) = match file {
+                Some(file) => file,
+                None => continue,
+            };
+            if !path.ends_with(".prodot") || saved.contains(&path) {
+                continue;
+            }
+
+            let text = prodot_file::write_prodot(&mesh);
+            if prodot_import::read_text_file(&path).ok().as_deref() != Some(text.as_str()) {
+                match prodot_export::write_text_file(&path, &text) {
+                    Ok(()) => godot_print!("[Prodot Builder]: Saved {}", path),
+                    Err(err) => godot_print!("[Prodot Builder]: {}", err),
+                }
+            }
+            saved.push(path);
+        }
+    }
+
+    /// Opens the import dialog for `format`, called by the dock's import
+    /// buttons
+    ///
+    #[export]
+    pub fn open_import_dialog(&mut self, _owner: TRef<EditorPlugin>, format: i64) {
+        self.import_format = match ImportFormat::from_value(format) {
+            Some(format) => format,
+            None => {
+                godot_print!("[Prodot Builder]: Cannot import from an unknown format!");
+                return;
+            }
+        };
+
+        let dialog = unsafe { self.import_dialog.unwrap().assume_safe() };
+        dialog.clear_filters();
+        dialog.add_filter(self.import_format.filter());
+        dialog.popup_centered_ratio(0.6);
+    }
+
+    #[export]
+    fn _on_import_file_selected(&mut self, owner: TRef<EditorPlugin>, path: String) {
+        match self.import_format {
+            ImportFormat::Obj => {
+                self.import_obj(owner, path);
+            }
+            ImportFormat::Ply => {
+                self.import_ply(owner, path);
+            }
+        }
+    }
+
+    /// Adds a ProdotMesh made from the OBJ file at `path` to the edited
+    /// scene. Its faces keep their corner count, and each `usemtl` group
+    /// gets its own material slot. Returns whether the file could be read.
+    ///
+    #[export]
+    pub fn import_obj(&mut self, owner: TRef<EditorPlugin>, path: String) -> bool {
+        let import = match prodot_import::import_obj(&path) {
+            Ok(import) => import,
+            Err(err) => {
+                godot_print!("[Prodot Builder]: {}", err);
+                return false;
+            }
+        };
+
+        let face_count = import.mesh.face_count();
+        // The file's own normals are kept, flat ones are made up without them
+        let normal_mode = if import.has_normals { None } else { Some(NormalMode::Flat) };
+        let added = self.add_prodot_mesh(owner, "Import OBJ", Some(&import.name), import.mesh, normal_mode, import.materials);
+        if added.is_some() {
+            godot_print!("[Prodot Builder]: Imported {} face(s) from {}", face_count, path);
+        }
+        added.is_some()
+    }
+
+    /// Adds a ProdotMesh made from the ASCII or binary PLY file at `path`
+    /// to the edited scene. Its faces keep their corner count, and corners
+    /// get the normals, UVs and colors of the file's vertices. Returns
+    /// whether the file could be read.
+    ///
+    #[export]
+    pub fn import_ply(&mut self, owner: TRef<EditorPlugin>, path: String) -> bool {
+        let import = match prodot_import::import_ply(&path) {
+            Ok(import) => import,
+            Err(err) => {
+                godot_print!("[Prodot Builder]: {}", err);
+                return false;
+            }
+        };
+
+        let face_count = import.mesh.face_count();
+        // The file's own normals are kept, flat ones are made up without them
+        let normal_mode = if import.has_normals { None } else { Some(NormalMode::Flat) };
+        let added = self.add_prodot_mesh(owner, "Import PLY", Some(&import.name), import.mesh, normal_mode, import.materials);
+        if added.is_some() {
+            godot_print!("[Prodot Builder]: Imported {} face(s) from {}", face_count, path);
+        }
+        added.is_some()
+    }
+
+    #[export]
+    fn _on_bake_pressed(&mut self, owner: TRef<EditorPlugin>) {
+        let whole_scene = self.is_checked(self.whole_scene_check);
+        self.bake(owner, whole_scene);
+    }
+
+    /// Replaces the selected ProdotMesh, or every ProdotMesh in the scene,
+    /// by a plain MeshInstance as one undoable action. Each mesh is saved
+    /// as a `.mesh` resource next to the scene, with levels of detail and
+    /// collision as set under Prodot Builder > Bake in the project
+    /// settings. Children added to a ProdotMesh move to its replacement.
+    /// Returns whether anything was baked.
+    ///
+    #[export]
+    pub fn bake(&mut self, owner: TRef<EditorPlugin>, whole_scene: bool) -> bool {
+        let root = match self.scene_root(owner) {
+            Some(root) => unsafe { root.assume_safe() },
+            None => {
+                godot_print!("[Prodot Builder]: Open a scene to bake!");
+                return false;
+            }
+        };
+        let scene_path = root.filename().to_string();
+        if scene_path.is_empty() {
+            godot_print!("[Prodot Builder]: Save the scene before baking, the meshes are saved next to it!");
+            return false;
+        }
+
+        let nodes = if whole_scene {
+            prodot_export::scene_prodot_meshes(root)
+        } else {
+            self.selected_node.into_iter().collect()
+        };
+        let steps = prodot_bake::plan_bake(&nodes, &BakeOptions::from_project_settings());
+        if steps.is_empty() {
+            godot_print!("[Prodot Builder]: There is no ProdotMesh to bake!");
+            return false;
+        }
+
+        // The editor holds the scene root, so there's nowhere to put its baked node
+        if steps.iter().any(|step| unsafe { step.node.assume_safe() }.get_instance_id() == root.get_instance_id()) {
+            godot_print!("[Prodot Builder]: The scene root can't be baked in the editor, it's baked when exported!");
+            return false;
+        }
+        for step in steps.iter() {
+            let node_path = root.get_path_to(step.node).to_string();
+            if let Err(err) = prodot_bake::save_baked_meshes(&step.baked, &scene_path, &node_path) {
+                godot_print!("[Prodot Builder]: {}", err);
+                return false;
+            }
+        }
+
+        let undo_redo = unsafe { self.undo_redo(owner).assume_safe() };
+        undo_redo.create_action("Bake Prodot Meshes", UndoRedo::MERGE_DISABLE);
+        prodot_bake::add_bake_ops(undo_redo, &steps);
+        undo_redo.commit_action();
+
+        self.reset(owner);
+        self.selected_node = None;
+        self.selected_mesh_instance = None;
+        godot_print!("[Prodot Builder]: Baked {} mesh(es)", steps.len());
+        true
+    }
+
+    /// Changes the build mode as an undoable action, called by the mode
+    /// buttons
     ///
     /// BuildMode::Vertex
     /// BuildMode::Face
@@ -953,28 +1750,45 @@ impl ProdotBuilderPlugin {
     ///
     #[export]
     pub fn change_build_mode(&mut self, owner: TRef<EditorPlugin>, mode: i64) {
+        let previous = self.build_mode.value();
+        if mode == previous {
+            return;
+        }
+
+        let undo_redo = unsafe { self.undo_redo(owner).assume_safe() };
+        undo_redo.create_action("Change Build Mode", UndoRedo::MERGE_DISABLE);
+        undo_redo.add_do_method(owner, "set_build_mode", &[mode.to_variant()]);
+        undo_redo.add_undo_method(owner, "set_build_mode", &[previous.to_variant()]);
+        // Committing runs the do method, which switches the mode. It can't
+        // call back into the plugin while this method has it borrowed, so
+        // the mode changes once the commit runs at the end of the frame
+        unsafe { undo_redo.call_deferred("commit_action", &[]) };
+    }
+
+    /// Sets the build mode of the plugin and toggles the matching button
+    ///
+    #[export]
+    pub fn set_build_mode(&mut self, owner: TRef<EditorPlugin>, mode: i64) {
         self.build_mode = self.build_mode.set(mode);
         godot_print!("[Prodot Builder]: Switched to {:?} mode", self.build_mode);
-        match self.build_mode {
-            BuildMode::Object => {
-                // Toggle the correct button, and untoggle the other buttons
-                
-            },
-            BuildMode::Vertex => {
-                // Toggle the correct button, and untoggle the other buttons
-                
-            },
-            BuildMode::Face => {
-                // Toggle the correct button, and untoggle the other buttons
-            },
-            BuildMode::Edge => {
-                // Toggle the correct button, and untoggle the other buttons
-               
+
+        let buttons = [
+            (self.object_mode_button, BuildMode::Object),
+            (self.vertex_mode_button, BuildMode::Vertex),
+            (self.face_mode_button, BuildMode::Face),
+            (self.edge_mode_button, BuildMode::Edge),
+        ];
+        for (button, button_mode) in buttons.iter() {
+            if let Some(button) = button {
+                let button = unsafe { button.assume_safe() };
+                // Toggling the button isn't a new mode change
+                button.set_block_signals(true);
+                button.set_pressed(button_mode.value() == self.build_mode.value());
+                button.set_block_signals(false);
             }
         }
 
         self.reset(owner);
-
     }
     
     /// Resets the indices of the active index and hover index
@@ -983,68 +1797,176 @@ impl ProdotBuilderPlugin {
     #[export]
     fn reset(&mut self, _owner: TRef<EditorPlugin>) {
         self.selected_indices.clear();
+        self.vertices_drag_state.clear();
         self.hover_index = -1;
         self.hovering_gizmo_axis = Vector3::zero();
 
         if let Some(mesh_ref) = self.selected_node {
             let mesh = unsafe { mesh_ref.assume_safe() };
-            let mesh_script = mesh.cast_instance::<ProdotMesh>().unwrap();
-            mesh_script
-                .map_mut(|mesh, owner: TRef<MeshInstance>| {
-                    mesh.clear(owner);
-                })
-                .ok()
-                .unwrap();
+            if let Some(mesh_script) = mesh.cast_instance::<ProdotMesh>() {
+                mesh_script
+                    .map_mut(|mesh, owner: TRef<MeshInstance>| {
+                        mesh.clear(owner);
+                    })
+                    .ok()
+                    .unwrap();
+            }
         }
 
     }
     
     #[export]
     fn detect_gizmo(&mut self, _owner: TRef<EditorPlugin>, center: Vector3, origin: Vector3, normal: Vector3) {
-        //let vertex_pos = vertices.get(self.active_vertex_index) + mesh_pos;
-        let mut plane = Plane::new(Vector3::new(0.0, 0.0, 1.0), center.z);
-        let gizmo_dist: f32 = 0.15;
-        let box_size = 0.05;
-
-        let mut hovering_gizmo = false;
-        if let Some(proj_pos) = plane.intersects_ray(origin, normal) {
-            // X_Plane
-            if proj_pos.x > center.x - box_size + gizmo_dist &&
-                proj_pos.x < center.x + box_size + gizmo_dist &&
-                proj_pos.y > center.y - box_size &&
-                proj_pos.y < center.y + box_size {
-                self.hovering_gizmo_axis = Vector3::new(1.0, 0.0, 0.0);
-                hovering_gizmo = true;
+        self.hovering_gizmo_axis = to_vector3(
+            picking::detect_gizmo_axis(to_vec3(center), to_vec3(origin), to_vec3(normal))
+        );
+    }
+
+    fn is_checked(&self, check: Option<Ref<CheckBox, Shared>>) -> bool {
+        check.map_or(false, |check| unsafe { check.assume_safe() }.is_pressed())
+    }
+
+    fn scene_root(&self, owner: TRef<EditorPlugin>) -> Option<Ref<Node, Shared>> {
+        let editor = EditorPlugin::get_editor_interface(owner.as_ref())?;
+        unsafe { editor.assume_safe() }.get_edited_scene_root()
+    }
+
+    /// Reads the selected ProdotMesh, or every ProdotMesh in the edited
+    /// scene, for the exporters
+    ///
+    fn export_meshes(&self, owner: TRef<EditorPlugin>, whole_scene: bool, apply_transform: bool) -> Vec<ExportMesh> {
+        let nodes = if whole_scene {
+            match self.scene_root(owner) {
+                Some(root) => prodot_export::scene_prodot_meshes(unsafe { root.assume_safe() }),
+                None => Vec::new(),
             }
-            
-            // Y_Plane
-            if proj_pos.x > center.x - box_size &&
-                proj_pos.x < center.x + box_size &&
-                proj_pos.y > center.y - box_size + gizmo_dist &&
-                proj_pos.y < center.y + box_size + gizmo_dist {
-                self.hovering_gizmo_axis = Vector3::new(0.0, 1.0, 0.0);
-                hovering_gizmo = true;
+        } else {
+            self.selected_node.into_iter().collect()
+        };
+
+        nodes
+            .iter()
+            .filter_map(|node| ExportMesh::from_node(unsafe { node.assume_safe() }, apply_transform))
+            .collect()
+    }
+
+    /// Connects the create button of each primitive tab in the dock to the
+    /// plugin method named by its signal
+    ///
+    fn connect_primitive_buttons(&self, owner: TRef<EditorPlugin>) {
+        let primitives = unsafe {
+            self.dock
+                .unwrap()
+                .assume_safe()
+                .get_node("./DockVC/Primitives")
+                .expect("[Prodot Builder]: The dock has no primitive tabs!")
+                .assume_safe()
+        };
+
+        for tab in primitives.get_children().iter() {
+            let tab = match tab.try_to_object::<Node>() {
+                Some(tab) => unsafe { tab.assume_safe() },
+                None => continue,
+            };
+            for child in tab.get_children().iter() {
+                let button = match child.try_to_object::<Button>() {
+                    Some(button) => unsafe { button.assume_safe() },
+                    None => continue,
+                };
+                let signal = match button.cast_instance::<CreatePrimitiveButton>() {
+                    Some(button) => button.map(|button, _| button.signal_name()).ok().unwrap(),
+                    None => continue,
+                };
+                if signal.is_empty() {
+                    continue;
+                }
+                button
+                    .connect(signal.clone(), owner, signal.clone(), VariantArray::new_shared(), 0)
+                    .unwrap_or_else(|_| panic!("[Prodot Builder]: Error when connecting the {} button!", signal));
             }
-                                
+        }
+    }
+
+    fn undo_redo(&self, owner: TRef<EditorPlugin>) -> Ref<UndoRedo, Shared> {
+        owner
+            .get_undo_redo()
+            .expect("[Prodot Builder]: The editor has no UndoRedo!")
+    }
 
+    /// Stores where the selected vertices are when a drag starts
+    ///
+    fn capture_drag_state(&mut self, mesh: TRef<MeshInstance>) {
+        if !matches!(self.build_mode, BuildMode::Vertex) {
+            return;
         }
-        
-        plane = Plane::new(Vector3::new(1.0, 0.0, 0.0), center.x);
-        if let Some(proj_pos) = plane.intersects_ray(origin, normal) {
-            // Z_Plane
-            if proj_pos.y > center.y - box_size &&
-                proj_pos.y < center.y + box_size &&
-                proj_pos.z > center.z - box_size + gizmo_dist &&
-                proj_pos.z < center.z + box_size + gizmo_dist {
-                self.hovering_gizmo_axis = Vector3::new(0.0, 0.0, 1.0);
-                hovering_gizmo = true;
+
+        let mesh_script = mesh.cast_instance::<ProdotMesh>().unwrap();
+        for index in self.selected_indices.iter() {
+            let vertex_pos = 
+                mesh_script
+                    .map_mut(|mesh, owner: TRef<MeshInstance>| {
+                        mesh.get_vertex(owner, *index)
+                    })
+                    .ok()
+                    .unwrap();
+            if let Some(vertex_pos) = vertex_pos {
+                self.vertices_drag_state.insert(*index, vertex_pos);
+            }
+        }
+    }
+
+    /// Registers the drag that just ended as a single undo step, from the
+    /// positions captured on mouse down to the current ones
+    ///
+    fn commit_drag(&mut self, owner: TRef<EditorPlugin>, mesh: TRef<MeshInstance>) {
+        if !matches!(self.build_mode, BuildMode::Vertex) {
+            return;
+        }
+
+        let mesh_script = mesh.cast_instance::<ProdotMesh>().unwrap();
+        let mut dragged: Vec<i32> = self.vertices_drag_state.keys().copied().collect();
+        dragged.sort_unstable();
+
+        let mut indices: Vec<i32> = Vec::new();
+        let mut start_positions: Vec<Vector3> = Vec::new();
+        let mut end_positions: Vec<Vector3> = Vec::new();
+        for index in dragged {
+            let start_pos = self.vertices_drag_state[&index];
+            let vertex_pos = 
+                mesh_script
+                    .map_mut(|mesh, owner: TRef<MeshInstance>| {
+                        mesh.get_vertex(owner, index)
+                    })
+                    .ok()
+                    .unwrap();
+            // The vertex is gone, e.g. the mesh was replaced
+            let vertex_pos = match vertex_pos {
+                Some(vertex_pos) => vertex_pos,
+                None => {
+                    self.vertices_drag_state.remove(&index);
+                    continue;
+                }
+            };
+            if vertex_pos != start_pos {
+                indices.push(index);
+                start_positions.push(start_pos);
+                end_positions.push(vertex_pos);
             }
+            self.vertices_drag_state.insert(index, vertex_pos);
         }
 
-        if !hovering_gizmo {
-            self.hovering_gizmo_axis = Vector3::zero();
+        if indices.is_empty() {
+            return;
         }
 
+        // The vertices are already in place, so committing repeats the last
+        // update and then saves the mesh data and rebuilds the collision,
+        // which the drag itself skipped
+        let undo_redo = unsafe { self.undo_redo(owner).assume_safe() };
+        undo_redo.create_action("Move Vertices", UndoRedo::MERGE_DISABLE);
+        undo_redo.add_do_method(mesh, "set_vertex_positions", &[indices.to_variant(), end_positions.to_variant()]);
+        undo_redo.add_undo_method(mesh, "set_vertex_positions", &[indices.to_variant(), start_positions.to_variant()]);
+        undo_redo.commit_action();
     }
 
     
@@ -0,0 +1,422 @@
+use gdnative::api::{File, Material, MeshInstance, ProjectSettings, Spatial, SpatialMaterial};
+use gdnative::prelude::*;
+
+use prodot_core::gltf::{self, GltfMaterial, GltfNode};
+use prodot_core::math;
+use prodot_core::obj::{self, ObjMaterial, ObjObject};
+use prodot_core::ply::{self, PlyFormat, PlyObject};
+use prodot_core::poly_mesh::PolyMesh;
+use prodot_core::prodot_file;
+use prodot_core::stl::{self, StlObject};
+use crate::prodot_mesh::*;
+use crate::prodot_utils::*;
+
+/// File formats the dock can export to.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ExportFormat {
+    Obj,
+    Gltf,
+    Prodot,
+    Stl,
+    Ply,
+}
+
+impl ExportFormat {
+    pub fn value(&self) -> i64 {
+        match *self {
+            ExportFormat::Obj => 0,
+            ExportFormat::Gltf => 1,
+            ExportFormat::Prodot => 2,
+            ExportFormat::Stl => 3,
+            ExportFormat::Ply => 4,
+        }
+    }
+
+    pub fn from_value(value: i64) -> Option<ExportFormat> {
+        match value {
+            0 => Some(ExportFormat::Obj),
+            1 => Some(ExportFormat::Gltf),
+            2 => Some(ExportFormat::Prodot),
+            3 => Some(ExportFormat::Stl),
+            4 => Some(ExportFormat::Ply),
+            _ => None,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match *self {
+            ExportFormat::Obj => "obj",
+            ExportFormat::Gltf => "glb",
+            ExportFormat::Prodot => "prodot",
+            ExportFormat::Stl => "stl",
+            ExportFormat::Ply => "ply",
+        }
+    }
+
+    /// Filter for the export file dialog.
+    pub fn filter(&self) -> &'static str {
+        match *self {
+            ExportFormat::Obj => "*.obj ; Wavefront OBJ",
+            ExportFormat::Gltf => "*.glb, *.gltf ; glTF 2.0",
+            ExportFormat::Prodot => "*.prodot ; Prodot Mesh",
+            ExportFormat::Stl => "*.stl ; STL",
+            ExportFormat::Ply => "*.ply ; PLY",
+        }
+    }
+}
+
+/// Everything the exporters need from one ProdotMesh node.
+pub struct ExportMesh {
+    pub name: String,
+    pub mesh: PolyMesh,
+    /// Material of each slot, see `ProdotMesh::slot_materials`.
+    pub materials: Vec<Option<Ref<Material, Shared>>>,
+    /// The node's global transform, or identity when exporting in local
+    /// space.
+    pub transform: math::Transform,
+}
+
+impl ExportMesh {
+    /// Reads the mesh of a ProdotMesh node, or `None` if `node` isn't one.
+    pub fn from_node(node: TRef<MeshInstance>, apply_transform: bool) -> Option<ExportMesh> {
+        let mesh_script = node.cast_instance::<ProdotMesh>()?;
+        let (mesh, materials) = mesh_script
+            .map(|mesh, owner: TRef<MeshInstance>| (mesh.poly_mesh().clone(), mesh.slot_materials(owner)))
+            .ok()?;
+
+        let transform = if apply_transform {
+            to_core_transform(node.global_transform())
+        } else {
+            math::Transform::identity()
+        };
+
+        Some(ExportMesh {
+            name: node.name().to_string(),
+            mesh,
+            materials,
+            transform,
+        })
+    }
+
+    /// Name of each material slot. Materials are named after their
+    /// resource name or file, unnamed ones after the node and slot.
+    pub fn material_names(&self) -> Vec<String> {
+        self.materials
+            .iter()
+            .enumerate()
+            .map(|(slot, material)| {
+                let name = match material {
+                    Some(material) => material_name(unsafe { material.assume_safe() }),
+                    None => String::new(),
+                };
+                if name.is_empty() {
+                    format!("{}_{}", self.name, slot)
+                } else {
+                    name
+                }
+            })
+            .collect()
+    }
+}
+
+fn material_name(material: TRef<Material>) -> String {
+    let name = material.name().to_string();
+    if !name.is_empty() {
+        return name;
+    }
+    // Materials saved in their own file are named after it, the ones
+    // embedded in a scene have a path like "scene.tscn::3"
+    let path = material.path().to_string();
+    if path.contains("::") {
+        return String::new();
+    }
+    let file = file_name(&path);
+    file.rsplit_once('.').map_or(file, |(stem, _)| stem).to_string()
+}
+
+/// A node of an exported hierarchy, for formats that keep one.
+pub struct ExportNode {
+    pub name: String,
+    /// Index of the parent in the exported node list.
+    pub parent: Option<usize>,
+    /// Transform relative to the parent.
+    pub transform: math::Transform,
+    pub mesh: Option<ExportMesh>,
+}
+
+impl ExportNode {
+    /// A ProdotMesh node on its own, see `ExportMesh::from_node`.
+    pub fn from_mesh(mesh: ExportMesh) -> ExportNode {
+        ExportNode {
+            name: mesh.name.clone(),
+            parent: None,
+            transform: mesh.transform,
+            mesh: Some(ExportMesh {
+                transform: math::Transform::identity(),
+                ..mesh
+            }),
+        }
+    }
+}
+
+/// Every ProdotMesh under `root` with the spatial nodes above them, in
+/// tree order. Spatial nodes without a ProdotMesh below them are left out.
+pub fn scene_export_nodes(root: TRef<Node>) -> Vec<ExportNode> {
+    let mut nodes = Vec::new();
+    collect_export_nodes(root, None, &mut nodes);
+
+    // Parents always come before their children, so going backwards marks
+    // every ancestor of a mesh
+    let mut keep: Vec<bool> = nodes.iter().map(|node| node.mesh.is_some()).collect();
+    for index in (0..nodes.len()).rev() {
+        if let (true, Some(parent)) = (keep[index], nodes[index].parent) {
+            keep[parent] = true;
+        }
+    }
+
+    let mut new_indices = Vec::with_capacity(nodes.len());
+    let mut kept = Vec::new();
+    for (node, keep) in nodes.into_iter().zip(keep) {
+        if keep {
+            new_indices.push(Some(kept.len()));
+            kept.push(ExportNode {
+                parent: node.parent.and_then(|parent| new_indices[parent]),
+                ..node
+            });
+        } else {
+            new_indices.push(None);
+        }
+    }
+    kept
+}
+
+fn collect_export_nodes(node: TRef<Node>, parent: Option<usize>, nodes: &mut Vec<ExportNode>) {
+    // Spatial nodes under anything else aren't moved by their ancestors
+    let index = match node.cast::<Spatial>() {
+        Some(spatial) => {
+            nodes.push(ExportNode {
+                name: node.name().to_string(),
+                parent,
+                transform: to_core_transform(spatial.transform()),
+                mesh: node
+                    .cast::<MeshInstance>()
+                    .and_then(|mesh| ExportMesh::from_node(mesh, false)),
+            });
+            Some(nodes.len() - 1)
+        }
+        None => None,
+    };
+
+    for child in node.get_children().iter() {
+        if let Some(child) = child.try_to_object::<Node>() {
+            collect_export_nodes(unsafe { child.assume_safe() }, index, nodes);
+        }
+    }
+}
+
+/// Every node with a ProdotMesh script under `root`, including itself,
+/// in tree order.
+pub fn scene_prodot_meshes(root: TRef<Node>) -> Vec<Ref<MeshInstance, Shared>> {
+    let mut meshes = Vec::new();
+    if let Some(mesh) = root.cast::<MeshInstance>() {
+        if mesh.cast_instance::<ProdotMesh>().is_some() {
+            meshes.push(mesh.claim());
+        }
+    }
+
+    for child in root.get_children().iter() {
+        if let Some(child) = child.try_to_object::<Node>() {
+            meshes.extend(scene_prodot_meshes(unsafe { child.assume_safe() }));
+        }
+    }
+    meshes
+}
+
+/// Writes the meshes to an OBJ file at `path`, with their materials in an
+/// MTL file next to it.
+pub fn export_obj(path: &str, meshes: &[ExportMesh]) -> Result<(), String> {
+    let mtl_path = with_extension(path, "mtl");
+
+    let material_names: Vec<Vec<String>> = meshes.iter().map(|mesh| mesh.material_names()).collect();
+    let objects: Vec<ObjObject> = meshes
+        .iter()
+        .zip(material_names.iter())
+        .map(|(mesh, names)| ObjObject {
+            name: &mesh.name,
+            mesh: &mesh.mesh,
+            materials: names,
+            transform: mesh.transform,
+        })
+        .collect();
+
+    let mut materials = Vec::new();
+    for (mesh, names) in meshes.iter().zip(material_names.iter()) {
+        for (material, name) in mesh.materials.iter().zip(names.iter()) {
+            materials.push(obj_material(name, material.as_ref()));
+        }
+    }
+
+    write_text_file(path, &obj::write_obj(&objects, Some(file_name(&mtl_path))))?;
+    write_text_file(&mtl_path, &obj::write_mtl(&materials))
+}
+
+/// Writes the nodes to a glTF file at `path`, binary if it ends in
+/// `.glb` and JSON with an embedded buffer otherwise.
+pub fn export_gltf(path: &str, nodes: &[ExportNode]) -> Result<(), String> {
+    let materials: Vec<Vec<GltfMaterial>> = nodes
+        .iter()
+        .map(|node| match &node.mesh {
+            Some(mesh) => mesh
+                .material_names()
+                .iter()
+                .zip(mesh.materials.iter())
+                .map(|(name, material)| gltf_material(name, material.as_ref()))
+                .collect(),
+            None => Vec::new(),
+        })
+        .collect();
+    let gltf_nodes: Vec<GltfNode> = nodes
+        .iter()
+        .zip(materials.iter())
+        .map(|(node, materials)| GltfNode {
+            name: &node.name,
+            parent: node.parent,
+            transform: node.transform,
+            mesh: node.mesh.as_ref().map(|mesh| &mesh.mesh),
+            materials,
+        })
+        .collect();
+
+    if path.to_lowercase().ends_with(".glb") {
+        write_binary_file(path, gltf::write_glb(&gltf_nodes))
+    } else {
+        write_text_file(path, &gltf::write_gltf(&gltf_nodes))
+    }
+}
+
+/// Writes the topology of `mesh` to a `.prodot` text file at `path`.
+pub fn export_prodot(path: &str, mesh: &PolyMesh) -> Result<(), String> {
+    write_text_file(path, &prodot_file::write_prodot(mesh))
+}
+
+/// Writes the meshes to an STL file at `path`, as text with `ascii` and
+/// binary otherwise. Meshes that aren't closed solids are reported, and
+/// nothing is written.
+pub fn export_stl(path: &str, meshes: &[ExportMesh], ascii: bool) -> Result<(), String> {
+    let problems: Vec<String> = meshes
+        .iter()
+        .filter_map(|mesh| {
+            let report = stl::check_solid(&mesh.mesh);
+            if report.is_solid() {
+                None
+            } else {
+                Some(format!("{} has {}", mesh.name, report))
+            }
+        })
+        .collect();
+    if !problems.is_empty() {
+        return Err(format!("Can't export {} to STL: {}", path, problems.join("; ")));
+    }
+
+    let objects: Vec<StlObject> = meshes
+        .iter()
+        .map(|mesh| StlObject {
+            mesh: &mesh.mesh,
+            transform: mesh.transform,
+        })
+        .collect();
+    if ascii {
+        let name = file_name(path).rsplit_once('.').map_or(file_name(path), |(stem, _)| stem);
+        write_text_file(path, &stl::write_stl_ascii(name, &objects))
+    } else {
+        write_binary_file(path, stl::write_stl_binary(&objects))
+    }
+}
+
+/// Writes the meshes to a PLY file at `path`, as text with `ascii` and
+/// binary little-endian otherwise.
+pub fn export_ply(path: &str, meshes: &[ExportMesh], ascii: bool) -> Result<(), String> {
+    let objects: Vec<PlyObject> = meshes
+        .iter()
+        .map(|mesh| PlyObject {
+            mesh: &mesh.mesh,
+            transform: mesh.transform,
+        })
+        .collect();
+    let format = if ascii { PlyFormat::Ascii } else { PlyFormat::BinaryLittleEndian };
+    write_binary_file(path, ply::write_ply(&objects, format))
+}
+
+/// Albedo, metallic and roughness of spatial materials, the defaults for
+/// the others.
+fn gltf_material(name: &str, material: Option<&Ref<Material, Shared>>) -> GltfMaterial {
+    let mut gltf_material = GltfMaterial {
+        name: name.to_string(),
+        ..GltfMaterial::slot_default(0)
+    };
+
+    let spatial = material.and_then(|material| unsafe { material.assume_safe() }.cast::<SpatialMaterial>());
+    if let Some(spatial) = spatial {
+        gltf_material.base_color = to_core_color(spatial.albedo());
+        gltf_material.metallic = spatial.metallic() as f32;
+        gltf_material.roughness = spatial.roughness() as f32;
+    }
+
+    gltf_material
+}
+
+/// Albedo color and texture of spatial materials, white for the others.
+fn obj_material(name: &str, material: Option<&Ref<Material, Shared>>) -> ObjMaterial {
+    let mut obj_material = ObjMaterial {
+        name: name.to_string(),
+        diffuse: math::Color::white(),
+        diffuse_texture: None,
+    };
+
+    let spatial = material.and_then(|material| unsafe { material.assume_safe() }.cast::<SpatialMaterial>());
+    if let Some(spatial) = spatial {
+        obj_material.diffuse = to_core_color(spatial.albedo());
+        if let Some(texture) = spatial.texture(SpatialMaterial::TEXTURE_ALBEDO) {
+            let path = unsafe { texture.assume_safe() }.path();
+            if !path.is_empty() {
+                let path = ProjectSettings::godot_singleton().globalize_path(path);
+                obj_material.diffuse_texture = Some(path.to_string());
+            }
+        }
+    }
+
+    obj_material
+}
+
+pub fn write_text_file(path: &str, text: &str) -> Result<(), String> {
+    let file = File::new();
+    file.open(path, File::WRITE)
+        .map_err(|err| format!("Failed to open {} for writing: {:?}", path, err))?;
+    file.store_string(text);
+    file.close();
+    Ok(())
+}
+
+pub fn write_binary_file(path: &str, bytes: Vec<u8>) -> Result<(), String> {
+    let file = File::new();
+    file.open(path, File::WRITE)
+        .map_err(|err| format!("Failed to open {} for writing: {:?}", path, err))?;
+    file.store_buffer(ByteArray::from_vec(bytes));
+    file.close();
+    Ok(())
+}
+
+/// `path` with its extension replaced by `extension`, or added if it has
+/// none.
+pub fn with_extension(path: &str, extension: &str) -> String {
+    let stem = match path.rfind('.') {
+        Some(dot) if dot > path.rfind('/').map_or(0, |slash| slash + 1) => &path[..dot],
+        _ => path,
+    };
+    format!("{}.{}", stem, extension)
+}
+
+/// Last component of `path`.
+pub fn file_name(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or(path)
+}
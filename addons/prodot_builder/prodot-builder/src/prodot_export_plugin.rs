@@ -0,0 +1,79 @@
+use gdnative::api::{Directory, EditorExportPlugin, File, PackedScene, Resource, ResourceLoader, ResourceSaver};
+use gdnative::prelude::*;
+
+use crate::prodot_bake::{self, BakeOptions};
+use crate::prodot_export::{file_name, scene_prodot_meshes};
+
+/// Bakes the ProdotMesh nodes of every exported scene, so exported games
+/// only get plain MeshInstances. The scenes in the project are left as
+/// they are.
+#[derive(NativeClass)]
+#[inherit(EditorExportPlugin)]
+pub struct ProdotExportPlugin;
+
+#[methods]
+impl ProdotExportPlugin {
+    fn new(_owner: TRef<EditorExportPlugin>) -> Self {
+        ProdotExportPlugin
+    }
+
+    #[export]
+    fn _export_file(&self, owner: TRef<EditorExportPlugin>, path: String, resource_type: String, _features: StringArray) {
+        if resource_type != "PackedScene" {
+            return;
+        }
+
+        match bake_scene_file(&path) {
+            Ok(Some(bytes)) => {
+                owner.add_file(path.as_str(), bytes, false);
+                owner.skip();
+            }
+            Ok(None) => (),
+            Err(err) => godot_print!("[Prodot Builder]: {}, exporting it unbaked", err),
+        }
+    }
+}
+
+/// The scene file at `path` with its ProdotMesh nodes baked, or `None`
+/// if it has none. The meshes end up embedded in the scene.
+fn bake_scene_file(path: &str) -> Result<Option<ByteArray>, String> {
+    // Not cached, the editor keeps its own copy of the scene
+    let scene = ResourceLoader::godot_singleton()
+        .load(path, "PackedScene", true)
+        .and_then(|scene| unsafe { scene.assume_safe() }.cast::<PackedScene>().map(|scene| scene.claim()))
+        .ok_or_else(|| format!("Failed to load {}", path))?;
+    let root = unsafe { scene.assume_safe() }
+        .instance(PackedScene::GEN_EDIT_STATE_DISABLED)
+        .ok_or_else(|| format!("Failed to instance {}", path))?;
+    let root = unsafe { root.assume_safe() };
+
+    let nodes = scene_prodot_meshes(root);
+    if nodes.is_empty() {
+        unsafe { root.claim().free() };
+        return Ok(None);
+    }
+    let (root, count) = prodot_bake::bake_scene(root, &nodes, &BakeOptions::from_project_settings());
+    let root = unsafe { root.assume_safe() };
+
+    let baked = PackedScene::new();
+    let packed = baked.pack(root);
+    unsafe { root.claim().free() };
+    packed.map_err(|err| format!("Failed to pack the baked {}: {:?}", path, err))?;
+
+    // Saved with the same extension, so it's in the same format
+    let extension = file_name(path).rsplit('.').next().unwrap_or("tscn");
+    let baked_path = format!("user://prodot_baked_scene.{}", extension);
+    ResourceSaver::godot_singleton()
+        .save(baked_path.as_str(), baked.upcast::<Resource>(), 0)
+        .map_err(|err| format!("Failed to save the baked {}: {:?}", path, err))?;
+
+    let file = File::new();
+    file.open(baked_path.as_str(), File::READ)
+        .map_err(|err| format!("Failed to open {} for reading: {:?}", baked_path, err))?;
+    let bytes = file.get_buffer(file.get_len());
+    file.close();
+    let _ = Directory::new().remove(baked_path.as_str());
+
+    godot_print!("[Prodot Builder]: Baked {} mesh(es) in {}", count, path);
+    Ok(Some(bytes))
+}
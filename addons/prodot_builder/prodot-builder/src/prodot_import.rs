@@ -0,0 +1,210 @@
+use gdnative::api::{ArrayMesh, File, Material, Mesh, MeshInstance, ProjectSettings, SpatialMaterial, Texture};
+use gdnative::prelude::*;
+
+use prodot_core::obj::{self, ObjMaterial};
+use prodot_core::ply;
+use prodot_core::poly_mesh::PolyMesh;
+use prodot_core::triangles::{self, TriangleSurface};
+use crate::prodot_export::file_name;
+use crate::prodot_utils::*;
+
+/// File formats the dock can import from.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ImportFormat {
+    Obj,
+    Ply,
+}
+
+impl ImportFormat {
+    pub fn value(&self) -> i64 {
+        match *self {
+            ImportFormat::Obj => 0,
+            ImportFormat::Ply => 1,
+        }
+    }
+
+    pub fn from_value(value: i64) -> Option<ImportFormat> {
+        match value {
+            0 => Some(ImportFormat::Obj),
+            1 => Some(ImportFormat::Ply),
+            _ => None,
+        }
+    }
+
+    /// Filter for the import file dialog.
+    pub fn filter(&self) -> &'static str {
+        match *self {
+            ImportFormat::Obj => "*.obj ; Wavefront OBJ",
+            ImportFormat::Ply => "*.ply ; PLY",
+        }
+    }
+}
+
+/// A mesh read from a file, ready to become a ProdotMesh node.
+pub struct ImportMesh {
+    pub name: String,
+    pub mesh: PolyMesh,
+    /// Material of each slot, `None` where the file has none.
+    pub materials: Vec<Option<Ref<Material, Shared>>>,
+    /// Whether the corners have normals from the file, which should be
+    /// kept rather than recalculated.
+    pub has_normals: bool,
+}
+
+/// Reads the OBJ file at `path`. Slots get the matching material of the
+/// MTL files it references, if they can be read.
+pub fn import_obj(path: &str) -> Result<ImportMesh, String> {
+    let import = obj::read_obj(&read_text_file(path)?).map_err(|err| format!("Failed to read {}: {}", path, err))?;
+    if import.skipped_faces > 0 {
+        godot_print!("[Prodot Builder]: Skipped {} degenerate face(s) of {}", import.skipped_faces, path);
+    }
+
+    let directory = &path[..path.len() - file_name(path).len()];
+    let mut mtl_materials = Vec::new();
+    for mtl_file in import.mtl_files.iter() {
+        let mtl_path = format!("{}{}", directory, mtl_file);
+        let materials = read_text_file(&mtl_path)
+            .and_then(|text| obj::read_mtl(&text).map_err(|err| format!("Failed to read {}: {}", mtl_path, err)));
+        match materials {
+            Ok(materials) => mtl_materials.extend(materials),
+            Err(err) => godot_print!("[Prodot Builder]: {}", err),
+        }
+    }
+
+    let materials = import
+        .materials
+        .iter()
+        .map(|name| {
+            mtl_materials
+                .iter()
+                .find(|material| material.name == *name)
+                .map(|material| spatial_material(material, directory))
+        })
+        .collect();
+
+    let name = if import.name.is_empty() {
+        let file = file_name(path);
+        file.rsplit_once('.').map_or(file, |(stem, _)| stem).to_string()
+    } else {
+        import.name
+    };
+
+    Ok(ImportMesh {
+        name,
+        mesh: import.mesh,
+        materials,
+        has_normals: import.has_normals,
+    })
+}
+
+/// Reads the ASCII or binary PLY file at `path` into a mesh with one
+/// material slot, named after the file.
+pub fn import_ply(path: &str) -> Result<ImportMesh, String> {
+    let import = ply::read_ply(&read_binary_file(path)?).map_err(|err| format!("Failed to read {}: {}", path, err))?;
+    if import.skipped_faces > 0 {
+        godot_print!("[Prodot Builder]: Skipped {} degenerate face(s) of {}", import.skipped_faces, path);
+    }
+
+    let file = file_name(path);
+    Ok(ImportMesh {
+        name: file.rsplit_once('.').map_or(file, |(stem, _)| stem).to_string(),
+        mesh: import.mesh,
+        materials: Vec::new(),
+        has_normals: import.has_normals,
+    })
+}
+
+/// A SpatialMaterial with the color of `material`, and its texture when
+/// that's a resource of the project.
+fn spatial_material(material: &ObjMaterial, directory: &str) -> Ref<Material, Shared> {
+    let spatial = SpatialMaterial::new();
+    spatial.set_name(material.name.as_str());
+    spatial.set_albedo(to_godot_color(material.diffuse));
+    if material.diffuse.a < 1.0 {
+        spatial.set_feature(SpatialMaterial::FEATURE_TRANSPARENT, true);
+    }
+
+    if let Some(texture) = &material.diffuse_texture {
+        let texture_path = if texture.starts_with('/') || texture.contains(':') {
+            texture.clone()
+        } else {
+            format!("{}{}", directory, texture)
+        };
+        let texture_path = ProjectSettings::godot_singleton().localize_path(texture_path).to_string();
+        if texture_path.starts_with("res://") && File::new().file_exists(texture_path.as_str()) {
+            if let Some(texture) = unsafe { load_resource::<Texture>(&texture_path, "Texture") } {
+                spatial.set_texture(SpatialMaterial::TEXTURE_ALBEDO, texture);
+            }
+        }
+    }
+
+    spatial.upcast::<Material>().into_shared()
+}
+
+/// Reads the mesh of any MeshInstance, an ArrayMesh or a primitive mesh
+/// like CubeMesh, as editable topology. Each triangle surface becomes a
+/// material slot with the surface's material, and surfaces drawn as lines
+/// or points are left out.
+pub fn mesh_instance_topology(node: TRef<MeshInstance>) -> Result<ImportMesh, String> {
+    let mesh = node
+        .mesh()
+        .ok_or_else(|| format!("{} has no mesh to make editable", node.name()))?;
+    let mesh = unsafe { mesh.assume_safe() };
+    let array_mesh = mesh.cast::<ArrayMesh>();
+
+    let mut surfaces = Vec::new();
+    let mut materials = Vec::new();
+    for index in 0..mesh.get_surface_count() {
+        if let Some(array_mesh) = array_mesh {
+            if array_mesh.surface_get_primitive_type(index) != Mesh::PRIMITIVE_TRIANGLES {
+                godot_print!("[Prodot Builder]: Skipped surface {} of {}, it isn't made of triangles", index, node.name());
+                continue;
+            }
+        }
+
+        let arrays = mesh.surface_get_arrays(index);
+        let positions = arrays.get(Mesh::ARRAY_VERTEX as i32).to_vector3_array();
+        let uvs = arrays.get(Mesh::ARRAY_TEX_UV as i32).to_vector2_array();
+        let colors = arrays.get(Mesh::ARRAY_COLOR as i32).to_color_array();
+        let indices = arrays.get(Mesh::ARRAY_INDEX as i32).to_int32_array();
+        surfaces.push(TriangleSurface {
+            positions: positions.read().iter().map(|position| to_vec3(*position)).collect(),
+            uvs: uvs.read().iter().map(|uv| to_vec2(*uv)).collect(),
+            colors: colors.read().iter().map(|color| to_core_color(*color)).collect(),
+            indices: indices.read().iter().map(|index| *index as u32).collect(),
+        });
+
+        // The node's own surface materials win over the mesh's
+        materials.push(node.get_surface_material(index).or_else(|| mesh.surface_get_material(index)));
+    }
+
+    let mesh = triangles::poly_mesh_from_triangles(&surfaces);
+    if mesh.face_count() == 0 {
+        return Err(format!("{} has no triangles to make editable", node.name()));
+    }
+
+    Ok(ImportMesh {
+        name: node.name().to_string(),
+        mesh,
+        materials,
+        has_normals: false,
+    })
+}
+
+pub fn read_text_file(path: &str) -> Result<String, String> {
+    let file = File::new();
+    file.open(path, File::READ)
+        .map_err(|err| format!("Failed to open {} for reading: {:?}", path, err))?;
+    let text = file.get_as_text().to_string();
+    file.close();
+    Ok(text)
+}
+
+pub fn read_binary_file(path: &str) -> Result<Vec<u8>, String> {
+    let file = File::new();
+    file.open(path, File::READ)
+        .map_err(|err| format!("Failed to open {} for reading: {:?}", path, err))?;
+    let bytes = file.get_buffer(file.get_len()).read().to_vec();
+    file.close();
+    Ok(bytes)
+}
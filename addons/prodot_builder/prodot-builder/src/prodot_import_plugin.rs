@@ -0,0 +1,106 @@
+use gdnative::api::{EditorImportPlugin, GlobalConstants, ResourceSaver};
+use gdnative::prelude::*;
+
+use prodot_core::prodot_file;
+use crate::prodot_import::read_text_file;
+use crate::prodot_mesh_data::*;
+
+/// Imports `.prodot` text files as ProdotMeshData resources, so scenes
+/// can use them as the mesh data of a ProdotMesh. Edits are written back
+/// to the text file when the scene is saved.
+#[derive(NativeClass)]
+#[inherit(EditorImportPlugin)]
+pub struct ProdotImportPlugin;
+
+#[methods]
+impl ProdotImportPlugin {
+    fn new(_owner: TRef<EditorImportPlugin>) -> Self {
+        ProdotImportPlugin
+    }
+
+    #[export]
+    fn get_importer_name(&self, _owner: TRef<EditorImportPlugin>) -> String {
+        "prodot_builder.prodot".to_string()
+    }
+
+    #[export]
+    fn get_visible_name(&self, _owner: TRef<EditorImportPlugin>) -> String {
+        "Prodot Mesh Data".to_string()
+    }
+
+    #[export]
+    fn get_recognized_extensions(&self, _owner: TRef<EditorImportPlugin>) -> Vec<String> {
+        vec!["prodot".to_string()]
+    }
+
+    #[export]
+    fn get_save_extension(&self, _owner: TRef<EditorImportPlugin>) -> String {
+        "res".to_string()
+    }
+
+    #[export]
+    fn get_resource_type(&self, _owner: TRef<EditorImportPlugin>) -> String {
+        "Resource".to_string()
+    }
+
+    #[export]
+    fn get_preset_count(&self, _owner: TRef<EditorImportPlugin>) -> i64 {
+        1
+    }
+
+    #[export]
+    fn get_preset_name(&self, _owner: TRef<EditorImportPlugin>, _preset: i64) -> String {
+        "Default".to_string()
+    }
+
+    #[export]
+    fn get_import_options(&self, _owner: TRef<EditorImportPlugin>, _preset: i64) -> VariantArray {
+        VariantArray::new_shared()
+    }
+
+    #[export]
+    fn get_option_visibility(&self, _owner: TRef<EditorImportPlugin>, _option: String, _options: Dictionary) -> bool {
+        true
+    }
+
+    #[export]
+    fn import(
+        &self,
+        _owner: TRef<EditorImportPlugin>,
+        source_file: String,
+        save_path: String,
+        _options: Dictionary,
+        _platform_variants: VariantArray,
+        _gen_files: VariantArray,
+    ) -> i64 {
+        let text = match read_text_file(&source_file) {
+            Ok(text) => text,
+            Err(err) => {
+                godot_print!("[Prodot Builder]: {}", err);
+                return GlobalConstants::ERR_FILE_CANT_OPEN;
+            }
+        };
+        let mesh = match prodot_file::read_prodot(&text) {
+            Ok(mesh) => mesh,
+            Err(err) => {
+                godot_print!("[Prodot Builder]: Failed to read {}: {}", source_file, err);
+                return GlobalConstants::ERR_PARSE_ERROR;
+            }
+        };
+
+        let mesh_data = create_mesh_data();
+        map_mesh_data(&mesh_data, |data, data_owner| {
+            data.set_arrays(data_owner, mesh.to_arrays());
+        })
+        .expect("[Prodot Builder]: Failed to fill the imported mesh data!");
+
+        let path = format!("{}.res", save_path);
+        match ResourceSaver::godot_singleton().save(path.as_str(), mesh_data, 0) {
+            Ok(()) => GlobalConstants::OK,
+            Err(err) => {
+                godot_print!("[Prodot Builder]: Failed to save {}: {:?}", path, err);
+                GlobalConstants::ERR_FILE_CANT_WRITE
+            }
+        }
+    }
+}
@@ -1,25 +1,97 @@
-use gdnative::api::{ArrayMesh, Mesh, MeshDataTool, ImmediateGeometry, MeshInstance};
+use gdnative::api::{
+    ArrayMesh, BoxShape, CollisionShape, ConcavePolygonShape, ConvexPolygonShape, GeometryInstance, ImmediateGeometry,
+    Material, Mesh, MeshInstance, Resource, Shape, SpatialMaterial, StaticBody,
+};
 use gdnative::prelude::*;
+use gdnative::GodotObject;
 
 use std::collections::HashMap;
 
-//use crate::prodot_utils::*;
-#[derive(Copy, Clone, FromVariant, ToVariant)]
-pub struct Face {
-    pub tris_one: Vector3,
-    pub tris_two: Vector3,
-}
+use prodot_core::collision::{self, CollisionMode};
+use prodot_core::normals::{self, NormalMode};
+use prodot_core::poly_mesh::*;
+use prodot_core::render::{self, MeshSurfaces, SurfaceArrays};
+use prodot_core::uv::{self, UvProjection, UvSettings};
+use prodot_core::vertex_buffer::VertexBuffer;
+use crate::prodot_mesh_data::*;
+use crate::prodot_utils::*;
+
+/// Format of the committed surfaces: uncompressed, so that they match the
+/// layout of `VertexBuffer`.
+const SURFACE_FORMAT: i64 = Mesh::ARRAY_FORMAT_VERTEX
+    | Mesh::ARRAY_FORMAT_NORMAL
+    | Mesh::ARRAY_FORMAT_TANGENT
+    | Mesh::ARRAY_FORMAT_COLOR
+    | Mesh::ARRAY_FORMAT_TEX_UV
+    | Mesh::ARRAY_FORMAT_INDEX;
+
+/// Name of the ImmediateGeometry child the overlays are drawn with.
+pub const IMMEDIATE_GEOMETRY_NAME: &str = "ProdotIG";
+
+/// Name of the StaticBody child that holds the generated collision.
+pub const COLLISION_BODY_NAME: &str = "ProdotCollision";
 
 #[derive(NativeClass)]
 #[inherit(MeshInstance)]
 pub struct ProdotMesh {
     imm_geo: Option<Ref<ImmediateGeometry, Shared>>,
-    vertices: TypedArray::<Vector3>,
+    // Vertices, edges, faces and face loops of the editable mesh
+    mesh: PolyMesh,
+    // ProdotMeshData resource the topology is saved in, possibly shared
+    // with other ProdotMesh nodes
+    #[property(before_set = "Self::disconnect_mesh_data", after_set = "Self::connect_mesh_data")]
+    mesh_data: Option<Ref<Resource, Shared>>,
+    // Revision of mesh_data that `mesh` was last synced with, -1 if never
+    data_revision: i64,
+    // How normals are recalculated after edits: 0 flat, 1 smooth,
+    // 2 auto-smooth (smooth up to smooth_angle, hard above it)
+    #[property(default = 0, after_set = "Self::refresh_shading")]
+    normal_mode: i64,
+    // Auto-smooth threshold in degrees
+    #[property(default = 30.0, after_set = "Self::refresh_shading")]
+    smooth_angle: f32,
+    // How UVs are generated after edits: 0 manual (kept as they are),
+    // 1 box, 2 planar, 3 world-space box
+    #[property(default = 0, after_set = "Self::refresh_shading")]
+    uv_mode: i64,
+    // Texture pixels per world unit
+    #[property(default = 64.0, after_set = "Self::refresh_shading")]
+    texel_density: f32,
+    // Size in pixels of the texture the density is meant for
+    #[property(default = 64.0, after_set = "Self::refresh_shading")]
+    texture_size: f32,
+    #[property(after_set = "Self::refresh_shading")]
+    uv_offset: Vector2,
+    // Degrees
+    #[property(default = 0.0, after_set = "Self::refresh_shading")]
+    uv_rotation: f32,
+    #[property(after_set = "Self::refresh_shading")]
+    uv_scale: Vector2,
+    // Material of each face material slot. Slots without an entry keep
+    // the material of their surface across rebuilds
+    #[property(after_set = "Self::refresh_materials")]
+    materials: VariantArray,
+    // Collision kept on a ProdotCollision StaticBody child: 0 none,
+    // 1 trimesh, 2 convex hull, 3 one box per connected part
+    #[property(default = 0, after_set = "Self::refresh_collision")]
+    collision_mode: i64,
+    // Surfaces of the committed ArrayMesh, one per material slot in use,
+    // and the loop each render vertex was built from
+    surfaces: MeshSurfaces,
+    // Vertex buffer of each surface. Edits update them and mark the
+    // changed ranges, which `_process` uploads once per frame
+    buffers: Vec<VertexBuffer>,
+    // A drag moved vertices since the topology was last copied into
+    // mesh_data. It is copied once the drag is committed
+    needs_store: bool,
+    // The topology changed since the collision was last generated
+    needs_collision: bool,
+    // An edit split or merged render vertices, so the surfaces have to be
+    // rebuilt. `_process` does it once per frame
+    needs_rebuild: bool,
     _uvs: TypedArray::<Vector2>,
     _normals: TypedArray::<Vector3>,
     _indices: TypedArray::<i32>,
-    // Holds the indices of the 3 vertices that make up a face
-    faces: Vec::<Face>,
     normal_color: Color,
     hover_color: Color,
     selected_color: Color,
@@ -40,11 +112,27 @@ impl ProdotMesh {
     pub fn new(_owner: TRef<MeshInstance>) -> Self {
         ProdotMesh {
             imm_geo: None,
-            vertices: TypedArray::<Vector3>::new(),
+            mesh: PolyMesh::new(),
+            mesh_data: None,
+            data_revision: -1,
+            normal_mode: 0,
+            smooth_angle: 30.0,
+            uv_mode: 0,
+            texel_density: 64.0,
+            texture_size: 64.0,
+            uv_offset: Vector2::zero(),
+            uv_rotation: 0.0,
+            uv_scale: Vector2::new(1.0, 1.0),
+            materials: VariantArray::new_shared(),
+            collision_mode: 0,
+            surfaces: MeshSurfaces::default(),
+            buffers: Vec::new(),
+            needs_store: false,
+            needs_collision: false,
+            needs_rebuild: false,
             _uvs: TypedArray::<Vector2>::new(),
             _normals: TypedArray::<Vector3>::new(),
             _indices: TypedArray::<i32>::new(),
-            faces: Vec::new(),
             normal_color: Color::rgba(0.4, 0.4, 0.4, 0.8),
             hover_color: Color::rgba(0.7, 0.7, 0.7, 1.0),
             selected_color: Color::rgba(0.2, 0.2, 0.2, 1.0),
@@ -71,25 +159,67 @@ impl ProdotMesh {
     fn _enter_tree(&mut self, owner: TRef<MeshInstance>) {
         godot_print!("Prodot Mesh created!");
         
-        self.imm_geo = unsafe {
-            Some( 
-                owner.
-                    get_node("./ProdotIG")
+        self.immediate_geometry(owner);
+
+        // Restore the editable topology saved with the scene
+        self.sync_mesh_data(owner);
+    }
+
+    #[export]
+    fn _exit_tree(&mut self, owner: TRef<MeshInstance>) {
+        self.flush_updates(owner);
+        // Keep the vertices of a drag that never got committed
+        if self.needs_store {
+            self.store_mesh();
+        }
+    }
+
+    #[export]
+    fn _process(&mut self, owner: TRef<MeshInstance>, _delta: f64) {
+        self.flush_updates(owner);
+    }
+    
+    /// The ImmediateGeometry the overlays are drawn with. ProdotMesh
+    /// scenes come with one, and meshes made editable in place get their
+    /// own, which isn't saved with the scene.
+    fn immediate_geometry(&mut self, owner: TRef<MeshInstance>) -> Ref<ImmediateGeometry, Shared> {
+        if let Some(geo) = self.imm_geo {
+            return geo;
+        }
+
+        let geo = if owner.has_node(IMMEDIATE_GEOMETRY_NAME) {
+            unsafe {
+                owner
+                    .get_node(IMMEDIATE_GEOMETRY_NAME)
                     .unwrap()
                     .assume_safe()
                     .cast::<ImmediateGeometry>()
-                    .unwrap()
+                    .expect("[Prodot Mesh]: ProdotIG is not an ImmediateGeometry!")
                     .claim()
-            )
+            }
+        } else {
+            // Same settings as the ProdotIG of prodot_mesh.tscn
+            let material = SpatialMaterial::new();
+            material.set_feature(SpatialMaterial::FEATURE_TRANSPARENT, true);
+            material.set_flag(SpatialMaterial::FLAG_UNSHADED, true);
+            material.set_flag(SpatialMaterial::FLAG_DISABLE_DEPTH_TEST, true);
+            material.set_flag(SpatialMaterial::FLAG_ALBEDO_FROM_VERTEX_COLOR, true);
+            material.set_flag(SpatialMaterial::FLAG_SRGB_VERTEX_COLOR, true);
+            material.set_cull_mode(SpatialMaterial::CULL_DISABLED);
+
+            let geo = ImmediateGeometry::new();
+            geo.set_name(IMMEDIATE_GEOMETRY_NAME);
+            geo.set_material_override(material);
+            geo.set_cast_shadows_setting(GeometryInstance::SHADOW_CASTING_SETTING_OFF);
+            let geo = geo.into_shared();
+            owner.add_child(geo, false);
+            geo
         };
 
-
-
+        self.imm_geo = Some(geo);
+        geo
     }
 
-    #[export]
-    fn _exit_tree(&self, _owner: TRef<MeshInstance>) {}
-    
     #[export]
     pub fn clear(&mut self, _owner: TRef<MeshInstance>) {
         if let Some(geo_ref) = self.imm_geo {
@@ -98,6 +228,17 @@ impl ProdotMesh {
         }
     }
 
+    /// Removes and frees the ImmediateGeometry, so undoing Make Editable
+    /// doesn't leave it on the plain MeshInstance.
+    #[export]
+    pub fn remove_overlay(&mut self, owner: TRef<MeshInstance>) {
+        if let Some(geo_ref) = self.imm_geo.take() {
+            let geo = unsafe { geo_ref.assume_safe() };
+            owner.remove_child(geo);
+            geo.queue_free();
+        }
+    }
+
     #[export]
     fn draw_gizmo_vertex(&mut self, owner: TRef<MeshInstance>, indices: Vec<i32>, hovering_gizmo_axis: Vector3) {
         // X plane
@@ -109,7 +250,7 @@ impl ProdotMesh {
         let half_depth = 0.05;
         let half_length = 0.05;
         
-        let geo = unsafe { self.imm_geo.unwrap().assume_safe() };
+        let geo = unsafe { self.immediate_geometry(owner).assume_safe() };
 
         if hovering_gizmo_axis == Vector3::new(1.0, 0.0, 0.0) {
             geo.set_color(self.handle_x_color_hover);
@@ -184,8 +325,8 @@ impl ProdotMesh {
 
     #[export]
     pub fn draw_vertices(&mut self, owner: TRef<MeshInstance>, indices: Vec<i32>, hover_index: i32, hovering_gizmo_axis: Vector3) {
-        if !self.vertices.is_empty() {
-            let geo = unsafe { self.imm_geo.unwrap().assume_safe() };
+        if !self.mesh.is_empty() {
+            let geo = unsafe { self.immediate_geometry(owner).assume_safe() };
             geo.clear();
             
             geo.begin(Mesh::PRIMITIVE_TRIANGLES, Null::null());
@@ -196,11 +337,11 @@ impl ProdotMesh {
             // Standard non-selected non-hovered
             geo.set_color(self.normal_color);
 
-            for i in 0..self.vertices.len() {
-                let vertex = self.vertices.get(i);
+            for i in 0..self.mesh.vertex_count() {
+                let vertex = to_vector3(self.mesh.position(i));
                 if indices.contains(&(i as i32)) {
                     geo.set_color(self.selected_color);
-                }else if hover_index == i {
+                }else if hover_index == i as i32 {
                     geo.set_color(self.hover_color);
                 }else {
                     geo.set_color(self.normal_color);
@@ -315,7 +456,7 @@ impl ProdotMesh {
         let half_depth = 0.05;
         let half_length = 0.05;
         
-        let geo = unsafe { self.imm_geo.unwrap().assume_safe() };
+        let geo = unsafe { self.immediate_geometry(owner).assume_safe() };
 
         if hovering_gizmo_axis == Vector3::new(1.0, 0.0, 0.0) {
             geo.set_color(self.handle_x_color_hover);
@@ -393,8 +534,8 @@ impl ProdotMesh {
 
     #[export]
     pub fn draw_faces(&mut self, owner: TRef<MeshInstance>, indices: Vec<i32>, hover_index: i32, hovering_gizmo_axis: Vector3) {
-        if !self.faces.is_empty() {
-            let geo = unsafe { self.imm_geo.unwrap().assume_safe() };
+        if self.mesh.face_count() > 0 {
+            let geo = unsafe { self.immediate_geometry(owner).assume_safe() };
             geo.clear();
             
             geo.begin(Mesh::PRIMITIVE_TRIANGLES, Null::null());
@@ -404,8 +545,7 @@ impl ProdotMesh {
             
             //let outline_thickness = 0.005;
             let outline_thickness = 0.05;
-            for i in 0..self.faces.len() {
-                let face = self.faces[i];
+            for i in 0..self.mesh.face_count() {
                 let mut draw: bool = false;
                 if indices.contains(&(i as i32)){
                     geo.set_color(self.face_selected_color);
@@ -417,32 +557,18 @@ impl ProdotMesh {
                     geo.set_color(self.face_outline_color);
                 }
 
-                let vertex_one = self.vertices.get(face.tris_one.x as i32);
-                let vertex_two = self.vertices.get(face.tris_one.y as i32);
-                let vertex_three  = self.vertices.get(face.tris_one.z as i32);
-
-                let vertex_four = self.vertices.get(face.tris_two.x as i32);
-                let vertex_five = self.vertices.get(face.tris_two.y as i32);
-                let vertex_six  = self.vertices.get(face.tris_two.z as i32);
-
                 if draw {
-                    geo.add_vertex(vertex_one);
-                    geo.add_vertex(vertex_two);
-                    geo.add_vertex(vertex_three);
-                    
-                    geo.add_vertex(vertex_four);
-                    geo.add_vertex(vertex_five);
-                    geo.add_vertex(vertex_six);
+                    for triangle in self.mesh.face_triangles(i) {
+                        geo.add_vertex(to_vector3(self.mesh.position(triangle[0])));
+                        geo.add_vertex(to_vector3(self.mesh.position(triangle[1])));
+                        geo.add_vertex(to_vector3(self.mesh.position(triangle[2])));
+                    }
                 }
                 
                 //self.draw_edge_lines(owner, vertex_one, vertex_two, vertex_three, outline_thickness);
                                
                 // Draw center cube
-                let center_pos = Vector3::new(
-                    (vertex_one.x + vertex_two.x + vertex_three.x + vertex_four.x + vertex_five.x + vertex_six.x) / 6.0,
-                    (vertex_one.y + vertex_two.y + vertex_three.y + vertex_four.y + vertex_five.y + vertex_six.y) / 6.0,
-                    (vertex_one.z + vertex_two.z + vertex_three.z + vertex_four.z + vertex_five.z + vertex_six.z) / 6.0,
-                );
+                let center_pos = to_vector3(self.mesh.face_center(i));
                 
                 geo.set_color(self.face_outline_color);
 
@@ -503,8 +629,8 @@ impl ProdotMesh {
     }
 
     #[export]
-    fn draw_edge_lines(&mut self, _owner: TRef<MeshInstance>, vertex_one: Vector3, vertex_two: Vector3, vertex_three: Vector3, outline_thickness: f32) {
-        let geo = unsafe { self.imm_geo.unwrap().assume_safe() };
+    fn draw_edge_lines(&mut self, owner: TRef<MeshInstance>, vertex_one: Vector3, vertex_two: Vector3, vertex_three: Vector3, outline_thickness: f32) {
+        let geo = unsafe { self.immediate_geometry(owner).assume_safe() };
         // V1 -> V2
         
         // Front
@@ -701,149 +827,724 @@ impl ProdotMesh {
         geo.add_vertex(Vector3::new(vertex_three.x - outline_thickness, vertex_three.y, vertex_three.z + outline_thickness));
     }
 
+    pub fn poly_mesh(&self) -> &PolyMesh {
+        &self.mesh
+    }
+
+    /// Replaces the topology, rebuilds the ArrayMesh from it and saves it
+    /// to the mesh data.
+    pub fn set_poly_mesh(&mut self, owner: TRef<MeshInstance>, mesh: PolyMesh) {
+        self.mesh = mesh;
+        self.apply_shading(owner);
+        self.commit_mesh(owner);
+        self.store_mesh();
+    }
+
+    /// Same as `set_poly_mesh`, but the corner normals `mesh` came with
+    /// are kept instead of recalculated, e.g. the ones of an imported
+    /// file. They stay until the mesh is edited or reshaded.
+    pub fn set_poly_mesh_keep_normals(&mut self, owner: TRef<MeshInstance>, mesh: PolyMesh) {
+        self.mesh = mesh;
+        self.mesh.recalculate_tangents();
+        self.commit_mesh(owner);
+        self.store_mesh();
+    }
+
+    /// Shades the mesh with `mode` from now on, as if the normal mode and
+    /// smooth angle were set in the inspector.
+    pub fn set_normal_mode(&mut self, owner: TRef<MeshInstance>, mode: NormalMode) {
+        match mode {
+            NormalMode::Flat => self.normal_mode = 0,
+            NormalMode::Smooth => self.normal_mode = 1,
+            NormalMode::AutoSmooth { angle } => {
+                self.normal_mode = 2;
+                self.smooth_angle = angle.to_degrees();
+            },
+        }
+        self.refresh_shading(owner);
+    }
+
+    fn get_normal_mode(&self) -> NormalMode {
+        match self.normal_mode {
+            0 => NormalMode::Flat,
+            1 => NormalMode::Smooth,
+            2 => NormalMode::AutoSmooth { angle: self.smooth_angle.to_radians() },
+            _ => {
+                godot_print!("[Prodot Mesh]: Invalid normal mode, using flat normals!");
+                NormalMode::Flat
+            },
+        }
+    }
+
+    /// UV settings of the current uv_mode, or `None` for manual UVs.
+    fn get_uv_settings(&self) -> Option<UvSettings> {
+        let projection = match self.uv_mode {
+            0 => return None,
+            1 => UvProjection::Box,
+            2 => UvProjection::Planar,
+            3 => UvProjection::World,
+            _ => {
+                godot_print!("[Prodot Mesh]: Invalid UV mode, keeping the current UVs!");
+                return None;
+            },
+        };
+
+        Some(UvSettings {
+            projection,
+            texel_density: self.texel_density,
+            texture_size: self.texture_size,
+            offset: to_vec2(self.uv_offset),
+            rotation: self.uv_rotation.to_radians(),
+            scale: to_vec2(self.uv_scale),
+        })
+    }
+
+    /// Reprojects the UVs if a UV mode is set, then recalculates the corner
+    /// normals with the current normal mode and the tangents that depend
+    /// on both.
+    fn apply_shading(&mut self, owner: TRef<MeshInstance>) {
+        let faces: Vec<FaceId> = (0..self.mesh.face_count()).collect();
+        let vertices: Vec<VertexId> = (0..self.mesh.vertex_count()).collect();
+        self.apply_local_shading(owner, &faces, &vertices);
+    }
+
+    /// Same as `apply_shading`, limited to the UVs of `faces` and the
+    /// normals and tangents of the corners on `vertices`.
+    fn apply_local_shading(&mut self, owner: TRef<MeshInstance>, faces: &[FaceId], vertices: &[VertexId]) {
+        if let Some(settings) = self.get_uv_settings() {
+            let transform = owner.global_transform();
+            uv::project_face_uvs(&mut self.mesh, &settings, faces, |position| {
+                to_vec3(transform.basis.xform(to_vector3(position)) + transform.origin)
+            });
+        }
+        normals::recalculate_vertex_normals(&mut self.mesh, self.get_normal_mode(), vertices);
+        self.mesh.recalculate_vertex_tangents(vertices);
+    }
+
+    /// Re-shades the mesh after a normal or UV setting changed. Does
+    /// nothing while the scene is loading, the stored normals and UVs are
+    /// used then.
+    fn refresh_shading(&mut self, owner: TRef<MeshInstance>) {
+        if !owner.is_inside_tree() || self.mesh.is_empty() {
+            return;
+        }
+
+        self.apply_shading(owner);
+        self.commit_mesh(owner);
+        self.store_mesh();
+    }
+
     #[export]
-    pub fn get_vertices(&mut self, _owner: TRef<MeshInstance>) -> TypedArray<Vector3> {
-        self.vertices.clone()
+    pub fn get_mesh_data(&self, _owner: TRef<MeshInstance>) -> Option<Ref<Resource, Shared>> {
+        self.mesh_data.clone()
+    }
+
+    #[export]
+    pub fn set_mesh_data(&mut self, owner: TRef<MeshInstance>, mesh_data: Option<Ref<Resource, Shared>>) {
+        // An uncommitted drag belongs to the data being replaced
+        if self.needs_store {
+            self.store_mesh();
+        }
+        self.disconnect_mesh_data(owner);
+        self.mesh_data = mesh_data;
+        self.connect_mesh_data(owner);
+    }
+
+    /// Gives this node its own copy of the mesh data, so edits no longer
+    /// affect the other nodes that shared it.
+    #[export]
+    pub fn make_unique(&mut self, owner: TRef<MeshInstance>) {
+        if let Some(data) = self.mesh_data.clone() {
+            let unique = unsafe { data.assume_safe().duplicate(false) };
+            self.set_mesh_data(owner, unique);
+        }
+    }
+
+    fn connect_mesh_data(&mut self, owner: TRef<MeshInstance>) {
+        if let Some(data) = self.mesh_data.clone() {
+            let data = unsafe { data.assume_safe() };
+            if !data.is_connected("changed", owner, "_on_mesh_data_changed") {
+                data.connect("changed", owner, "_on_mesh_data_changed", VariantArray::new_shared(), 0)
+                    .expect("[Prodot Mesh]: Error when connecting to the mesh data!");
+            }
+        }
+
+        self.data_revision = -1;
+        self.sync_mesh_data(owner);
+    }
+
+    fn disconnect_mesh_data(&mut self, owner: TRef<MeshInstance>) {
+        if let Some(data) = self.mesh_data.clone() {
+            let data = unsafe { data.assume_safe() };
+            if data.is_connected("changed", owner, "_on_mesh_data_changed") {
+                data.disconnect("changed", owner, "_on_mesh_data_changed");
+            }
+        }
+    }
+
+    #[export]
+    fn _on_mesh_data_changed(&mut self, owner: TRef<MeshInstance>) {
+        self.sync_mesh_data(owner);
+    }
+
+    /// Reloads the topology if another node edited the shared mesh data.
+    /// The ArrayMesh saved with the scene is kept on the first sync if it
+    /// still matches the topology, and rebuilt on every later one.
+    fn sync_mesh_data(&mut self, owner: TRef<MeshInstance>) {
+        let data = match self.mesh_data.clone() {
+            Some(data) => data,
+            None => return,
+        };
+
+        let synced = map_mesh_data(&data, |data, owner| (data.get_revision(owner), data.arrays()));
+        let (revision, arrays) = match synced {
+            Some(synced) => synced,
+            None => {
+                godot_print!("[Prodot Mesh]: mesh_data is not a ProdotMeshData resource!");
+                return;
+            }
+        };
+
+        if revision == self.data_revision {
+            return;
+        }
+        let first_sync = self.data_revision == -1;
+
+        match PolyMesh::from_arrays(&arrays) {
+            Ok(mesh) => self.mesh = mesh,
+            Err(err) => {
+                godot_print!("[Prodot Mesh]: Failed to restore the mesh data: {}", err);
+                return;
+            }
+        }
+        self.data_revision = revision;
+
+        if self.mesh.is_empty() {
+            return;
+        }
+
+        if first_sync {
+            self.surfaces = render::build_surfaces(&self.mesh);
+            if self.saved_mesh_matches(owner) {
+                self.buffers = self.surfaces.surfaces.iter().map(VertexBuffer::new).collect();
+                return;
+            }
+        }
+        self.commit_mesh(owner);
+    }
+
+    /// Whether the ArrayMesh saved with the scene has the surfaces, format
+    /// and render vertices the topology builds, which is what edits expect.
+    fn saved_mesh_matches(&self, owner: TRef<MeshInstance>) -> bool {
+        let mesh_array = match self.array_mesh(owner) {
+            Some(mesh_array) => mesh_array,
+            None => return false,
+        };
+        let mesh_array = unsafe { mesh_array.assume_safe() };
+
+        mesh_array.get_surface_count() as usize == self.surfaces.surfaces.len()
+            && self.surfaces.surfaces.iter().enumerate().all(|(index, surface)| {
+                mesh_array.surface_get_format(index as i64) == SURFACE_FORMAT
+                    && mesh_array.surface_get_array_len(index as i64) as usize == surface.vertex_count()
+            })
+    }
+
+    fn array_mesh(&self, owner: TRef<MeshInstance>) -> Option<Ref<ArrayMesh, Shared>> {
+        let mesh_ref = owner.mesh()?;
+        let mesh = unsafe { mesh_ref.assume_safe() };
+        mesh.cast::<ArrayMesh>().map(|mesh_array| mesh_array.claim())
+    }
+
+    /// Material set for `slot` in the materials list, if any.
+    fn slot_material(&self, slot: usize) -> Option<Ref<Material, Shared>> {
+        if slot as i32 >= self.materials.len() {
+            return None;
+        }
+        self.materials.get(slot as i32).try_to_object::<Material>()
+    }
+
+    /// Material of every slot the faces use: the one in the materials
+    /// list, or else the one its surface has.
+    pub fn slot_materials(&self, owner: TRef<MeshInstance>) -> Vec<Option<Ref<Material, Shared>>> {
+        let mut surface_materials = self.surface_materials(owner);
+        (0..self.mesh.material_count())
+            .map(|slot| self.slot_material(slot).or_else(|| surface_materials.remove(&slot)))
+            .collect()
+    }
+
+    /// Materials of the current surfaces, by material slot.
+    fn surface_materials(&self, owner: TRef<MeshInstance>) -> HashMap<usize, Ref<Material, Shared>> {
+        let mut materials = HashMap::new();
+        if let Some(mesh_array) = self.array_mesh(owner) {
+            let mesh_array = unsafe { mesh_array.assume_safe() };
+            for (index, surface) in self.surfaces.surfaces.iter().enumerate() {
+                if index as i64 >= mesh_array.get_surface_count() {
+                    break;
+                }
+                if let Some(material) = mesh_array.surface_get_material(index as i64) {
+                    materials.insert(surface.material, material);
+                }
+            }
+        }
+        materials
+    }
+
+    /// Applies the materials list to the current surfaces.
+    fn refresh_materials(&mut self, owner: TRef<MeshInstance>) {
+        if !owner.is_inside_tree() {
+            return;
+        }
+
+        if let Some(mesh_array) = self.array_mesh(owner) {
+            let mesh_array = unsafe { mesh_array.assume_safe() };
+            for (index, surface) in self.surfaces.surfaces.iter().enumerate() {
+                if let Some(material) = self.slot_material(surface.material) {
+                    if (index as i64) < mesh_array.get_surface_count() {
+                        mesh_array.surface_set_material(index as i64, material);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Uploads the vertex ranges edited since the last frame, so a drag
+    /// costs one upload of what it moved per frame, or rebuilds the
+    /// surfaces once if an edit split render vertices. Regenerates the
+    /// collision after the ArrayMesh was rebuilt outside a drag.
+    fn flush_updates(&mut self, owner: TRef<MeshInstance>) {
+        if self.needs_rebuild {
+            let surfaces = render::build_surfaces(&self.mesh);
+            self.commit_surfaces(owner, surfaces);
+        } else if self.buffers.iter().any(|buffer| buffer.is_dirty()) {
+            if let Some(mesh_array) = self.array_mesh(owner) {
+                let mesh_array = unsafe { mesh_array.assume_safe() };
+                for (index, buffer) in self.buffers.iter_mut().enumerate() {
+                    for (offset, bytes) in buffer.take_dirty() {
+                        mesh_array.surface_update_region(index as i64, offset as i64, ByteArray::from_slice(bytes));
+                    }
+                }
+            }
+        }
+
+        if self.needs_collision {
+            self.update_collision(owner);
+        }
+    }
+
+    /// Copies the topology into the mesh data, which notifies every node
+    /// sharing it.
+    fn store_mesh(&mut self) {
+        self.needs_store = false;
+        if let Some(data) = self.mesh_data.clone() {
+            let arrays = self.mesh.to_arrays();
+            if let Some(revision) = map_mesh_data(&data, |data, owner| data.set_arrays(owner, arrays)) {
+                self.data_revision = revision;
+            }
+        }
+    }
+
+    /// Replaces the ArrayMesh with one built from the topology.
+    fn commit_mesh(&mut self, owner: TRef<MeshInstance>) {
+        self.needs_collision = true;
+        let surfaces = render::build_surfaces(&self.mesh);
+        self.commit_surfaces(owner, surfaces);
+    }
+
+    /// Replaces the ArrayMesh with one made of the given render vertices,
+    /// one surface per material slot. Face corners only share a render
+    /// vertex when their normals and UVs match, so hard edges and seams are
+    /// split. Slots without an entry in the materials list keep the
+    /// material their old surface had.
+    fn commit_surfaces(&mut self, owner: TRef<MeshInstance>, surfaces: MeshSurfaces) {
+        self.needs_rebuild = false;
+        let mut kept_materials = self.surface_materials(owner);
+        self.surfaces = surfaces;
+        self.buffers = self.surfaces.surfaces.iter().map(VertexBuffer::new).collect();
+
+        let slot_count = self.surfaces.surfaces.iter().map(|surface| surface.material + 1).max().unwrap_or(0);
+        let materials: Vec<Option<Ref<Material, Shared>>> = (0..slot_count)
+            .map(|slot| self.slot_material(slot).or_else(|| kept_materials.remove(&slot)))
+            .collect();
+        owner.set_mesh(surfaces_mesh(&self.surfaces.surfaces, &materials));
+    }
+
+    fn get_collision_mode(&self) -> CollisionMode {
+        match self.collision_mode {
+            1 => CollisionMode::Trimesh,
+            2 => CollisionMode::Convex,
+            3 => CollisionMode::Boxes,
+            _ => CollisionMode::None,
+        }
+    }
+
+    /// Regenerates the collision after the mode changed in the inspector.
+    /// Scenes being loaded already have theirs saved.
+    fn refresh_collision(&mut self, owner: TRef<MeshInstance>) {
+        if owner.is_inside_tree() {
+            self.update_collision(owner);
+        }
+    }
+
+    /// Rebuilds the collision shapes from the topology. The body, shape
+    /// nodes and shape resources already there are reused, so a drag
+    /// doesn't create new nodes every frame.
+    fn update_collision(&mut self, owner: TRef<MeshInstance>) {
+        self.needs_collision = false;
+        let mode = self.get_collision_mode();
+        let body = match collision_body(owner, mode != CollisionMode::None) {
+            Some(body) => unsafe { body.assume_safe() },
+            None => return,
+        };
+
+        let shape_count = match mode {
+            CollisionMode::None => {
+                owner.remove_child(body);
+                body.queue_free();
+                return;
+            }
+            CollisionMode::Trimesh => {
+                let shape_node = collision_shape(owner, body, 0);
+                shape_node.set_transform(Transform::translate(Vector3::zero()));
+                let shape = node_shape(shape_node, ConcavePolygonShape::new);
+                let faces: TypedArray<Vector3> = collision::trimesh_faces(&self.mesh).into_iter().map(to_vector3).collect();
+                unsafe { shape.assume_safe() }.set_faces(faces);
+                1
+            }
+            CollisionMode::Convex => {
+                let shape_node = collision_shape(owner, body, 0);
+                shape_node.set_transform(Transform::translate(Vector3::zero()));
+                let shape = node_shape(shape_node, ConvexPolygonShape::new);
+                let points: TypedArray<Vector3> = collision::convex_points(&self.mesh).into_iter().map(to_vector3).collect();
+                unsafe { shape.assume_safe() }.set_points(points);
+                1
+            }
+            CollisionMode::Boxes => {
+                let boxes = collision::collision_boxes(&self.mesh);
+                for (index, collision_box) in boxes.iter().enumerate() {
+                    let shape_node = collision_shape(owner, body, index as i64);
+                    shape_node.set_transform(Transform::translate(to_vector3(collision_box.center)));
+                    let shape = node_shape(shape_node, BoxShape::new);
+                    unsafe { shape.assume_safe() }.set_extents(to_vector3(collision_box.extents));
+                }
+                boxes.len() as i64
+            }
+        };
+
+        // Drop the shapes left over from another mode or part count
+        for index in (shape_count..body.get_child_count()).rev() {
+            if let Some(child) = body.get_child(index) {
+                let child = unsafe { child.assume_safe() };
+                body.remove_child(child);
+                child.queue_free();
+            }
+        }
     }
 
     #[export]
-    pub fn get_vertex(&mut self, _owner: TRef<MeshInstance>, index: i32) -> Vector3 {
-        self.vertices.get(index)
+    pub fn get_vertices(&mut self, _owner: TRef<MeshInstance>) -> TypedArray<Vector3> {
+        self.mesh.positions().map(to_vector3).collect()
     }
 
+    /// Position of vertex `index`, or nothing when there is no such vertex.
     #[export]
-    pub fn set_vertices(&mut self, _owner: TRef<MeshInstance>, vertices: TypedArray<Vector3>) {
-        self.vertices = vertices;
+    pub fn get_vertex(&mut self, _owner: TRef<MeshInstance>, index: i32) -> Option<Vector3> {
+        if index < 0 || index as usize >= self.mesh.vertex_count() {
+            return None;
+        }
+        Some(to_vector3(self.mesh.position(index as VertexId)))
     }
 
     //pub fn set_vertex(&mut self, owner: TRef<MeshInstance>, index: i32, position: Vector3) {
+    /// Moves vertices while they are dragged. The mesh data and collision
+    /// are left as they are until the drag is committed through
+    /// `set_vertex_positions`.
     pub fn set_vertex(&mut self, owner: TRef<MeshInstance>, updated_vertices: HashMap<i32, Vector3>) {
         //self.vertices.set(index, position);
         //self.update_mesh_vertex(owner, index, position);
         self.update_mesh_vertex(owner, updated_vertices);
     }
 
-    pub fn get_vertices_center(&mut self, owner: TRef<MeshInstance>, indices: Vec<i32>) -> Vector3 {
-        let mesh_pos = owner.global_transform().origin;
-        let num_indices = indices.len();
-        let mut x: f32 = 0.0;
-        let mut y: f32 = 0.0;
-        let mut z: f32 = 0.0;
-
-        for index in indices {
-            let vertex = self.vertices.get(index);
-            x += vertex.x;
-            y += vertex.y;
-            z += vertex.z;
+    /// Moves each vertex in `indices` to the position at the same index in
+    /// `positions`, then saves the topology and regenerates the collision.
+    /// This is what committing, undoing and redoing a drag calls.
+    #[export]
+    pub fn set_vertex_positions(&mut self, owner: TRef<MeshInstance>, indices: Vec<i32>, positions: Vec<Vector3>) {
+        if indices.len() != positions.len() {
+            godot_print!("[Prodot Mesh]: Got {} vertex indices but {} positions!", indices.len(), positions.len());
+            return;
+        }
+        if !indices.iter().all(|&index| self.check_vertex_index(index)) {
+            return;
         }
 
+        self.update_mesh_vertex(owner, indices.into_iter().zip(positions).collect());
+        self.store_mesh();
+        self.update_collision(owner);
+    }
 
-        Vector3::new(
-            x / num_indices as f32,
-            y / num_indices as f32,
-            z / num_indices as f32
-        )
+    /// Average position of the given vertices, leaving out indices that
+    /// aren't vertices of the mesh.
+    pub fn get_vertices_center(&mut self, _owner: TRef<MeshInstance>, indices: Vec<i32>) -> Vector3 {
+        let vertices: Vec<VertexId> = indices
+            .into_iter()
+            .filter(|&index| self.check_vertex_index(index))
+            .map(|index| index as VertexId)
+            .collect();
+        to_vector3(self.mesh.vertices_center(&vertices))
     }
 
     //fn update_mesh_vertex(&mut self, owner: TRef<MeshInstance>, index: i32, position: Vector3) {
+    /// Moves vertices and updates only what depends on them: the faces
+    /// around them and the corners of those faces. The changed render
+    /// vertices are written to the cached vertex buffers, and uploaded on
+    /// the next `_process`, which also rebuilds the surfaces if the render
+    /// vertices have to be split.
     fn update_mesh_vertex(&mut self, owner: TRef<MeshInstance>, updated_vertices: HashMap<i32, Vector3>) {
-        //let mesh_pos = owner.global_transform().origin;
-        let mesh_ref = owner.mesh().unwrap();
-        let mesh = unsafe { mesh_ref.assume_safe() };
-        let mesh_array =  mesh.cast::<ArrayMesh>().unwrap();
-        let mesh_tool = MeshDataTool::new();
-        mesh_tool.create_from_surface(mesh_array, 0).expect("[Prodot Mesh]: Failed to create mesh from surface!");
-        
+        let mut moved = Vec::with_capacity(updated_vertices.len());
         for (index, position) in updated_vertices {
-            self.vertices.set(index, position);
-            mesh_tool.set_vertex(index as i64, position);
+            let vertex = index as VertexId;
+            self.mesh.set_position(vertex, to_vec3(position));
+            moved.push(vertex);
+        }
+
+        let mut faces: Vec<FaceId> = moved.iter().flat_map(|&vertex| self.mesh.vertex_faces(vertex)).collect();
+        faces.sort_unstable();
+        faces.dedup();
+        let mut vertices: Vec<VertexId> = faces.iter().flat_map(|&face| self.mesh.face_vertices(face)).collect();
+        vertices.sort_unstable();
+        vertices.dedup();
+
+        self.apply_local_shading(owner, &faces, &vertices);
+        self.needs_store = true;
+
+        // The rebuild waiting for this frame covers these vertices too
+        if self.needs_rebuild {
+            return;
+        }
+
+        let loops: Vec<LoopId> = vertices.iter().flat_map(|&vertex| self.mesh.vertex_loops(vertex)).collect();
+        match self.surfaces.update_loops(&self.mesh, &loops) {
+            Some(updated) => {
+                for (surface, vertex) in updated {
+                    self.buffers[surface].update_vertex(&self.surfaces.surfaces[surface], vertex);
+                }
+            }
+            // Edges turned hard, so the render vertices have to be split.
+            // The collision waits for the drag to be committed
+            None => self.needs_rebuild = true,
         }
-        mesh_array.surface_remove(0);
-        mesh_tool.commit_to_surface(mesh_array).expect("[Prodot Mesh]: Failed to commit mesh array to surface!");
     }
 
+    /// Moves every vertex of the given faces so that each face's center
+    /// ends up at its new position.
     fn update_mesh_face(&mut self, owner: TRef<MeshInstance>, updated_faces: HashMap<i32, Vector3>) {
-        let mesh_ref = owner.mesh().unwrap();
-        let mesh = unsafe { mesh_ref.assume_safe() };
-        let mesh_array =  mesh.cast::<ArrayMesh>().unwrap();
-        let mesh_tool = MeshDataTool::new();
-        mesh_tool.create_from_surface(mesh_array, 0).expect("[Prodot Mesh]: Failed to create mesh from surface!");
-        
-        for (index, _position) in updated_faces {
-        //let indices = Vec::<i32>::new();
-        //indices.push(index);
-        //let center = self.get_face_center(owner, indices);
-            let face = self.faces[index as usize];
-            let vertex_one = self.vertices.get(face.tris_one.x as i32);
-            let vertex_two = self.vertices.get(face.tris_one.y as i32);
-            let vertex_three = self.vertices.get(face.tris_one.z as i32);
-            let vertex_four = self.vertices.get(face.tris_two.x as i32);
-            let vertex_five = self.vertices.get(face.tris_two.y as i32);
-            let vertex_six =  self.vertices.get(face.tris_two.z as i32);
-            
-            self.vertices.set(face.tris_one.x as i32, vertex_one);
-            self.vertices.set(face.tris_one.y as i32, vertex_two);
-            self.vertices.set(face.tris_one.z as i32, vertex_three);
-            self.vertices.set(face.tris_two.x as i32, vertex_four);
-            self.vertices.set(face.tris_two.y as i32, vertex_five);
-            self.vertices.set(face.tris_two.z as i32, vertex_six);
+        let mut updated_vertices: HashMap<i32, Vector3> = HashMap::new();
 
-            //mesh_tool.set_vertex(face.tris_one.x as i64, vertex_one);
-            //mesh_tool.set_vertex(face.tris_one.y as i64, vertex_two);
-            //mesh_tool.set_vertex(face.tris_one.z as i64, vertex_three);
-            //mesh_tool.set_vertex(face.tris_two.x as i64, vertex_four);
-            //mesh_tool.set_vertex(face.tris_two.y as i64, vertex_five);
-            //mesh_tool.set_vertex(face.tris_two.z as i64, vertex_six);
+        for (index, position) in updated_faces {
+            let face = index as FaceId;
+            let delta = to_vec3(position) - self.mesh.face_center(face);
+            for vertex in self.mesh.face_vertices(face) {
+                updated_vertices.insert(vertex as i32, to_vector3(self.mesh.position(vertex) + delta));
+            }
         }
-        mesh_array.surface_remove(0);
-        mesh_tool.commit_to_surface(mesh_array).expect("[Prodot Mesh]: Failed to commit mesh array to surface!");
+
+        self.update_mesh_vertex(owner, updated_vertices);
     }
 
+    /// Material slot of face `index`, or nothing when there is no such face.
     #[export]
-    pub fn get_faces(&mut self, _owner: TRef<MeshInstance>) -> Vec<Face> {
-        self.faces.clone()
+    pub fn get_face_material(&mut self, _owner: TRef<MeshInstance>, index: i32) -> Option<i64> {
+        if !self.check_face_index(index) {
+            return None;
+        }
+        Some(self.mesh.face_material(index as FaceId) as i64)
     }
 
+    /// Puts the given faces in material slot `slot`, growing the materials
+    /// list if the slot is past its end. The slot can be at most one past
+    /// the last slot in use.
     #[export]
-    pub fn set_faces(&mut self, _owner: TRef<MeshInstance>, faces: Vec<Face>) {
-        self.faces = faces;
+    pub fn set_face_material(&mut self, owner: TRef<MeshInstance>, indices: Vec<i32>, slot: i64) {
+        let slots = vec![slot; indices.len()];
+        self.set_face_materials(owner, indices, slots);
+    }
+
+    /// Puts each face in `indices` in the slot at the same index in
+    /// `slots`, which is how undo restores faces that had different slots.
+    #[export]
+    pub fn set_face_materials(&mut self, owner: TRef<MeshInstance>, indices: Vec<i32>, slots: Vec<i64>) {
+        if indices.len() != slots.len() {
+            godot_print!("[Prodot Mesh]: Got {} face indices but {} material slots!", indices.len(), slots.len());
+            return;
+        }
+        if slots.iter().any(|&slot| slot < 0) {
+            godot_print!("[Prodot Mesh]: Material slots can't be negative!");
+            return;
+        }
+        // Faces can go in any existing slot or open the one after the last
+        let slot_count = self.mesh.material_count().max(self.materials.len() as usize) as i64;
+        if let Some(slot) = slots.iter().find(|&&slot| slot > slot_count) {
+            godot_print!("[Prodot Mesh]: Material slot {} is past the next free slot {}!", slot, slot_count);
+            return;
+        }
+        if !indices.iter().all(|&index| self.check_face_index(index)) {
+            return;
+        }
+
+        for (index, slot) in indices.into_iter().zip(slots) {
+            self.mesh.set_face_material(index as FaceId, slot as usize);
+        }
+        if self.materials.len() < self.mesh.material_count() as i32 {
+            // The list is only ever touched from the main thread
+            unsafe { self.materials.resize(self.mesh.material_count() as i32) };
+        }
+
+        self.commit_mesh(owner);
+        self.store_mesh();
+    }
+
+    #[export]
+    pub fn get_face_count(&mut self, _owner: TRef<MeshInstance>) -> i32 {
+        self.mesh.face_count() as i32
+    }
+
+    /// Vertices of face `index` in winding order, or none when there is
+    /// no such face.
+    #[export]
+    pub fn get_face_vertices(&mut self, _owner: TRef<MeshInstance>, index: i32) -> Vec<i32> {
+        if !self.check_face_index(index) {
+            return Vec::new();
+        }
+        self.mesh.face_vertices(index as FaceId).map(|vertex| vertex as i32).collect()
+    }
+
+    /// Whether a face index that came from a script is one of the mesh's
+    /// faces, printing why not.
+    fn check_face_index(&self, index: i32) -> bool {
+        let valid = index >= 0 && (index as usize) < self.mesh.face_count();
+        if !valid {
+            godot_print!("[Prodot Mesh]: There is no face {}, the mesh has {}!", index, self.mesh.face_count());
+        }
+        valid
+    }
+
+    /// Whether a vertex index that came from a script is one of the
+    /// mesh's vertices, printing why not.
+    fn check_vertex_index(&self, index: i32) -> bool {
+        let valid = index >= 0 && (index as usize) < self.mesh.vertex_count();
+        if !valid {
+            godot_print!("[Prodot Mesh]: There is no vertex {}, the mesh has {}!", index, self.mesh.vertex_count());
+        }
+        valid
     }
 
     pub fn set_face(&mut self, owner: TRef<MeshInstance>, updated_faces: HashMap<i32, Vector3>) {
         self.update_mesh_face(owner, updated_faces);
     }
 
+    /// Average center of the given faces, leaving out indices that aren't
+    /// faces of the mesh.
     #[export]
-    pub fn get_face_center(&mut self, owner: TRef<MeshInstance>, indices: Vec<i32>) -> Vector3 {
-        let mut x: f32 = 0.0;
-        let mut y: f32 = 0.0;
-        let mut z: f32 = 0.0;
-        
-        let mesh_pos = owner.global_transform().origin;
-        let num_indices = indices.len();
-        
-        for i in 0..num_indices {
-            let face = self.faces[i];
-            let vertex_one = mesh_pos + self.vertices.get(face.tris_one.x as i32);
-            let vertex_two = mesh_pos + self.vertices.get(face.tris_one.y as i32);
-            let vertex_three = mesh_pos + self.vertices.get(face.tris_one.z as i32);
+    pub fn get_face_center(&mut self, _owner: TRef<MeshInstance>, indices: Vec<i32>) -> Vector3 {
+        let faces: Vec<FaceId> = indices
+            .into_iter()
+            .filter(|&index| self.check_face_index(index))
+            .map(|index| index as FaceId)
+            .collect();
+        to_vector3(self.mesh.faces_center(&faces))
+    }
 
-            let vertex_four = mesh_pos + self.vertices.get(face.tris_two.x as i32);
-            let vertex_five = mesh_pos + self.vertices.get(face.tris_two.y as i32);
-            let vertex_six =  mesh_pos + self.vertices.get(face.tris_two.z as i32);
 
-            x += vertex_one.x + vertex_two.x + vertex_three.x + vertex_four.x + vertex_five.x + vertex_six.x;
-            y += vertex_one.y + vertex_two.y + vertex_three.y + vertex_four.y + vertex_five.y + vertex_six.y;
-            z += vertex_one.z + vertex_two.z + vertex_three.z + vertex_four.z + vertex_five.z + vertex_six.z;
+}
+
+/// An ArrayMesh with one surface per entry of `surfaces`, each with the
+/// material of its slot in `materials` if there is one.
+///
+/// Surfaces are left uncompressed so the cached vertex buffers match
+/// their layout byte for byte.
+pub fn surfaces_mesh(surfaces: &[SurfaceArrays], materials: &[Option<Ref<Material, Shared>>]) -> Ref<ArrayMesh, Unique> {
+    let end_mesh = ArrayMesh::new();
+    for (index, surface) in surfaces.iter().enumerate() {
+        let arrays = VariantArray::new();
+        arrays.resize(Mesh::ARRAY_MAX as i32);
+
+        let vertex_array: TypedArray<Vector3> = surface.positions.iter().map(|position| to_vector3(*position)).collect();
+        let normal_array: TypedArray<Vector3> = surface.normals.iter().map(|normal| to_vector3(*normal)).collect();
+        let tangent_array = to_tangent_array(&surface.tangents);
+        let color_array: TypedArray<Color> = surface.colors.iter().map(|color| to_godot_color(*color)).collect();
+        let uv_array: TypedArray<Vector2> = surface.uvs.iter().map(|uv| to_vector2(*uv)).collect();
+        let index_array: TypedArray<i32> = surface.indices.iter().map(|index| *index as i32).collect();
+
+        arrays.set(Mesh::ARRAY_VERTEX as i32, vertex_array);
+        arrays.set(Mesh::ARRAY_NORMAL as i32, normal_array);
+        arrays.set(Mesh::ARRAY_TANGENT as i32, tangent_array);
+        arrays.set(Mesh::ARRAY_COLOR as i32, color_array);
+        arrays.set(Mesh::ARRAY_TEX_UV as i32, uv_array);
+        arrays.set(Mesh::ARRAY_INDEX as i32, index_array);
+
+        end_mesh.add_surface_from_arrays(Mesh::PRIMITIVE_TRIANGLES, arrays.into_shared(), VariantArray::new_shared(), 0);
+
+        if let Some(Some(material)) = materials.get(surface.material) {
+            end_mesh.surface_set_material(index as i64, material.clone());
         }
+    }
+    end_mesh
+}
 
-        Vector3::new(
-            x / ( 6.0 * num_indices as f32),
-            y / ( 6.0 * num_indices as f32),
-            z / ( 6.0 * num_indices as f32),
-        )
+/// Node that new children have to be owned by to be saved with the scene.
+fn scene_owner(owner: TRef<MeshInstance>) -> Ref<Node, Shared> {
+    owner.owner().unwrap_or_else(|| owner.upcast::<Node>().claim())
+}
 
+/// The StaticBody child holding the collision shapes. It's created when
+/// `create` is set, owned by the scene so it's saved with it.
+fn collision_body(owner: TRef<MeshInstance>, create: bool) -> Option<Ref<StaticBody, Shared>> {
+    if owner.has_node(COLLISION_BODY_NAME) {
+        let node = owner.get_node(COLLISION_BODY_NAME)?;
+        return unsafe { node.assume_safe() }.cast::<StaticBody>().map(|body| body.claim());
+    }
+    if !create {
+        return None;
     }
 
+    let body = StaticBody::new();
+    body.set_name(COLLISION_BODY_NAME);
+    let body = body.into_shared();
+    owner.add_child(body, false);
+    unsafe { body.assume_safe() }.set_owner(scene_owner(owner));
+    Some(body)
+}
+
+/// The body's CollisionShape at `index`, added if the body has fewer.
+fn collision_shape<'a>(owner: TRef<MeshInstance>, body: TRef<'a, StaticBody>, index: i64) -> TRef<'a, CollisionShape> {
+    if index < body.get_child_count() {
+        if let Some(child) = body.get_child(index) {
+            if let Some(shape_node) = unsafe { child.assume_safe() }.cast::<CollisionShape>() {
+                return shape_node;
+            }
+        }
+    }
+
+    let shape_node = CollisionShape::new().into_shared();
+    body.add_child(shape_node, false);
+    let shape_node = unsafe { shape_node.assume_safe() };
+    shape_node.set_owner(scene_owner(owner));
+    shape_node
+}
+
+/// The node's shape if it's already a `T`, otherwise a new one set on it.
+fn node_shape<T, F>(shape_node: TRef<CollisionShape>, new_shape: F) -> Ref<T, Shared>
+where
+    T: GodotObject<RefKind = RefCounted> + SubClass<Shape>,
+    F: FnOnce() -> Ref<T, Unique>,
+{
+    if let Some(shape) = shape_node.shape() {
+        if let Some(shape) = unsafe { shape.assume_safe() }.cast::<T>() {
+            return shape.claim();
+        }
+    }
 
+    let shape = new_shape().into_shared();
+    shape_node.set_shape(shape.clone());
+    shape
 }
@@ -0,0 +1,120 @@
+use gdnative::api::{Resource, Script};
+use gdnative::prelude::*;
+use prodot_core::poly_mesh::MeshArrays;
+
+use crate::prodot_utils::*;
+
+/// Editable geometry of a ProdotMesh. It lives in its own resource so it
+/// can be saved as a .tres/.res file and shared by several ProdotMesh
+/// nodes, which all rebuild when the `changed` signal fires.
+#[derive(NativeClass)]
+#[inherit(Resource)]
+pub struct ProdotMeshData {
+    #[property(no_editor)]
+    vertices: TypedArray::<Vector3>,
+    #[property(no_editor)]
+    face_sizes: TypedArray::<i32>,
+    // Material slot of each face
+    #[property(no_editor)]
+    face_materials: TypedArray::<i32>,
+    #[property(no_editor)]
+    face_indices: TypedArray::<i32>,
+    // Per face corner, in the same order as face_indices
+    #[property(no_editor)]
+    normals: TypedArray::<Vector3>,
+    #[property(no_editor)]
+    uvs: TypedArray::<Vector2>,
+    #[property(no_editor)]
+    colors: TypedArray::<Color>,
+    // 4 floats per corner, laid out like Mesh.ARRAY_TANGENT
+    #[property(no_editor)]
+    tangents: TypedArray::<f32>,
+    // Bumped on every edit so the nodes sharing this data can tell whether
+    // their copy of the topology is stale
+    revision: i64,
+}
+
+#[methods]
+impl ProdotMeshData {
+    fn new(_owner: TRef<Resource>) -> Self {
+        ProdotMeshData {
+            vertices: TypedArray::<Vector3>::new(),
+            face_sizes: TypedArray::<i32>::new(),
+            face_materials: TypedArray::<i32>::new(),
+            face_indices: TypedArray::<i32>::new(),
+            normals: TypedArray::<Vector3>::new(),
+            uvs: TypedArray::<Vector2>::new(),
+            colors: TypedArray::<Color>::new(),
+            tangents: TypedArray::<f32>::new(),
+            revision: 0,
+        }
+    }
+
+    #[export]
+    fn to_string(&self, _owner: TRef<Resource>) -> GodotString {
+        GodotString::from("ProdotMeshData")
+    }
+
+    #[export]
+    pub fn get_revision(&self, _owner: TRef<Resource>) -> i64 {
+        self.revision
+    }
+
+    #[export]
+    pub fn is_empty(&self, _owner: TRef<Resource>) -> bool {
+        self.vertices.is_empty()
+    }
+
+    pub fn arrays(&self) -> MeshArrays {
+        MeshArrays {
+            positions: self.vertices.read().iter().map(|vertex| to_vec3(*vertex)).collect(),
+            face_sizes: self.face_sizes.read().iter().map(|size| *size as usize).collect(),
+            face_materials: self.face_materials.read().iter().map(|slot| *slot as usize).collect(),
+            face_vertices: self.face_indices.read().iter().map(|index| *index as usize).collect(),
+            normals: self.normals.read().iter().map(|normal| to_vec3(*normal)).collect(),
+            uvs: self.uvs.read().iter().map(|uv| to_vec2(*uv)).collect(),
+            colors: self.colors.read().iter().map(|color| to_core_color(*color)).collect(),
+            tangents: from_tangent_array(&self.tangents),
+        }
+    }
+
+    /// Replaces the stored topology and returns the new revision. The
+    /// `changed` signal is deferred so the node making the edit isn't
+    /// called back while it is still borrowed.
+    pub fn set_arrays(&mut self, owner: TRef<Resource>, arrays: MeshArrays) -> i64 {
+        self.vertices = arrays.positions.into_iter().map(to_vector3).collect();
+        self.face_sizes = arrays.face_sizes.into_iter().map(|size| size as i32).collect();
+        self.face_materials = arrays.face_materials.into_iter().map(|slot| slot as i32).collect();
+        self.face_indices = arrays.face_vertices.into_iter().map(|index| index as i32).collect();
+        self.normals = arrays.normals.into_iter().map(to_vector3).collect();
+        self.uvs = arrays.uvs.into_iter().map(to_vector2).collect();
+        self.colors = arrays.colors.into_iter().map(to_godot_color).collect();
+        self.tangents = to_tangent_array(&arrays.tangents);
+        self.revision += 1;
+
+        owner.call_deferred("emit_changed", &[]);
+        self.revision
+    }
+}
+
+/// Creates an empty ProdotMeshData resource.
+pub fn create_mesh_data() -> Ref<Resource, Shared> {
+    let script = unsafe {
+        load_resource::<Script>("res://addons/prodot_builder/prodot_mesh_data.gdns", "Script")
+            .expect("[Prodot Mesh Data]: Failed to load the ProdotMeshData script!")
+    };
+
+    let resource = Resource::new();
+    resource.set_script(script);
+    resource.into_shared()
+}
+
+/// Runs `f` on the ProdotMeshData script instance behind `resource`.
+/// Returns `None` if the resource isn't a ProdotMeshData.
+pub fn map_mesh_data<F, R>(resource: &Ref<Resource, Shared>, f: F) -> Option<R>
+where
+    F: FnOnce(&mut ProdotMeshData, TRef<Resource>) -> R,
+{
+    let resource = unsafe { resource.assume_safe() };
+    resource.cast_instance::<ProdotMeshData>()?.map_mut(f).ok()
+}
@@ -0,0 +1,147 @@
+use gdnative::api::{CheckBox, OptionButton, Range};
+use gdnative::prelude::*;
+
+use prodot_core::math::Vec3;
+use prodot_core::primitives::{
+    BoxParams, Caps, CylinderParams, Facing, IcosphereParams, Pivot, PlaneParams, StairLayout, StairParams,
+    StairSupport, TorusParams, UvSphereParams,
+};
+
+/// Reads the parameter controls under `container` into a dictionary for
+/// the plugin's `create_*` methods. Each SpinBox, CheckBox and
+/// OptionButton is read under its node name, other nodes are skipped.
+pub fn read_dock_params(container: TRef<Node>) -> Dictionary<Unique> {
+    let params = Dictionary::new();
+    for child in container.get_children().iter() {
+        let child = match child.try_to_object::<Node>() {
+            Some(child) => unsafe { child.assume_safe() },
+            None => continue,
+        };
+        let name = child.name().to_string();
+        // CheckBox and OptionButton are both buttons, OptionButton first
+        if let Some(option) = child.cast::<OptionButton>() {
+            params.insert(name, option.get_selected_id());
+        } else if let Some(check) = child.cast::<CheckBox>() {
+            params.insert(name, check.is_pressed());
+        } else if let Some(range) = child.cast::<Range>() {
+            params.insert(name, range.value());
+        }
+    }
+    params
+}
+
+/// Reads `BoxParams` from `width`, `height`, `depth`, `segments_x`,
+/// `segments_y`, `segments_z` and `pivot`. Missing keys keep the default.
+pub fn box_params(params: &Dictionary) -> BoxParams {
+    let default = BoxParams::default();
+    BoxParams {
+        size: Vec3::new(
+            param_f32(params, "width", default.size.x),
+            param_f32(params, "height", default.size.y),
+            param_f32(params, "depth", default.size.z),
+        ),
+        segments: [
+            param_usize(params, "segments_x", default.segments[0]),
+            param_usize(params, "segments_y", default.segments[1]),
+            param_usize(params, "segments_z", default.segments[2]),
+        ],
+        pivot: Pivot::from_index(param_usize(params, "pivot", 0)).unwrap_or(default.pivot),
+    }
+}
+
+/// Reads `PlaneParams` from `width`, `depth`, `segments_x`, `segments_z`
+/// and `facing`. Missing keys keep the default.
+pub fn plane_params(params: &Dictionary) -> PlaneParams {
+    let default = PlaneParams::default();
+    PlaneParams {
+        width: param_f32(params, "width", default.width),
+        depth: param_f32(params, "depth", default.depth),
+        segments: [
+            param_usize(params, "segments_x", default.segments[0]),
+            param_usize(params, "segments_z", default.segments[1]),
+        ],
+        facing: Facing::from_index(param_usize(params, "facing", 0)).unwrap_or(default.facing),
+    }
+}
+
+/// Reads `CylinderParams` from `radius_top`, `radius_bottom`, `height`,
+/// `radial_segments`, `height_segments`, `caps` and `smooth`. Missing
+/// keys keep the ones in `defaults`, so cones can leave out the top.
+pub fn cylinder_params(params: &Dictionary, defaults: CylinderParams) -> CylinderParams {
+    CylinderParams {
+        radius_top: param_f32(params, "radius_top", defaults.radius_top),
+        radius_bottom: param_f32(params, "radius_bottom", defaults.radius_bottom),
+        height: param_f32(params, "height", defaults.height),
+        radial_segments: param_usize(params, "radial_segments", defaults.radial_segments),
+        height_segments: param_usize(params, "height_segments", defaults.height_segments),
+        caps: Caps::from_index(param_usize(params, "caps", 0)).unwrap_or(defaults.caps),
+        smooth: params.get("smooth").try_to_bool().unwrap_or(defaults.smooth),
+    }
+}
+
+/// Reads `UvSphereParams` from `radius`, `segments` and `rings`. Missing
+/// keys keep the default.
+pub fn uv_sphere_params(params: &Dictionary) -> UvSphereParams {
+    let default = UvSphereParams::default();
+    UvSphereParams {
+        radius: param_f32(params, "radius", default.radius),
+        segments: param_usize(params, "segments", default.segments),
+        rings: param_usize(params, "rings", default.rings),
+    }
+}
+
+/// Reads `IcosphereParams` from `radius` and `subdivisions`. Missing keys
+/// keep the default.
+pub fn icosphere_params(params: &Dictionary) -> IcosphereParams {
+    let default = IcosphereParams::default();
+    IcosphereParams {
+        radius: param_f32(params, "radius", default.radius),
+        subdivisions: param_usize(params, "subdivisions", default.subdivisions),
+    }
+}
+
+/// Reads `TorusParams` from `major_radius`, `minor_radius`,
+/// `major_segments` and `minor_segments`. Missing keys keep the default.
+pub fn torus_params(params: &Dictionary) -> TorusParams {
+    let default = TorusParams::default();
+    TorusParams {
+        major_radius: param_f32(params, "major_radius", default.major_radius),
+        minor_radius: param_f32(params, "minor_radius", default.minor_radius),
+        major_segments: param_usize(params, "major_segments", default.major_segments),
+        minor_segments: param_usize(params, "minor_segments", default.minor_segments),
+    }
+}
+
+/// Reads `StairParams` from `steps`, `rise`, `run`, `width`, `layout`,
+/// `support`, `stringer_width`, `stringer_depth` and `inner_radius`.
+/// Missing keys keep the default.
+pub fn stair_params(params: &Dictionary) -> StairParams {
+    let default = StairParams::default();
+    StairParams {
+        steps: param_usize(params, "steps", default.steps),
+        rise: param_f32(params, "rise", default.rise),
+        run: param_f32(params, "run", default.run),
+        width: param_f32(params, "width", default.width),
+        layout: StairLayout::from_index(param_usize(params, "layout", 0)).unwrap_or(default.layout),
+        support: StairSupport::from_index(param_usize(params, "support", 0)).unwrap_or(default.support),
+        stringer_width: param_f32(params, "stringer_width", default.stringer_width),
+        stringer_depth: param_f32(params, "stringer_depth", default.stringer_depth),
+        inner_radius: param_f32(params, "inner_radius", default.inner_radius),
+    }
+}
+
+/// A number from `params`, whether it's stored as a float or an int.
+fn param_f64(params: &Dictionary, key: &str) -> Option<f64> {
+    let value = params.get(key);
+    value.try_to_f64().or_else(|| value.try_to_i64().map(|value| value as f64))
+}
+
+fn param_f32(params: &Dictionary, key: &str, default: f32) -> f32 {
+    param_f64(params, key).map_or(default, |value| value as f32)
+}
+
+/// Counts below zero are read as zero. Counts past the limits in
+/// `prodot_core::primitives` are clamped when the mesh is generated.
+fn param_usize(params: &Dictionary, key: &str, default: usize) -> usize {
+    param_f64(params, key).map_or(default, |value| value.max(0.0).round() as usize)
+}
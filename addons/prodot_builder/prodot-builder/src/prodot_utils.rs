@@ -1,8 +1,8 @@
 use gdnative::api::{PackedScene, Resource, ResourceLoader};
 use gdnative::prelude::*;
 use gdnative::GodotObject;
+use prodot_core::math::{self, Tangent, Vec2, Vec3};
 
-#[allow(dead_code)]
 pub unsafe fn load_resource<T>(path: &str, hint: &str) -> Option<Ref<T, Shared>>
 where
     T: GodotObject<RefKind = RefCounted> + SubClass<Resource>,
@@ -23,3 +23,56 @@ pub unsafe fn load_scene(path: &str) -> Option<Ref<PackedScene>> {
             .claim(),
     )
 }
+
+pub fn to_vec3(vector: Vector3) -> Vec3 {
+    Vec3::new(vector.x, vector.y, vector.z)
+}
+
+pub fn to_vector3(vector: Vec3) -> Vector3 {
+    Vector3::new(vector.x, vector.y, vector.z)
+}
+
+pub fn to_vec2(vector: Vector2) -> Vec2 {
+    Vec2::new(vector.x, vector.y)
+}
+
+pub fn to_vector2(vector: Vec2) -> Vector2 {
+    Vector2::new(vector.x, vector.y)
+}
+
+pub fn to_core_color(color: Color) -> math::Color {
+    math::Color::rgba(color.r, color.g, color.b, color.a)
+}
+
+pub fn to_godot_color(color: math::Color) -> Color {
+    Color::rgba(color.r, color.g, color.b, color.a)
+}
+
+pub fn to_core_transform(transform: Transform) -> math::Transform {
+    math::Transform {
+        x: to_vec3(transform.basis.x()),
+        y: to_vec3(transform.basis.y()),
+        z: to_vec3(transform.basis.z()),
+        origin: to_vec3(transform.origin),
+    }
+}
+
+/// Flattens tangents into the 4 floats per vertex Godot's
+/// `ARRAY_TANGENT` expects.
+pub fn to_tangent_array(tangents: &[Tangent]) -> TypedArray<f32> {
+    tangents
+        .iter()
+        .flat_map(|tangent| {
+            let direction = tangent.direction;
+            [direction.x, direction.y, direction.z, tangent.sign]
+        })
+        .collect()
+}
+
+pub fn from_tangent_array(tangents: &TypedArray<f32>) -> Vec<Tangent> {
+    tangents
+        .read()
+        .chunks_exact(4)
+        .map(|tangent| Tangent::new(Vec3::new(tangent[0], tangent[1], tangent[2]), tangent[3]))
+        .collect()
+}